@@ -15,4 +15,5 @@ pub fn transfer_event_with_contract(contract: Contract<()>) -> Event {
         &contract.name,
         1_i64,
     )
+    .unwrap()
 }
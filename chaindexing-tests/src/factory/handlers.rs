@@ -1,4 +1,7 @@
-use chaindexing::{EventContext, EventHandler};
+use chaindexing::{
+    Address, BlockContext, BlockHandler, CallContext, CallHandler, EventContext, EventHandler,
+    TopicFilter, TransactionContext, TransactionHandler,
+};
 
 #[derive(Clone, Debug)]
 pub struct NftState;
@@ -13,6 +16,16 @@ impl EventHandler for TransferTestHandler {
     async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
 }
 
+pub struct Erc20TransferTestHandler;
+
+#[chaindexing::augmenting_std::async_trait]
+impl EventHandler for Erc20TransferTestHandler {
+    fn abi(&self) -> &'static str {
+        "event Transfer(address indexed from, address indexed to, uint256 value)"
+    }
+    async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
+}
+
 pub struct ApprovalForAllTestHandler;
 
 #[chaindexing::augmenting_std::async_trait]
@@ -22,3 +35,47 @@ impl EventHandler for ApprovalForAllTestHandler {
     }
     async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
 }
+
+pub const VAULT_ADDRESS: &str = "0xb518b3136e491101f22b77f385fe22269c515188";
+
+pub struct TransferToVaultTestHandler;
+
+#[chaindexing::augmenting_std::async_trait]
+impl EventHandler for TransferToVaultTestHandler {
+    fn abi(&self) -> &'static str {
+        "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)"
+    }
+    fn topic_filter(&self) -> Option<TopicFilter> {
+        let vault: Address = VAULT_ADDRESS.parse().unwrap();
+
+        Some(TopicFilter::new().with_values("to", [vault]))
+    }
+    async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
+}
+
+pub struct SetApprovalForAllTestHandler;
+
+#[chaindexing::augmenting_std::async_trait]
+impl TransactionHandler for SetApprovalForAllTestHandler {
+    fn abi(&self) -> &'static str {
+        "function setApprovalForAll(address operator, bool approved)"
+    }
+    async fn handle_transaction<'a>(&self, _context: TransactionContext<'a>) {}
+}
+
+pub struct SetApprovalForAllCallTestHandler;
+
+#[chaindexing::augmenting_std::async_trait]
+impl CallHandler for SetApprovalForAllCallTestHandler {
+    fn abi(&self) -> &'static str {
+        "function setApprovalForAll(address operator, bool approved)"
+    }
+    async fn handle_call<'a>(&self, _context: CallContext<'a>) {}
+}
+
+pub struct SnapshotTestHandler;
+
+#[chaindexing::augmenting_std::async_trait]
+impl BlockHandler for SnapshotTestHandler {
+    async fn handle_block<'a, 'b>(&self, _context: BlockContext<'a, 'b>) {}
+}
@@ -58,6 +58,86 @@ pub fn transfer_log(contract_address: &str) -> Log {
     }
 }
 
+/// Shares `transfer_log`'s signature, but indexes one parameter less
+pub fn erc20_transfer_log(contract_address: &str) -> Log {
+    let transfer_log = transfer_log(contract_address);
+
+    Log {
+        topics: transfer_log.topics[..3].to_vec(),
+        data: Bytes::from_str(&format!("{:0>64}", "67d")).unwrap(),
+        ..transfer_log
+    }
+}
+
+pub const SET_APPROVAL_FOR_ALL_OPERATOR: &str = "0x1e0049783f008a0085193e00003d00cd54003c71";
+
+pub fn set_approval_for_all_transaction(
+    contract_address: &str,
+    block_number: u64,
+) -> ethers::types::Transaction {
+    ethers::types::Transaction {
+        hash: h256("0x1b3f4a1a26c1a0b9d07b0e6fb1b9fdc8e5b1f60f6fbc8c3e8c5a2fce0a66cf0d"),
+        block_hash: Some(H256::from_low_u64_be(block_number)),
+        block_number: Some(block_number.into()),
+        transaction_index: Some(12.into()),
+        from: H160::from_str("0xb518b3136e491101f22b77f385fe22269c515188").unwrap(),
+        to: Some(H160::from_str(contract_address).unwrap()),
+        input: Bytes::from_str(&format!(
+            "0xa22cb465{:0>64}{:0>64}",
+            &SET_APPROVAL_FOR_ALL_OPERATOR[2..],
+            1
+        ))
+        .unwrap(),
+        ..Default::default()
+    }
+}
+
+/// An internal call to `setApprovalForAll` followed by a native transfer,
+/// both made by another contract to the contract address
+pub fn set_approval_for_all_call_traces(
+    contract_address: &str,
+    block_number: u64,
+) -> Vec<ethers::types::Trace> {
+    use ethers::types::{Action, ActionType, Call, CallType, Trace, U256};
+
+    let call_trace = |trace_address: Vec<usize>, value: u64, input: &str| Trace {
+        action: Action::Call(Call {
+            from: H160::from_str(VAULT_CALLER_ADDRESS).unwrap(),
+            to: H160::from_str(contract_address).unwrap(),
+            value: U256::from(value),
+            input: Bytes::from_str(input).unwrap(),
+            call_type: CallType::Call,
+            ..Default::default()
+        }),
+        result: None,
+        trace_address,
+        subtraces: 0,
+        transaction_position: Some(12),
+        transaction_hash: Some(h256(
+            "0x5a4b0c1ac8a1e5e8f6bc1f39e7a7f3c0d6e2e0f1d6f2c3b4a5968778695a4b3c",
+        )),
+        block_number,
+        block_hash: H256::from_low_u64_be(block_number),
+        action_type: ActionType::Call,
+        error: None,
+    };
+
+    vec![
+        call_trace(
+            vec![0],
+            0,
+            &format!(
+                "0xa22cb465{:0>64}{:0>64}",
+                &SET_APPROVAL_FOR_ALL_OPERATOR[2..],
+                1
+            ),
+        ),
+        call_trace(vec![1], 10, "0x"),
+    ]
+}
+
+const VAULT_CALLER_ADDRESS: &str = "0x7dfd6013cf8d92b751e63d481b51fe0e4c5abf5e";
+
 fn h256(str: &str) -> H256 {
     H256::from_str(str).unwrap()
 }
@@ -92,6 +172,7 @@ macro_rules! provider_with_logs {
             async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
                 Ok(Block {
                     number: Some(block_number),
+                    hash: transfer_log(&self.contract_address).block_hash,
                     ..Default::default()
                 })
             }
@@ -106,6 +187,11 @@ macro_rules! provider_with_logs {
 #[macro_export]
 macro_rules! provider_with_filter_stubber {
     ($contract_address:expr, $filter_stubber: expr) => {{
+        use $crate::provider_with_filter_stubber;
+
+        provider_with_filter_stubber!($contract_address, $filter_stubber, 3)
+    }};
+    ($contract_address:expr, $filter_stubber: expr, $current_block_number:expr) => {{
         use chaindexing::IngesterProvider;
         use ethers::providers::ProviderError;
         use ethers::types::{Block, Filter, Log, TxHash, U64};
@@ -115,7 +201,7 @@ macro_rules! provider_with_filter_stubber {
         #[chaindexing::augmenting_std::async_trait]
         impl IngesterProvider for Provider {
             async fn get_block_number(&self) -> Result<U64, ProviderError> {
-                Ok(U64::from(3))
+                Ok(U64::from($current_block_number))
             }
 
             async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
@@ -168,3 +254,163 @@ macro_rules! provider_with_empty_logs {
         Provider
     }};
 }
+
+#[macro_export]
+macro_rules! provider_with_block_range_limit {
+    ($current_block_number:expr, $max_blocks_count:expr) => {{
+        use chaindexing::IngesterProvider;
+        use ethers::providers::ProviderError;
+        use ethers::types::{Block, Filter, Log, TxHash, U64};
+
+        #[derive(Clone)]
+        struct Provider;
+        #[chaindexing::augmenting_std::async_trait]
+        impl IngesterProvider for Provider {
+            async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                Ok(U64::from($current_block_number))
+            }
+
+            async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                let from_block_number = filter.get_from_block().unwrap().as_u64();
+                let to_block_number = filter.get_to_block().unwrap().as_u64();
+
+                if to_block_number - from_block_number > $max_blocks_count {
+                    Err(ProviderError::CustomError(
+                        "query returned more than 10000 results".to_string(),
+                    ))
+                } else {
+                    Ok(vec![])
+                }
+            }
+
+            async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+                Ok(Block {
+                    number: Some(block_number),
+                    ..Default::default()
+                })
+            }
+        }
+
+        Provider
+    }};
+}
+
+#[macro_export]
+macro_rules! provider_with_transactions {
+    ($contract_address:expr, $transaction_block_number:expr, $current_block_number:expr) => {{
+        use chaindexing::IngesterProvider;
+        use ethers::providers::ProviderError;
+        use ethers::types::{
+            Block, Filter, Log, Transaction, TransactionReceipt, TxHash, H256, U256, U64,
+        };
+        use $crate::factory::set_approval_for_all_transaction;
+
+        #[derive(Clone)]
+        struct Provider {
+            contract_address: String,
+        }
+        #[chaindexing::augmenting_std::async_trait]
+        impl IngesterProvider for Provider {
+            async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                Ok(U64::from($current_block_number))
+            }
+
+            async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                Ok(vec![])
+            }
+
+            async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+                Ok(Block {
+                    number: Some(block_number),
+                    hash: Some(H256::from_low_u64_be(block_number.as_u64())),
+                    ..Default::default()
+                })
+            }
+
+            async fn get_block_with_transactions(
+                &self,
+                block_number: U64,
+            ) -> Result<Block<Transaction>, ProviderError> {
+                let transactions = if block_number == U64::from($transaction_block_number) {
+                    vec![set_approval_for_all_transaction(
+                        &self.contract_address,
+                        block_number.as_u64(),
+                    )]
+                } else {
+                    vec![]
+                };
+
+                Ok(Block {
+                    number: Some(block_number),
+                    hash: Some(H256::from_low_u64_be(block_number.as_u64())),
+                    timestamp: U256::from(1690000000),
+                    transactions,
+                    ..Default::default()
+                })
+            }
+
+            async fn get_transaction_receipt(
+                &self,
+                transaction_hash: TxHash,
+            ) -> Result<TransactionReceipt, ProviderError> {
+                Ok(TransactionReceipt {
+                    transaction_hash,
+                    gas_used: Some(U256::from(46_000)),
+                    status: Some(U64::one()),
+                    ..Default::default()
+                })
+            }
+        }
+
+        Provider {
+            contract_address: $contract_address.to_string(),
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! provider_with_call_traces {
+    ($contract_address:expr, $trace_block_number:expr, $current_block_number:expr) => {{
+        use chaindexing::IngesterProvider;
+        use ethers::providers::ProviderError;
+        use ethers::types::{Block, Filter, Log, Trace, TraceFilter, TxHash, H256, U64};
+        use $crate::factory::set_approval_for_all_call_traces;
+
+        #[derive(Clone)]
+        struct Provider {
+            contract_address: String,
+        }
+        #[chaindexing::augmenting_std::async_trait]
+        impl IngesterProvider for Provider {
+            async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                Ok(U64::from($current_block_number))
+            }
+
+            async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                Ok(vec![])
+            }
+
+            async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+                Ok(Block {
+                    number: Some(block_number),
+                    hash: Some(H256::from_low_u64_be(block_number.as_u64())),
+                    ..Default::default()
+                })
+            }
+
+            async fn trace_filter(
+                &self,
+                _filter: &TraceFilter,
+            ) -> Result<Vec<Trace>, ProviderError> {
+                Ok(set_approval_for_all_call_traces(
+                    &self.contract_address,
+                    $trace_block_number,
+                ))
+            }
+        }
+
+        Provider {
+            contract_address: $contract_address.to_string(),
+        }
+    }};
+}
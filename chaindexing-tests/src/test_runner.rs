@@ -60,11 +60,16 @@ fn should_setup_test_db() -> bool {
     env::var("SETUP_TEST_DB").is_ok()
 }
 
-const ALL_TABLE_NAMES: [&str; 5] = [
+const ALL_TABLE_NAMES: [&str; 10] = [
+    "chaindexing_block_handler_cursors",
+    "chaindexing_block_hashes",
+    "chaindexing_blocks",
+    "chaindexing_call_traces",
     "chaindexing_contract_addresses",
     "chaindexing_events",
     "chaindexing_reorged_blocks",
     "chaindexing_root_states",
+    "chaindexing_transactions",
     "nfts",
 ];
 
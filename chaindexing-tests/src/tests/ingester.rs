@@ -1,19 +1,29 @@
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use tokio::sync::Mutex;
 
     use crate::db::database_url;
-    use crate::factory::{bayc_contract, empty_provider, BAYC_CONTRACT_START_BLOCK_NUMBER};
+    use crate::factory::{
+        bayc_contract, empty_provider, erc20_transfer_log, transfer_log, ApprovalForAllTestHandler,
+        Erc20TransferTestHandler, SetApprovalForAllCallTestHandler, SetApprovalForAllTestHandler,
+        SnapshotTestHandler, TransferTestHandler, TransferToVaultTestHandler,
+        BAYC_CONTRACT_START_BLOCK_NUMBER, SET_APPROVAL_FOR_ALL_OPERATOR, VAULT_ADDRESS,
+    };
     use crate::{
-        find_contract_address_by_contract_name, provider_with_empty_logs,
-        provider_with_filter_stubber, provider_with_logs, test_runner,
+        find_contract_address_by_contract_name, provider_with_block_range_limit,
+        provider_with_call_traces, provider_with_empty_logs, provider_with_filter_stubber,
+        provider_with_logs, provider_with_transactions, test_runner,
     };
+    use chaindexing::ingester::{AdaptiveBlocksPerBatch, IngesterError};
     use chaindexing::{
-        ingester, ChainId, ChaindexingRepo, Config, ExecutesWithRawQuery, HasRawQueryClient,
-        PostgresRepo, Repo,
+        ingester, Address, ArchiveProvider, Chain, ChainId, ChaindexingRepo, Config, Contract,
+        ContractAddressStatus, EventHandler, ExecutesWithRawQuery, HasRawQueryClient, PostgresRepo,
+        RecordingProvider, ReorgDetection, ReplayProvider, Repo, TraceMode,
     };
+    use ethers::types::{Block, Log, TxHash, ValueOrArray, H256};
 
     #[tokio::test]
     pub async fn creates_contract_events() {
@@ -43,6 +53,7 @@ mod tests {
                 &repo_client,
                 &config,
                 &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
             )
             .await
             .unwrap();
@@ -59,12 +70,104 @@ mod tests {
     }
 
     #[tokio::test]
-    pub async fn starts_from_start_block_number() {
+    pub async fn does_not_create_events_again_when_reingesting_a_batch() {
         let pool = test_runner::get_pool().await;
 
         test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Filter, U64};
+
             let repo_client = test_runner::new_repo().get_client().await;
-            let bayc_contract = bayc_contract("BoredApeYachtClub-10", "02");
+            let bayc_contract = bayc_contract("BoredApeYachtClub-27", "25");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            /// Returns the same logs on every request, unlike `provider_with_logs!`
+            #[derive(Clone)]
+            struct Provider {
+                logs: Vec<Log>,
+            }
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for Provider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(BAYC_CONTRACT_START_BLOCK_NUMBER + 20))
+                }
+
+                async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    Ok(self.logs.clone())
+                }
+
+                async fn get_block(
+                    &self,
+                    block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    Ok(Block {
+                        number: Some(block_number),
+                        hash: self.logs[0].block_hash,
+                        ..Default::default()
+                    })
+                }
+            }
+
+            let provider = Arc::new(Provider {
+                logs: vec![transfer_log(&bayc_contract.addresses[0].address)],
+            });
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            let mut events_counts = vec![];
+
+            // Next block numbers only get updated within the test's transaction,
+            // so the same batch gets ingested again
+            for _ in 0..2 {
+                ingester::ingest_for_chain(
+                    &ChainId::Mainnet,
+                    provider.clone(),
+                    conn.clone(),
+                    &repo_client,
+                    &config,
+                    &mut HashMap::new(),
+                    &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+                )
+                .await
+                .unwrap();
+
+                let mut conn = conn.lock().await;
+                events_counts.push(ChaindexingRepo::get_all_events(&mut conn).await.len());
+            }
+
+            assert_eq!(events_counts, vec![1, 1]);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn fetches_logs_of_contract_addresses_together() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            static GET_LOGS_CALLS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            // Other tests' contract addresses are streamed in chunks alongside
+            // Mainnet's, so a chain of their own keeps both addresses together
+            let bayc_contract = Contract::<()>::new("BoredApeYachtClub-14")
+                .add_event_handler(TransferTestHandler)
+                .add_event_handler(ApprovalForAllTestHandler)
+                .add_address(
+                    "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f12D",
+                    &ChainId::Polygon,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                )
+                .add_address(
+                    "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f12E",
+                    &ChainId::Polygon,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
             let config =
                 Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
 
@@ -73,13 +176,327 @@ mod tests {
             let provider = Arc::new(provider_with_filter_stubber!(
                 BAYC_CONTRACT_ADDRESS,
                 |filter: &Filter| {
-                    assert_eq!(
-                        filter.get_from_block().unwrap().as_u32(),
-                        BAYC_CONTRACT_START_BLOCK_NUMBER
-                    );
-                }
+                    GET_LOGS_CALLS_COUNT.fetch_add(1, Ordering::SeqCst);
+
+                    match filter.address.as_ref().unwrap() {
+                        ValueOrArray::Array(addresses) => assert_eq!(addresses.len(), 2),
+                        ValueOrArray::Value(_) => {
+                            panic!("Expected addresses to be fetched together")
+                        }
+                    }
+                },
+                BAYC_CONTRACT_START_BLOCK_NUMBER + 20
+            ));
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Polygon,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            // Once for ingesting and once for confirming the ingested blocks
+            assert_eq!(GET_LOGS_CALLS_COUNT.load(Ordering::SeqCst), 2);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn fetches_logs_by_topic_filters_of_handlers() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            static FILTERED_GET_LOGS_CALLS_COUNT: AtomicUsize = AtomicUsize::new(0);
+            static UNFILTERED_GET_LOGS_CALLS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = Contract::<()>::new("BoredApeYachtClub-15")
+                .add_event_handler(TransferToVaultTestHandler)
+                .add_event_handler(ApprovalForAllTestHandler)
+                .add_address(
+                    "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f14D",
+                    &ChainId::Mainnet,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            let provider = Arc::new(provider_with_filter_stubber!(
+                BAYC_CONTRACT_ADDRESS,
+                |filter: &Filter| {
+                    let vault: Address = VAULT_ADDRESS.parse().unwrap();
+
+                    match &filter.topics[2] {
+                        Some(ValueOrArray::Array(tos)) => {
+                            assert_eq!(tos, &vec![Some(H256::from(vault))]);
+                            FILTERED_GET_LOGS_CALLS_COUNT.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Some(ValueOrArray::Value(_)) => panic!("Expected vault as an array"),
+                        None => {
+                            UNFILTERED_GET_LOGS_CALLS_COUNT.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                },
+                BAYC_CONTRACT_START_BLOCK_NUMBER + 20
+            ));
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            // Once for ingesting and once for confirming the ingested blocks
+            assert_eq!(FILTERED_GET_LOGS_CALLS_COUNT.load(Ordering::SeqCst), 2);
+            assert_eq!(UNFILTERED_GET_LOGS_CALLS_COUNT.load(Ordering::SeqCst), 2);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn creates_transactions_sent_to_contract_addresses() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let contract_address = "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f15D";
+            let bayc_contract = Contract::<()>::new("BoredApeYachtClub-16")
+                .add_transaction_handler(SetApprovalForAllTestHandler)
+                .add_address(
+                    contract_address,
+                    &ChainId::Mainnet,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            let provider = Arc::new(provider_with_transactions!(
+                contract_address,
+                BAYC_CONTRACT_START_BLOCK_NUMBER + 1,
+                BAYC_CONTRACT_START_BLOCK_NUMBER + 2
+            ));
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let transactions = ChaindexingRepo::get_all_transactions(&mut conn).await;
+            let transaction = transactions.first().unwrap();
+
+            // Confirming the ingested blocks must not ingest it again
+            assert_eq!(transactions.len(), 1);
+            assert_eq!(
+                transaction.get_block_number(),
+                BAYC_CONTRACT_START_BLOCK_NUMBER as u64 + 1
+            );
+            assert_eq!(
+                transaction.get_params().get_address("operator"),
+                SET_APPROVAL_FOR_ALL_OPERATOR.parse::<Address>().unwrap()
+            );
+            assert_eq!(transaction.get_gas_used(), 46_000);
+            assert_eq!(transaction.is_successful(), Some(true));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn creates_call_traces_of_calls_to_contract_addresses() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let contract_address = "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f16D";
+            let bayc_contract = Contract::<()>::new("BoredApeYachtClub-17")
+                .add_call_handler(SetApprovalForAllCallTestHandler)
+                .add_address(
+                    contract_address,
+                    &ChainId::Mainnet,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_chain(
+                    Chain::new(ChainId::Mainnet, "http://localhost:8545")
+                        .with_trace_mode(TraceMode::TraceFilter),
+                )
+                .add_contract(bayc_contract.clone());
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            let provider = Arc::new(provider_with_call_traces!(
+                contract_address,
+                BAYC_CONTRACT_START_BLOCK_NUMBER as u64 + 1,
+                BAYC_CONTRACT_START_BLOCK_NUMBER + 2
+            ));
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let mut call_traces = ChaindexingRepo::get_all_call_traces(&mut conn).await;
+            call_traces.sort_by_key(|c| c.get_trace_address());
+
+            // Confirming the ingested blocks must not ingest them again
+            assert_eq!(call_traces.len(), 2);
+            let (handled_call, native_transfer) = (&call_traces[0], &call_traces[1]);
+
+            assert_eq!(handled_call.get_trace_address(), vec![0]);
+            assert_eq!(
+                handled_call.get_params().unwrap().get_address("operator"),
+                SET_APPROVAL_FOR_ALL_OPERATOR.parse::<Address>().unwrap()
+            );
+            assert!(native_transfer.get_params().is_none());
+            assert_eq!(native_transfer.get_value(), 10.into());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn creates_contract_events_from_archive_files() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-19", "18");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            let contract_address = bayc_contract.addresses.first().cloned().unwrap();
+            let contract_address = &contract_address.address;
+            let archived_block_number = BAYC_CONTRACT_START_BLOCK_NUMBER as u64 + 10;
+            let archived_log = Log {
+                block_number: Some(archived_block_number.into()),
+                ..transfer_log(contract_address)
+            };
+            let archived_block = Block::<TxHash> {
+                number: archived_log.block_number,
+                hash: archived_log.block_hash,
+                timestamp: 1690000000.into(),
+                ..Default::default()
+            };
+
+            let archive_dir = std::env::temp_dir().join(format!("archive-{}", H256::random()));
+            std::fs::create_dir_all(archive_dir.join("logs")).unwrap();
+            std::fs::create_dir_all(archive_dir.join("blocks")).unwrap();
+            std::fs::write(
+                archive_dir.join(format!(
+                    "logs/{BAYC_CONTRACT_START_BLOCK_NUMBER}-{archived_block_number}.jsonl"
+                )),
+                serde_json::to_string(&archived_log).unwrap(),
+            )
+            .unwrap();
+            std::fs::write(
+                archive_dir.join(format!(
+                    "blocks/{archived_block_number}-{archived_block_number}.jsonl"
+                )),
+                serde_json::to_string(&archived_block).unwrap(),
+            )
+            .unwrap();
+            let provider = Arc::new(ArchiveProvider::new(&archive_dir));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+            std::fs::remove_dir_all(archive_dir).unwrap();
+
+            let mut conn = conn.lock().await;
+            let ingested_events = ChaindexingRepo::get_all_events(&mut conn).await;
+            assert_eq!(ingested_events.len(), 1);
+            assert_eq!(ingested_events[0].get_block_number(), archived_block_number);
+            assert_eq!(ingested_events[0].get_block_timestamp(), 1690000000);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn creates_contract_events_from_recordings() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-20", "19");
+            let contract_address = bayc_contract.addresses.first().cloned().unwrap();
+            let contract_address = &contract_address.address;
+            // Replays on a chain of its own since the recorded chain is already ingested
+            let replayed_bayc_contract = Contract::<()>::new("BoredApeYachtClub-20")
+                .add_event_handler(TransferTestHandler)
+                .add_address(
+                    contract_address,
+                    &ChainId::Optimism,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(bayc_contract.clone())
+                .add_contract(replayed_bayc_contract.clone());
+
+            let recording_path =
+                std::env::temp_dir().join(format!("recording-{}.jsonl", H256::random()));
+            let provider = Arc::new(RecordingProvider::new(
+                provider_with_logs!(&contract_address),
+                &recording_path,
             ));
 
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            ChaindexingRepo::create_contract_addresses(
+                &repo_client,
+                &replayed_bayc_contract.addresses,
+            )
+            .await;
+
             let conn = Arc::new(Mutex::new(conn));
             let repo_client = Arc::new(Mutex::new(repo_client));
             ingester::ingest_for_chain(
@@ -89,9 +506,842 @@ mod tests {
                 &repo_client,
                 &config,
                 &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
             )
             .await
             .unwrap();
+
+            let provider = Arc::new(ReplayProvider::new(&recording_path));
+            ingester::ingest_for_chain(
+                &ChainId::Optimism,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+            std::fs::remove_file(recording_path).unwrap();
+
+            let mut conn = conn.lock().await;
+            let ingested_events = ChaindexingRepo::get_all_events(&mut conn).await;
+            let (recorded_events, replayed_events): (Vec<_>, Vec<_>) = ingested_events
+                .iter()
+                .partition(|event| event.get_chain_id() == ChainId::Mainnet);
+            assert_eq!(recorded_events.len(), 1);
+            assert_eq!(replayed_events.len(), 1);
+            assert_eq!(
+                replayed_events[0].get_block_number(),
+                recorded_events[0].get_block_number()
+            );
+            assert_eq!(replayed_events[0].block_hash, recorded_events[0].block_hash);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn reingests_contract_events_from_fork_points_of_tracked_block_hashes() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Filter, U64};
+            use std::sync::atomic::{AtomicBool, AtomicU64};
+
+            const FORK_BLOCK_NUMBER: u64 = 18115950;
+
+            #[derive(Clone)]
+            struct ForkingProvider {
+                contract_address: String,
+                current_block_number: Arc<AtomicU64>,
+                is_forked: Arc<AtomicBool>,
+            }
+            impl ForkingProvider {
+                fn get_block_hash(&self, block_number: u64) -> H256 {
+                    if self.is_forked.load(Ordering::SeqCst) && block_number >= FORK_BLOCK_NUMBER {
+                        H256::from_low_u64_be(block_number + 1_000_000_000)
+                    } else {
+                        H256::from_low_u64_be(block_number)
+                    }
+                }
+            }
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for ForkingProvider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(self.current_block_number.load(Ordering::SeqCst)))
+                }
+
+                async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    let log = transfer_log(&self.contract_address);
+                    let block_number = log.block_number.unwrap().as_u64();
+
+                    Ok(vec![Log {
+                        block_hash: Some(self.get_block_hash(block_number)),
+                        ..log
+                    }])
+                }
+
+                async fn get_block(
+                    &self,
+                    block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    let block_number = block_number.as_u64();
+
+                    Ok(Block {
+                        number: Some(block_number.into()),
+                        hash: Some(self.get_block_hash(block_number)),
+                        parent_hash: self.get_block_hash(block_number - 1),
+                        ..Default::default()
+                    })
+                }
+            }
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let contract_address = "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f08D";
+            let bayc_contract = Contract::<()>::new("BoredApeYachtClub-21")
+                .add_event_handler(TransferTestHandler)
+                .add_address(
+                    contract_address,
+                    &ChainId::Sepolia,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_chain(
+                    Chain::new(ChainId::Sepolia, "http://localhost:8545")
+                        .with_reorg_detection(ReorgDetection::ParentHashes),
+                )
+                .add_contract(bayc_contract.clone());
+
+            let transfer_log_block_number =
+                transfer_log(contract_address).block_number.unwrap().as_u64();
+            let provider = Arc::new(ForkingProvider {
+                contract_address: contract_address.to_string(),
+                current_block_number: Arc::new(AtomicU64::new(transfer_log_block_number + 2)),
+                is_forked: Arc::new(AtomicBool::new(false)),
+            });
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Sepolia,
+                provider.clone(),
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            provider.is_forked.store(true, Ordering::SeqCst);
+            provider.current_block_number.fetch_add(1, Ordering::SeqCst);
+            ingester::ingest_for_chain(
+                &ChainId::Sepolia,
+                provider.clone(),
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let ingested_events: Vec<_> = ChaindexingRepo::get_all_events(&mut conn)
+                .await
+                .into_iter()
+                .filter(|event| event.get_chain_id() == ChainId::Sepolia)
+                .collect();
+            assert_eq!(ingested_events.len(), 1);
+            assert_eq!(
+                ingested_events[0].block_hash,
+                format!("{:?}", provider.get_block_hash(transfer_log_block_number))
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn caches_blocks_of_contract_events() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-13", "11");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            static CURRENT_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 20;
+            let contract_address = bayc_contract.addresses.first().cloned().unwrap();
+            let contract_address = &contract_address.address;
+            let provider = Arc::new(provider_with_logs!(&contract_address, CURRENT_BLOCK_NUMBER));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let ingested_event = ChaindexingRepo::get_all_events(&mut conn).await.pop().unwrap();
+            let cached_blocks = ChaindexingRepo::get_blocks(
+                &mut conn,
+                ChainId::Mainnet as i64,
+                &[ingested_event.get_block_number() as i64],
+            )
+            .await;
+            assert_eq!(cached_blocks.len(), 1);
+            assert_eq!(cached_blocks[0].hash, ingested_event.block_hash);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn caches_blocks_handled_by_block_handlers() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-18", "17");
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(bayc_contract.clone())
+                .add_block_handler(&ChainId::Mainnet, 5, SnapshotTestHandler);
+
+            static CURRENT_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 20;
+            let contract_address = bayc_contract.addresses.first().cloned().unwrap();
+            let contract_address = &contract_address.address;
+            let provider = Arc::new(provider_with_logs!(&contract_address, CURRENT_BLOCK_NUMBER));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let block_numbers: Vec<_> = (BAYC_CONTRACT_START_BLOCK_NUMBER..=CURRENT_BLOCK_NUMBER)
+                .map(|n| n as i64)
+                .collect();
+            let cached_blocks =
+                ChaindexingRepo::get_blocks(&mut conn, ChainId::Mainnet as i64, &block_numbers)
+                    .await;
+            let mut cached_block_numbers: Vec<_> =
+                cached_blocks.iter().map(|b| b.number).filter(|n| n % 5 == 0).collect();
+            cached_block_numbers.sort();
+
+            assert_eq!(
+                cached_block_numbers,
+                vec![17773490, 17773495, 17773500, 17773505, 17773510]
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn starts_from_start_block_number() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-10", "02");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            let provider = Arc::new(provider_with_filter_stubber!(
+                BAYC_CONTRACT_ADDRESS,
+                |filter: &Filter| {
+                    assert_eq!(
+                        filter.get_from_block().unwrap().as_u32(),
+                        BAYC_CONTRACT_START_BLOCK_NUMBER
+                    );
+                }
+            ));
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn narrows_blocks_per_batch_when_provider_rejects_block_range() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-12", "10");
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(bayc_contract.clone())
+                .with_blocks_per_batch(20);
+
+            static CURRENT_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 40;
+            let provider = Arc::new(provider_with_block_range_limit!(CURRENT_BLOCK_NUMBER, 5));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            let mut blocks_per_batch = AdaptiveBlocksPerBatch::new(config.blocks_per_batch);
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut blocks_per_batch,
+            )
+            .await
+            .unwrap();
+
+            let bayc_contract_address = find_contract_address_by_contract_name(
+                &repo_client,
+                "BoredApeYachtClub-12",
+                &ChainId::Mainnet,
+            )
+            .await
+            .unwrap();
+            assert!(blocks_per_batch.get(bayc_contract_address.id) <= 5);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn returns_fatal_provider_errors_without_retrying() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Filter, U64};
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-22", "20");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            #[derive(Clone)]
+            struct UnauthorizedProvider;
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for UnauthorizedProvider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(BAYC_CONTRACT_START_BLOCK_NUMBER + 20))
+                }
+
+                async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    Err(ProviderError::CustomError("Invalid API key".to_string()))
+                }
+
+                async fn get_block(
+                    &self,
+                    _block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    Err(ProviderError::CustomError("Invalid API key".to_string()))
+                }
+            }
+
+            let provider = Arc::new(UnauthorizedProvider);
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            let result = ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await;
+
+            assert!(matches!(result, Err(IngesterError::ProviderError(_))));
+            let mut conn = conn.lock().await;
+            assert!(ChaindexingRepo::get_all_events(&mut conn).await.is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn decodes_logs_by_contract_and_quarantines_undecodable_ones() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Bytes, Filter, U64};
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-23", "21");
+            let weth_contract =
+                Contract::new("WETH-1").add_event_handler(Erc20TransferTestHandler).add_address(
+                    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756C21",
+                    &ChainId::Mainnet,
+                    BAYC_CONTRACT_START_BLOCK_NUMBER as u64,
+                );
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(bayc_contract.clone())
+                .add_contract(weth_contract.clone());
+
+            #[derive(Clone)]
+            struct Provider {
+                logs: Vec<Log>,
+            }
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for Provider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(BAYC_CONTRACT_START_BLOCK_NUMBER + 20))
+                }
+
+                async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    let addresses = match filter.address.clone().unwrap() {
+                        ValueOrArray::Value(address) => vec![address],
+                        ValueOrArray::Array(addresses) => addresses,
+                    };
+
+                    Ok(self
+                        .logs
+                        .iter()
+                        .filter(|log| addresses.contains(&log.address))
+                        .cloned()
+                        .collect())
+                }
+
+                async fn get_block(
+                    &self,
+                    block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    Ok(Block {
+                        number: Some(block_number),
+                        hash: self.logs[0].block_hash,
+                        ..Default::default()
+                    })
+                }
+            }
+
+            let bayc_contract_address = &bayc_contract.addresses[0].address;
+            let weth_contract_address = &weth_contract.addresses[0].address;
+            let bayc_transfer_log = transfer_log(bayc_contract_address);
+            let log_index = bayc_transfer_log.log_index.unwrap();
+            let weth_transfer_log = Log {
+                log_index: Some(log_index + 1),
+                ..erc20_transfer_log(weth_contract_address)
+            };
+            let undecodable_log = Log {
+                log_index: Some(log_index + 2),
+                data: Bytes::default(),
+                ..weth_transfer_log.clone()
+            };
+            let provider = Arc::new(Provider {
+                logs: vec![
+                    bayc_transfer_log,
+                    weth_transfer_log,
+                    undecodable_log.clone(),
+                ],
+            });
+
+            ChaindexingRepo::create_contract_addresses(
+                &repo_client,
+                &[
+                    bayc_contract.addresses.clone(),
+                    weth_contract.addresses.clone(),
+                ]
+                .concat(),
+            )
+            .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let ingested_events = ChaindexingRepo::get_all_events(&mut conn).await;
+            let abi_of = |contract_name: &str| {
+                ingested_events
+                    .iter()
+                    .find(|e| e.contract_name == contract_name)
+                    .unwrap()
+                    .abi
+                    .clone()
+            };
+            assert_eq!(ingested_events.len(), 2);
+            assert_eq!(abi_of("BoredApeYachtClub-23"), TransferTestHandler.abi());
+            assert_eq!(abi_of("WETH-1"), Erc20TransferTestHandler.abi());
+
+            let quarantined_logs = ChaindexingRepo::get_all_quarantined_logs(&mut conn).await;
+            assert_eq!(quarantined_logs.len(), 1);
+            assert_eq!(quarantined_logs[0].contract_name.as_deref(), Some("WETH-1"));
+            assert_eq!(
+                quarantined_logs[0].log_index,
+                Some(undecodable_log.log_index.unwrap().as_u32() as i32)
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn quarantines_pending_logs_and_logs_of_unknown_addresses() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Filter, U64};
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-28", "26");
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            #[derive(Clone)]
+            struct Provider {
+                logs: Vec<Log>,
+            }
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for Provider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(BAYC_CONTRACT_START_BLOCK_NUMBER + 20))
+                }
+
+                async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    Ok(self.logs.clone())
+                }
+
+                async fn get_block(
+                    &self,
+                    block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    Ok(Block {
+                        number: Some(block_number),
+                        hash: self.logs[0].block_hash,
+                        ..Default::default()
+                    })
+                }
+            }
+
+            let transfer_log = transfer_log(&bayc_contract.addresses[0].address);
+            let log_index = transfer_log.log_index.unwrap();
+            let pending_log = Log {
+                block_hash: None,
+                block_number: None,
+                transaction_hash: None,
+                log_index: None,
+                ..transfer_log.clone()
+            };
+            let unknown_address_log = Log {
+                address: Address::repeat_byte(0x11),
+                log_index: Some(log_index + 1),
+                ..transfer_log.clone()
+            };
+            let provider = Arc::new(Provider {
+                logs: vec![transfer_log, pending_log, unknown_address_log],
+            });
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            assert_eq!(ChaindexingRepo::get_all_events(&mut conn).await.len(), 1);
+
+            let quarantined_logs = ChaindexingRepo::get_all_quarantined_logs(&mut conn).await;
+            let pending_log = quarantined_logs.iter().find(|l| l.block_number.is_none()).unwrap();
+            let unknown_address_log =
+                quarantined_logs.iter().find(|l| l.contract_name.is_none()).unwrap();
+            assert_eq!(quarantined_logs.len(), 2);
+            assert_eq!(
+                pending_log.contract_name.as_deref(),
+                Some("BoredApeYachtClub-28")
+            );
+            assert_eq!(
+                unknown_address_log.log_index,
+                Some((log_index + 1).as_u32() as i32)
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn decodes_logs_of_upgraded_contracts_by_their_versions() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            use chaindexing::IngesterProvider;
+            use ethers::providers::ProviderError;
+            use ethers::types::{Filter, U64};
+
+            static UPGRADE_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 10;
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-24", "22");
+            let contract_address = bayc_contract.addresses[0].address.clone();
+            let bayc_contract = bayc_contract.add_version(
+                &contract_address,
+                &ChainId::Mainnet,
+                UPGRADE_BLOCK_NUMBER as u64,
+                Contract::new("BoredApeYachtClubV2").add_event_handler(Erc20TransferTestHandler),
+            );
+            let config =
+                Config::new(PostgresRepo::new(&database_url())).add_contract(bayc_contract.clone());
+
+            #[derive(Clone)]
+            struct Provider {
+                logs: Vec<Log>,
+            }
+            #[chaindexing::augmenting_std::async_trait]
+            impl IngesterProvider for Provider {
+                async fn get_block_number(&self) -> Result<U64, ProviderError> {
+                    Ok(U64::from(BAYC_CONTRACT_START_BLOCK_NUMBER + 20))
+                }
+
+                async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+                    let from_block_number = filter.get_from_block().unwrap();
+                    let to_block_number = filter.get_to_block().unwrap();
+
+                    Ok(self
+                        .logs
+                        .iter()
+                        .filter(|log| {
+                            let block_number = log.block_number.unwrap();
+
+                            block_number >= from_block_number && block_number <= to_block_number
+                        })
+                        .cloned()
+                        .collect())
+                }
+
+                async fn get_block(
+                    &self,
+                    block_number: U64,
+                ) -> Result<Block<TxHash>, ProviderError> {
+                    Ok(Block {
+                        number: Some(block_number),
+                        hash: self.logs[0].block_hash,
+                        ..Default::default()
+                    })
+                }
+            }
+
+            let erc721_transfer_log = Log {
+                block_number: Some((UPGRADE_BLOCK_NUMBER - 5).into()),
+                ..transfer_log(&contract_address)
+            };
+            let erc20_transfer_log = Log {
+                block_number: Some((UPGRADE_BLOCK_NUMBER + 5).into()),
+                log_index: Some(erc721_transfer_log.log_index.unwrap() + 1),
+                ..erc20_transfer_log(&contract_address)
+            };
+            let provider = Arc::new(Provider {
+                logs: vec![erc721_transfer_log, erc20_transfer_log],
+            });
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let mut ingested_events = ChaindexingRepo::get_all_events(&mut conn).await;
+            ingested_events.sort_by_key(|e| e.get_block_number());
+            assert_eq!(
+                ingested_events.iter().map(|e| e.abi.as_str()).collect::<Vec<_>>(),
+                vec![TransferTestHandler.abi(), Erc20TransferTestHandler.abi()]
+            );
+            assert!(ChaindexingRepo::get_all_quarantined_logs(&mut conn).await.is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn includes_contracts_spawned_by_factory_events() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            let repo_client = test_runner::new_repo().get_client().await;
+            let bayc_contract = bayc_contract("BoredApeYachtClub-25", "23");
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(Contract::new("Receiver").spawned_by(
+                    "BoredApeYachtClub-25",
+                    TransferTestHandler.abi(),
+                    "to",
+                ))
+                .add_contract(bayc_contract.clone());
+
+            static CURRENT_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 20;
+            let contract_address = &bayc_contract.addresses[0].address;
+            let provider = Arc::new(provider_with_logs!(contract_address, CURRENT_BLOCK_NUMBER));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            let contract_addresses = ChaindexingRepo::get_all_contract_addresses(&mut conn).await;
+            let spawned_contract_address =
+                contract_addresses.iter().find(|c| c.contract_name == "Receiver").unwrap();
+            assert_eq!(
+                spawned_contract_address.address,
+                "0x7dfd6013cf8d92b751e63d481b51fe0e4c5abf5e"
+            );
+            assert_eq!(spawned_contract_address.start_block_number, 18115958);
+            assert_eq!(
+                spawned_contract_address.next_block_number_to_ingest_from,
+                18115958
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn completes_contract_addresses_ingested_up_to_final_end_blocks() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |conn| async move {
+            static END_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 10;
+            static CURRENT_BLOCK_NUMBER: u32 = BAYC_CONTRACT_START_BLOCK_NUMBER + 20;
+
+            let repo_client = test_runner::new_repo().get_client().await;
+            let mut bayc_contract = bayc_contract("BoredApeYachtClub-26", "24");
+            bayc_contract.addresses[0] = bayc_contract.addresses[0]
+                .clone()
+                .with_end_block_number(END_BLOCK_NUMBER as u64);
+            let config = Config::new(PostgresRepo::new(&database_url()))
+                .add_contract(bayc_contract.clone())
+                .with_min_confirmation_count(5);
+
+            let contract_address = &bayc_contract.addresses[0].address;
+            let provider = Arc::new(provider_with_logs!(contract_address, CURRENT_BLOCK_NUMBER));
+
+            ChaindexingRepo::create_contract_addresses(&repo_client, &bayc_contract.addresses)
+                .await;
+            // Ingested up to the end block in earlier runs
+            ChaindexingRepo::execute(
+                &repo_client,
+                &format!(
+                    "UPDATE chaindexing_contract_addresses
+                    SET next_block_number_to_ingest_from = {}
+                    WHERE address = '{contract_address}'",
+                    END_BLOCK_NUMBER + 1
+                ),
+            )
+            .await;
+
+            let conn = Arc::new(Mutex::new(conn));
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            ingester::ingest_for_chain(
+                &ChainId::Mainnet,
+                provider,
+                conn.clone(),
+                &repo_client,
+                &config,
+                &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
+            )
+            .await
+            .unwrap();
+
+            let mut conn = conn.lock().await;
+            assert!(ChaindexingRepo::get_all_events(&mut conn).await.is_empty());
+
+            let contract_addresses = ChaindexingRepo::get_all_contract_addresses(&mut conn).await;
+            let contract_address =
+                contract_addresses.iter().find(|c| c.address == *contract_address).unwrap();
+            assert_eq!(contract_address.status, ContractAddressStatus::Completed);
         })
         .await;
     }
@@ -130,6 +1380,7 @@ mod tests {
                 &repo_client,
                 &config,
                 &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
             )
             .await
             .unwrap();
@@ -174,6 +1425,7 @@ mod tests {
                 &repo_client,
                 &config,
                 &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
             )
             .await
             .unwrap();
@@ -207,6 +1459,7 @@ mod tests {
                 &repo_client,
                 &config,
                 &mut HashMap::new(),
+                &mut AdaptiveBlocksPerBatch::new(config.blocks_per_batch),
             )
             .await
             .unwrap();
@@ -178,3 +178,190 @@ mod create_contract_addresses {
         .await;
     }
 }
+
+#[cfg(test)]
+mod stream_contract_addresses {
+    use std::sync::Arc;
+
+    use chaindexing::streams::ContractAddressesStream;
+    use chaindexing::{ChainId, ChaindexingRepo, ExecutesWithRawQuery, UnsavedContractAddress};
+    use futures_util::StreamExt;
+    use tokio::sync::Mutex;
+
+    use crate::test_runner;
+
+    #[tokio::test]
+    pub async fn streams_each_contract_address_once_across_chunks() {
+        test_runner::run_test_new(|repo_client| async move {
+            let chain_id = ChainId::Optimism;
+            let contract_names = ["contract-name-7", "contract-name-8", "contract-name-9"];
+
+            let contract_addresses: Vec<_> = contract_names
+                .iter()
+                .zip([
+                    "0x8a90CAb2b38dba80c64b7734e58Ee1dB38B8197e",
+                    "0x8a90CAb2b38dba80c64b7734e58Ee1dB38B8198e",
+                    "0x8a90CAb2b38dba80c64b7734e58Ee1dB38B8199e",
+                ])
+                .map(|(contract_name, contract_address_value)| {
+                    UnsavedContractAddress::new(contract_name, contract_address_value, &chain_id, 0)
+                })
+                .collect();
+            ChaindexingRepo::create_contract_addresses(&repo_client, &contract_addresses).await;
+
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            let streamed_contract_addresses: Vec<_> =
+                ContractAddressesStream::new(&repo_client, chain_id as i64)
+                    .with_chunk_size(1)
+                    .collect::<Vec<_>>()
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .filter(|ca| contract_names.contains(&ca.contract_name.as_str()))
+                    .collect();
+
+            assert_eq!(streamed_contract_addresses.len(), contract_names.len());
+        })
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod update_contract_address_status {
+    use std::sync::Arc;
+
+    use chaindexing::{
+        ChainId, ChaindexingRepo, ContractAddressStatus, ExecutesWithRawQuery,
+        UnsavedContractAddress,
+    };
+
+    use tokio::sync::Mutex;
+
+    use crate::{find_contract_address_by_contract_name, test_runner};
+
+    #[tokio::test]
+    pub async fn stops_streaming_paused_contract_addresses_until_resumed() {
+        test_runner::run_test_new(|repo_client| async move {
+            let contract_name = "contract-name-5";
+            let contract_address_value = "0x8a90CAb2b38dba80c64b7734e58Ee1dB38B8195e";
+            let chain_id = ChainId::Arbitrum;
+
+            let contract_addresses = vec![UnsavedContractAddress::new(
+                contract_name,
+                contract_address_value,
+                &chain_id,
+                0,
+            )];
+            ChaindexingRepo::create_contract_addresses(&repo_client, &contract_addresses).await;
+
+            ChaindexingRepo::update_contract_address_status(
+                &repo_client,
+                &contract_address_value.parse().unwrap(),
+                chain_id as u64,
+                ContractAddressStatus::Paused,
+            )
+            .await;
+
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            assert!(
+                find_contract_address_by_contract_name(&repo_client, contract_name, &chain_id)
+                    .await
+                    .is_none()
+            );
+
+            ChaindexingRepo::update_contract_address_status(
+                &*repo_client.lock().await,
+                &contract_address_value.parse().unwrap(),
+                chain_id as u64,
+                ContractAddressStatus::Active,
+            )
+            .await;
+
+            let contract_address =
+                find_contract_address_by_contract_name(&repo_client, contract_name, &chain_id)
+                    .await
+                    .unwrap();
+            assert_eq!(contract_address.status, ContractAddressStatus::Active);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    pub async fn keeps_excluded_contract_addresses_excluded() {
+        test_runner::run_test_new(|repo_client| async move {
+            let contract_name = "contract-name-6";
+            let contract_address_value = "0x8a90CAb2b38dba80c64b7734e58Ee1dB38B8196e";
+            let chain_id = ChainId::Arbitrum;
+
+            let contract_addresses = vec![UnsavedContractAddress::new(
+                contract_name,
+                contract_address_value,
+                &chain_id,
+                0,
+            )];
+            ChaindexingRepo::create_contract_addresses(&repo_client, &contract_addresses).await;
+
+            ChaindexingRepo::update_contract_address_status(
+                &repo_client,
+                &contract_address_value.parse().unwrap(),
+                chain_id as u64,
+                ContractAddressStatus::Excluded,
+            )
+            .await;
+            ChaindexingRepo::update_contract_address_status(
+                &repo_client,
+                &contract_address_value.parse().unwrap(),
+                chain_id as u64,
+                ContractAddressStatus::Active,
+            )
+            .await;
+            ChaindexingRepo::create_contract_addresses(&repo_client, &contract_addresses).await;
+
+            let repo_client = Arc::new(Mutex::new(repo_client));
+            assert!(
+                find_contract_address_by_contract_name(&repo_client, contract_name, &chain_id)
+                    .await
+                    .is_none()
+            );
+        })
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod upsert_blocks {
+    use chaindexing::blocks::Block;
+    use chaindexing::{ChainId, ChaindexingRepo, Repo};
+
+    use crate::test_runner;
+
+    #[tokio::test]
+    pub async fn keeps_known_parent_hashes_of_blocks_upserted_without_them() {
+        let pool = test_runner::get_pool().await;
+
+        test_runner::run_test(&pool, |mut conn| async move {
+            let chain_id = ChainId::Arbitrum as i64;
+            let block = Block {
+                chain_id,
+                number: 100,
+                hash: "0x02".to_string(),
+                parent_hash: Some("0x01".to_string()),
+                timestamp: 1_700_000_000,
+            };
+
+            ChaindexingRepo::upsert_blocks(&mut conn, std::slice::from_ref(&block)).await;
+            ChaindexingRepo::upsert_blocks(
+                &mut conn,
+                &[Block {
+                    parent_hash: None,
+                    ..block.clone()
+                }],
+            )
+            .await;
+
+            let blocks = ChaindexingRepo::get_blocks(&mut conn, chain_id, &[100]).await;
+            assert_eq!(blocks[0].parent_hash, Some("0x01".to_string()));
+        })
+        .await;
+    }
+}
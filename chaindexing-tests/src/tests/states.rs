@@ -88,6 +88,7 @@ use chaindexing::{
 
 use crate::{factory::bayc_contract, test_runner};
 
+#[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(crate = "chaindexing::augmenting_std::serde")]
 struct Nft {
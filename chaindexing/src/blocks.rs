@@ -0,0 +1,177 @@
+use ethers::types::{Block as EthersBlock, TxHash, H256, U256, U64};
+use serde::Deserialize;
+
+use crate::diesel::schema::{chaindexing_block_hashes, chaindexing_blocks};
+use crate::ChainId;
+use diesel::{Insertable, Queryable};
+
+/// Header of a canonical block, cached to save refetching it from providers
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Insertable, Queryable)]
+#[diesel(table_name = chaindexing_blocks)]
+pub struct Block {
+    pub chain_id: i64,
+    pub number: i64,
+    pub hash: String,
+    /// Unknown for blocks only seen through the timestamps returned with logs
+    pub parent_hash: Option<String>,
+    pub timestamp: i64,
+}
+
+impl Block {
+    pub fn new(
+        chain_id: &ChainId,
+        EthersBlock {
+            number,
+            hash,
+            parent_hash,
+            timestamp,
+            ..
+        }: &EthersBlock<TxHash>,
+    ) -> Self {
+        Self {
+            chain_id: *chain_id as i64,
+            number: number.unwrap().as_u64() as i64,
+            hash: h256_to_string(&hash.unwrap()),
+            parent_hash: if parent_hash.is_zero() {
+                None
+            } else {
+                Some(h256_to_string(parent_hash))
+            },
+            timestamp: timestamp.as_u64() as i64,
+        }
+    }
+
+    pub fn to_ethers_block(&self) -> EthersBlock<TxHash> {
+        EthersBlock {
+            number: Some(U64::from(self.number)),
+            hash: Some(self.hash.parse().unwrap()),
+            parent_hash: self.parent_hash.as_ref().map(|h| h.parse().unwrap()).unwrap_or_default(),
+            timestamp: U256::from(self.timestamp),
+            ..Default::default()
+        }
+    }
+}
+
+/// Hash of a block in the recent canonical chain, linked to its parent's
+/// hash to find where the chain forked off
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Insertable, Queryable)]
+#[diesel(table_name = chaindexing_block_hashes)]
+pub struct BlockHash {
+    pub chain_id: i64,
+    pub number: i64,
+    pub hash: String,
+    pub parent_hash: String,
+}
+
+impl BlockHash {
+    pub fn new(
+        chain_id: &ChainId,
+        EthersBlock {
+            number,
+            hash,
+            parent_hash,
+            ..
+        }: &EthersBlock<TxHash>,
+    ) -> Self {
+        Self {
+            chain_id: *chain_id as i64,
+            number: number.unwrap().as_u64() as i64,
+            hash: h256_to_string(&hash.unwrap()),
+            parent_hash: h256_to_string(parent_hash),
+        }
+    }
+
+    pub fn is_parent_of(&self, block_hash: &BlockHash) -> bool {
+        self.number + 1 == block_hash.number && self.hash == block_hash.parent_hash
+    }
+}
+
+/// Range of blocks that block handlers of a chain can run at
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BlockHandlerRange {
+    /// Unknown until block handlers of the chain run for the first time
+    pub next_block_number: Option<i64>,
+    /// Unknown for chains with no contract addresses
+    pub start_block_number: Option<i64>,
+    /// Block handlers run only after every contract address of the chain
+    /// handled the block's events
+    pub next_block_number_to_handle_from: Option<i64>,
+}
+
+impl BlockHandlerRange {
+    /// Returns the range's first block number and the block number it ends before
+    pub fn get(&self) -> Option<(u64, u64)> {
+        let from_block_number = self.next_block_number.or(self.start_block_number)?;
+        let to_block_number = self.next_block_number_to_handle_from?;
+
+        Some((from_block_number as u64, to_block_number as u64))
+            .filter(|(from_block_number, to_block_number)| from_block_number < to_block_number)
+    }
+}
+
+fn h256_to_string(h256: &H256) -> String {
+    format!("{:?}", h256)
+}
+
+#[cfg(test)]
+mod block_tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_and_from_ethers_blocks() {
+        let ethers_block = EthersBlock {
+            number: Some(U64::from(18115958)),
+            hash: Some(
+                "0x8fd4ca304a2e81854059bc3e42f32064cca8b6b453f6286f95060edc6382c6f8"
+                    .parse()
+                    .unwrap(),
+            ),
+            parent_hash: "0x83d751998ff98cd609bc9b18bb36bdef8659cde2f74d6d7a1b0fef2c2bf8f839"
+                .parse()
+                .unwrap(),
+            timestamp: U256::from(1695182747),
+            ..Default::default()
+        };
+
+        let block = Block::new(&ChainId::Mainnet, &ethers_block);
+
+        assert_eq!(
+            block.hash,
+            "0x8fd4ca304a2e81854059bc3e42f32064cca8b6b453f6286f95060edc6382c6f8"
+        );
+        assert_eq!(block.to_ethers_block(), ethers_block);
+    }
+
+    #[test]
+    fn starts_block_handler_range_from_start_block_number_when_unhandled() {
+        let range = BlockHandlerRange {
+            next_block_number: None,
+            start_block_number: Some(100),
+            next_block_number_to_handle_from: Some(150),
+        };
+
+        assert_eq!(range.get(), Some((100, 150)));
+    }
+
+    #[test]
+    fn returns_no_block_handler_range_when_events_are_not_handled_past_it() {
+        let range = BlockHandlerRange {
+            next_block_number: Some(150),
+            start_block_number: Some(100),
+            next_block_number_to_handle_from: Some(150),
+        };
+
+        assert_eq!(range.get(), None);
+    }
+
+    #[test]
+    fn leaves_unknown_parent_hash_empty() {
+        let ethers_block = EthersBlock {
+            number: Some(U64::from(18115958)),
+            hash: Some(H256::random()),
+            ..Default::default()
+        };
+
+        assert!(Block::new(&ChainId::Mainnet, &ethers_block).parent_hash.is_none());
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::diesel::schema::chaindexing_call_traces;
+use diesel::{Insertable, Queryable};
+use ethers::abi::Token;
+use ethers::types::{
+    Action, Address, Block, Bytes as EthersBytes, CallFrame, Trace, TxHash, H256, U256, U64,
+};
+use uuid::Uuid;
+
+use crate::contracts::{self, ContractFunction, ContractFunctionSelector};
+use crate::{Bytes, ChainId, Contract, ContractAddress, EventParam};
+
+use serde::Deserialize;
+
+/// Calls made to contract addresses, including internal calls from other
+/// contracts. Calls to handled functions get their calldata decoded.
+#[derive(Debug, Deserialize, Clone, Eq, Queryable, Insertable)]
+#[diesel(table_name = chaindexing_call_traces)]
+pub struct CallTrace {
+    pub id: Uuid,
+    pub(crate) chain_id: i64,
+    pub contract_address: String,
+    pub contract_name: String,
+    abi: Option<String>,
+    parameters: Option<serde_json::Value>,
+    call_type: String,
+    pub from_address: String,
+    value: String,
+    input: String,
+    error: Option<String>,
+    trace_address: String,
+    pub block_hash: String,
+    pub(crate) block_number: i64,
+    pub transaction_hash: String,
+    pub(crate) transaction_index: i32,
+}
+
+impl PartialEq for CallTrace {
+    fn eq(&self, other: &Self) -> bool {
+        self.chain_id == other.chain_id
+            && self.contract_address == other.contract_address
+            && self.transaction_hash == other.transaction_hash
+            && self.trace_address == other.trace_address
+            && self.block_hash == other.block_hash
+    }
+}
+
+impl Hash for CallTrace {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chain_id.hash(state);
+        self.contract_address.hash(state);
+        self.transaction_hash.hash(state);
+        self.trace_address.hash(state);
+        self.block_hash.hash(state);
+    }
+}
+
+impl CallTrace {
+    /// Calls whose calldata does not match the function, e.g. calls to
+    /// fallbacks sharing its selector, get kept undecoded
+    pub fn new(
+        traced_call: &TracedCall,
+        function: Option<&ContractFunction>,
+        chain_id: &ChainId,
+        contract_name: &str,
+    ) -> Self {
+        let decoded_function = function.and_then(|function| {
+            let params = function.value.decode_input(traced_call.input.get(4..)?).ok()?;
+            let parameters: HashMap<_, _> = function
+                .value
+                .inputs
+                .iter()
+                .map(|input| input.name.to_string())
+                .zip(params)
+                .collect::<HashMap<String, Token>>();
+
+            Some((function, serde_json::to_value(parameters).unwrap()))
+        });
+        let trace_address = traced_call
+            .trace_address
+            .iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        let block_hash = format!("{:?}", traced_call.block_hash);
+        let transaction_hash = format!("{:?}", traced_call.transaction_hash);
+
+        Self {
+            id: Self::get_id(chain_id, &block_hash, &transaction_hash, &trace_address),
+            chain_id: *chain_id as i64,
+            contract_address: format!("{:?}", traced_call.to),
+            contract_name: contract_name.to_owned(),
+            abi: decoded_function.as_ref().map(|(function, _parameters)| function.abi.clone()),
+            parameters: decoded_function.map(|(_function, parameters)| parameters),
+            call_type: traced_call.call_type.clone(),
+            from_address: format!("{:?}", traced_call.from),
+            value: traced_call.value.to_string(),
+            input: traced_call.input.to_string(),
+            error: traced_call.error.clone(),
+            trace_address,
+            block_hash,
+            block_number: traced_call.block_number.as_u64() as i64,
+            transaction_hash,
+            transaction_index: traced_call.transaction_index as i32,
+        }
+    }
+
+    /// Derives the call's id from its position in its transaction's call tree,
+    /// like events', see `Event::get_id`
+    pub(crate) fn get_id(
+        chain_id: &ChainId,
+        block_hash: &str,
+        transaction_hash: &str,
+        trace_address: &str,
+    ) -> Uuid {
+        let name = format!(
+            "{}:{}:{}:{}",
+            *chain_id as u64, block_hash, transaction_hash, trace_address
+        );
+
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    }
+
+    pub(crate) fn get_abi(&self) -> Option<&str> {
+        self.abi.as_deref()
+    }
+
+    /// Returns the call's block number
+    pub fn get_block_number(&self) -> u64 {
+        self.block_number as u64
+    }
+    /// Returns the index of the call's transaction in its block
+    pub fn get_transaction_index(&self) -> u32 {
+        self.transaction_index as u32
+    }
+    /// Returns the call's position in its transaction's call tree.
+    /// Empty for the transaction's top-level call.
+    pub fn get_trace_address(&self) -> Vec<usize> {
+        if self.trace_address.is_empty() {
+            vec![]
+        } else {
+            self.trace_address.split('.').map(|index| index.parse().unwrap()).collect()
+        }
+    }
+
+    /// Returns the arguments the function was called with.
+    /// Only calls to handled functions get decoded.
+    pub fn get_params(&self) -> Option<EventParam> {
+        self.parameters.as_ref().map(EventParam::new)
+    }
+
+    /// Returns the call's type, i.e. `call`, `callcode`, `delegatecall` or `staticcall`
+    pub fn get_call_type(&self) -> &str {
+        self.call_type.as_str()
+    }
+    /// Returns the call's sender, which is a contract for internal calls
+    pub fn get_from(&self) -> Address {
+        self.from_address.parse().unwrap()
+    }
+    /// Returns the wei sent along with the call
+    pub fn get_value(&self) -> U256 {
+        U256::from_dec_str(&self.value).unwrap()
+    }
+    /// Returns the call's calldata
+    pub fn get_input(&self) -> Bytes {
+        EthersBytes::from_str(&self.input).unwrap().to_vec()
+    }
+    /// Returns why the call reverted, if it did
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+    /// Returns whether the call succeeded. Calls can revert without
+    /// reverting their transaction.
+    pub fn is_successful(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Returns the call's chain id
+    pub fn get_chain_id(&self) -> ChainId {
+        U64::from(self.chain_id).try_into().unwrap()
+    }
+}
+
+/// Call made to an address, normalized from either `trace_filter`'s traces
+/// or `debug_traceBlockByNumber`'s call frames
+#[derive(Clone, Debug, PartialEq)]
+pub struct TracedCall {
+    pub call_type: String,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub input: EthersBytes,
+    pub error: Option<String>,
+    pub trace_address: Vec<usize>,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub transaction_hash: TxHash,
+    pub transaction_index: usize,
+}
+
+impl TracedCall {
+    /// Returns `None` for contract creations, self-destructs and block rewards
+    pub fn from_trace(trace: &Trace) -> Option<Self> {
+        match &trace.action {
+            Action::Call(call) => Some(Self {
+                call_type: serde_json::to_value(&call.call_type)
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+                from: call.from,
+                to: call.to,
+                value: call.value,
+                input: call.input.clone(),
+                error: trace.error.clone(),
+                trace_address: trace.trace_address.clone(),
+                block_hash: trace.block_hash,
+                block_number: U64::from(trace.block_number),
+                transaction_hash: trace.transaction_hash?,
+                transaction_index: trace.transaction_position?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Flattens the call frames of a block's transactions, in the block's order
+    pub fn from_call_frames(block: &Block<TxHash>, call_frames: &[CallFrame]) -> Vec<Self> {
+        let mut traced_calls = vec![];
+
+        for (transaction_index, (call_frame, transaction_hash)) in
+            call_frames.iter().zip(block.transactions.iter()).enumerate()
+        {
+            let traced_call = Self {
+                call_type: String::new(),
+                from: Address::zero(),
+                to: Address::zero(),
+                value: U256::zero(),
+                input: EthersBytes::new(),
+                error: None,
+                trace_address: vec![],
+                block_hash: block.hash.unwrap(),
+                block_number: block.number.unwrap(),
+                transaction_hash: *transaction_hash,
+                transaction_index,
+            };
+
+            Self::flatten(call_frame, traced_call, &mut traced_calls);
+        }
+
+        traced_calls
+    }
+
+    fn flatten(call_frame: &CallFrame, traced_call: TracedCall, traced_calls: &mut Vec<Self>) {
+        const CALL_TYPES: [&str; 4] = ["call", "callcode", "delegatecall", "staticcall"];
+
+        let call_type = call_frame.typ.to_lowercase();
+
+        if let (true, Some(to)) = (
+            CALL_TYPES.contains(&call_type.as_str()),
+            call_frame.to.as_ref().and_then(|to| to.as_address()),
+        ) {
+            traced_calls.push(Self {
+                call_type,
+                from: call_frame.from,
+                to: *to,
+                value: call_frame.value.unwrap_or_default(),
+                input: call_frame.input.clone(),
+                error: call_frame.error.clone(),
+                ..traced_call.clone()
+            });
+        }
+
+        for (index, nested_call_frame) in call_frame.calls.iter().flatten().enumerate() {
+            let mut trace_address = traced_call.trace_address.clone();
+            trace_address.push(index);
+
+            Self::flatten(
+                nested_call_frame,
+                TracedCall {
+                    trace_address,
+                    ..traced_call.clone()
+                },
+                traced_calls,
+            );
+        }
+    }
+}
+
+/// Decodes the calls to contract addresses, keeping the calldata of
+/// unhandled functions and native transfers undecoded
+pub fn get<S: Send + Sync + Clone>(
+    traced_calls: &[TracedCall],
+    contracts: &[Contract<S>],
+    contract_addresses: &[ContractAddress],
+    chain_id: &ChainId,
+) -> Vec<CallTrace> {
+    let functions_by_keys = contracts::group_call_functions_by_keys(contracts);
+    let contract_addresses_by_address =
+        ContractAddress::group_contract_addresses_by_address_and_chain_id(contract_addresses);
+
+    traced_calls
+        .iter()
+        .filter_map(|traced_call| {
+            let contract_address =
+                contract_addresses_by_address.get(&(traced_call.to, *chain_id))?;
+            let function = get_selector(traced_call).and_then(|selector| {
+                functions_by_keys.get(&(contract_address.contract_name.clone(), selector))
+            });
+
+            Some(CallTrace::new(
+                traced_call,
+                function,
+                chain_id,
+                &contract_address.contract_name,
+            ))
+        })
+        .collect()
+}
+
+fn get_selector(traced_call: &TracedCall) -> Option<ContractFunctionSelector> {
+    traced_call.input.get(..4).map(|selector| selector.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod call_trace_tests {
+    use ethers::abi::Tokenize;
+    use ethers::types::{Call, CallType};
+
+    use super::*;
+    use crate::contracts::ContractAddressStatus;
+
+    const SET_APPROVAL_FOR_ALL_ABI: &str =
+        "function setApprovalForAll(address operator, bool approved)";
+
+    struct SetApprovalForAllHandler;
+
+    #[crate::augmenting_std::async_trait]
+    impl crate::CallHandler for SetApprovalForAllHandler {
+        fn abi(&self) -> &'static str {
+            SET_APPROVAL_FOR_ALL_ABI
+        }
+        async fn handle_call<'a>(&self, _context: crate::CallContext<'a>) {}
+    }
+
+    fn contract_address(contract_name: &str) -> ContractAddress {
+        ContractAddress {
+            id: 1,
+            chain_id: ChainId::Mainnet as i64,
+            next_block_number_to_ingest_from: 0,
+            next_block_number_to_handle_from: 0,
+            next_block_number_for_side_effects: 0,
+            start_block_number: 0,
+            address: format!("{:?}", Address::random()),
+            contract_name: contract_name.to_string(),
+            status: ContractAddressStatus::Active,
+            end_block_number: None,
+        }
+    }
+
+    fn set_approval_for_all(to: Address, operator: Address) -> TracedCall {
+        let function = ContractFunction::new(SET_APPROVAL_FOR_ALL_ABI);
+
+        TracedCall {
+            call_type: "call".to_string(),
+            from: Address::random(),
+            to,
+            value: U256::zero(),
+            input: function.value.encode_input(&(operator, true).into_tokens()).unwrap().into(),
+            error: None,
+            trace_address: vec![0],
+            block_hash: H256::random(),
+            block_number: U64::from(17773490),
+            transaction_hash: H256::random(),
+            transaction_index: 3,
+        }
+    }
+
+    fn call_frame(typ: &str, to: Address, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            typ: typ.to_string(),
+            from: Address::random(),
+            to: Some(to.into()),
+            input: EthersBytes::new(),
+            calls: Some(calls),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flattens_call_frames_with_their_trace_addresses() {
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        let block = Block {
+            number: Some(U64::from(17773490)),
+            hash: Some(H256::random()),
+            transactions: vec![H256::random(), H256::random()],
+            ..Default::default()
+        };
+        let call_frames = [
+            call_frame("CALL", a, vec![]),
+            call_frame(
+                "CALL",
+                a,
+                vec![
+                    call_frame("STATICCALL", b, vec![]),
+                    call_frame("DELEGATECALL", b, vec![call_frame("CALL", c, vec![])]),
+                ],
+            ),
+        ];
+
+        let traced_calls = TracedCall::from_call_frames(&block, &call_frames);
+
+        assert_eq!(
+            traced_calls
+                .iter()
+                .map(|t| (t.transaction_index, t.trace_address.clone(), t.to))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, vec![], a),
+                (1, vec![], a),
+                (1, vec![0], b),
+                (1, vec![1], b),
+                (1, vec![1, 0], c)
+            ]
+        );
+        assert_eq!(traced_calls[1].transaction_hash, block.transactions[1]);
+        assert_eq!(traced_calls[3].call_type, "delegatecall");
+    }
+
+    #[test]
+    fn skips_contract_creations() {
+        let block = Block {
+            number: Some(U64::from(17773490)),
+            hash: Some(H256::random()),
+            transactions: vec![H256::random()],
+            ..Default::default()
+        };
+        let created = Address::random();
+        let call_frames = [call_frame(
+            "CALL",
+            Address::random(),
+            vec![call_frame("CREATE2", created, vec![])],
+        )];
+
+        let traced_calls = TracedCall::from_call_frames(&block, &call_frames);
+
+        assert_eq!(traced_calls.len(), 1);
+        assert!(traced_calls.iter().all(|t| t.to != created));
+    }
+
+    #[test]
+    fn normalizes_traces() {
+        let trace = Trace {
+            action: Action::Call(Call {
+                from: Address::random(),
+                to: Address::random(),
+                value: U256::from(10),
+                call_type: CallType::DelegateCall,
+                ..Default::default()
+            }),
+            result: None,
+            trace_address: vec![0, 2],
+            subtraces: 0,
+            transaction_position: Some(3),
+            transaction_hash: Some(H256::random()),
+            block_number: 17773490,
+            block_hash: H256::random(),
+            action_type: ethers::types::ActionType::Call,
+            error: Some("Reverted".to_string()),
+        };
+
+        let traced_call = TracedCall::from_trace(&trace).unwrap();
+
+        assert_eq!(traced_call.call_type, "delegatecall");
+        assert_eq!(traced_call.trace_address, vec![0, 2]);
+        assert_eq!(traced_call.transaction_index, 3);
+        assert_eq!(traced_call.error.as_deref(), Some("Reverted"));
+    }
+
+    #[test]
+    fn decodes_calldata_of_handled_functions_only() {
+        let contract_address = contract_address("BoredApeYachtClub");
+        let contract =
+            Contract::<()>::new("BoredApeYachtClub").add_call_handler(SetApprovalForAllHandler);
+
+        let operator = Address::random();
+        let handled_call =
+            set_approval_for_all(contract_address.address.parse().unwrap(), operator);
+        let ether_transfer = TracedCall {
+            value: U256::from(10),
+            input: EthersBytes::new(),
+            trace_address: vec![1],
+            ..handled_call.clone()
+        };
+
+        let call_traces = get(
+            &[handled_call, ether_transfer],
+            &[contract],
+            &[contract_address],
+            &ChainId::Mainnet,
+        );
+
+        assert_eq!(
+            call_traces[0].get_params().unwrap().get_address("operator"),
+            operator
+        );
+        assert_eq!(call_traces[0].get_trace_address(), vec![0]);
+        assert!(call_traces[1].get_params().is_none());
+        assert_eq!(call_traces[1].get_value(), U256::from(10));
+    }
+
+    #[test]
+    fn decodes_calldata_with_functions_of_the_called_contracts() {
+        let handled_contract_address = contract_address("BoredApeYachtClub");
+        let other_contract_address = contract_address("Doodles");
+        let contract =
+            Contract::<()>::new("BoredApeYachtClub").add_call_handler(SetApprovalForAllHandler);
+
+        let call_traces = get(
+            &[set_approval_for_all(
+                other_contract_address.address.parse().unwrap(),
+                Address::random(),
+            )],
+            &[contract, Contract::new("Doodles")],
+            &[handled_contract_address, other_contract_address],
+            &ChainId::Mainnet,
+        );
+
+        assert_eq!(call_traces[0].contract_name, "Doodles");
+        assert!(call_traces[0].get_params().is_none());
+    }
+
+    #[test]
+    fn keeps_calldata_not_matching_functions_undecoded() {
+        let mut traced_call = set_approval_for_all(Address::random(), Address::random());
+        traced_call.input = traced_call.input[..8].to_vec().into();
+
+        let call_trace = CallTrace::new(
+            &traced_call,
+            Some(&ContractFunction::new(SET_APPROVAL_FOR_ALL_ABI)),
+            &ChainId::Mainnet,
+            "BoredApeYachtClub",
+        );
+
+        assert!(call_trace.get_abi().is_none());
+        assert!(call_trace.get_params().is_none());
+    }
+
+    #[test]
+    fn derives_ids_from_positions_in_call_trees() {
+        let traced_call = set_approval_for_all(Address::random(), Address::random());
+        let nested_traced_call = TracedCall {
+            trace_address: vec![0, 1],
+            ..traced_call.clone()
+        };
+        let get_id = |traced_call: &TracedCall| {
+            CallTrace::new(traced_call, None, &ChainId::Mainnet, "BoredApeYachtClub").id
+        };
+
+        assert_eq!(get_id(&traced_call), get_id(&traced_call));
+        assert_ne!(get_id(&traced_call), get_id(&nested_traced_call));
+    }
+}
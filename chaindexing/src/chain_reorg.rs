@@ -1,45 +1,86 @@
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::collections::HashMap;
 
 use crate::diesel::schema::chaindexing_reorged_blocks;
 use crate::ChainId;
 use diesel::prelude::Insertable;
+use ethers::types::BlockNumber;
 use serde::Deserialize;
 
-/// Tolerance for chain re-organization
+/// Decides which ingested blocks are final, i.e. no longer get
+/// re-checked for chain re-organizations or uncled blocks
+#[derive(Clone, Debug, PartialEq)]
+pub enum Finality {
+    /// Blocks with at least this many confirmations are final
+    MinConfirmationCount(u8),
+    /// Blocks up to the `finalized` block tag are final.
+    /// Served by post-merge Ethereum and most L2s.
+    Finalized,
+    /// Blocks up to the `safe` block tag are final.
+    /// Re-checks fewer blocks than `Finalized`, at the risk of missing deep re-organizations.
+    Safe,
+}
+
+impl Finality {
+    /// Returns the block tag to fetch the last final block with, if any
+    pub fn get_block_tag(&self) -> Option<BlockNumber> {
+        match self {
+            Finality::MinConfirmationCount(_) => None,
+            Finality::Finalized => Some(BlockNumber::Finalized),
+            Finality::Safe => Some(BlockNumber::Safe),
+        }
+    }
+}
+
+/// How chain re-organizations of blocks that are not final yet get detected
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ReorgDetection {
+    /// Refetches the logs, transactions and call traces of blocks that are not
+    /// final yet, replacing the ingested ones that changed
+    #[default]
+    Refetching,
+    /// Tracks the hashes of blocks that are not final yet. A reorg is found where
+    /// fetched blocks' parent hashes stop matching the tracked hashes, and everything
+    /// ingested from there on gets ingested again. Catches reorgs that did not change
+    /// any of the contracts' logs, without refetching logs of every contract address.
+    ParentHashes,
+}
+
+/// Last final block of a chain, past which ingested blocks get re-checked
 #[derive(Clone, Debug)]
-pub struct MinConfirmationCount {
-    value: u8,
+pub struct FinalizedBlockNumber {
+    value: u64,
 }
 
-impl MinConfirmationCount {
-    pub fn new(value: u8) -> Self {
+impl FinalizedBlockNumber {
+    pub fn new(value: u64) -> Self {
         Self { value }
     }
 
-    pub fn deduct_from(&self, block_number: u64, start_block_number: u64) -> u64 {
-        let deduction = max(0, (block_number as i64) - (self.value as i64));
+    pub fn from_min_confirmation_count(
+        min_confirmation_count: u8,
+        current_block_number: u64,
+    ) -> Self {
+        Self::new(current_block_number.saturating_sub(min_confirmation_count as u64))
+    }
 
-        max(start_block_number, deduction as u64)
+    pub fn get(&self) -> u64 {
+        self.value
     }
 
-    pub fn is_in_confirmation_window(
-        &self,
-        next_block_number: u64,
-        current_block_number: u64,
-    ) -> bool {
-        if self.value as u64 >= current_block_number {
-            false
-        } else {
-            next_block_number >= current_block_number - (self.value as u64)
-        }
+    pub fn deduct_from(&self, block_number: u64, start_block_number: u64) -> u64 {
+        max(start_block_number, min(block_number, self.value))
+    }
+
+    pub fn is_in_confirmation_window(&self, next_block_number: u64) -> bool {
+        self.value > 0 && next_block_number >= self.value
     }
 }
 
 #[derive(Clone)]
 pub enum Execution<'a> {
     Main,
-    Confirmation(&'a MinConfirmationCount),
+    Confirmation(&'a FinalizedBlockNumber),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
@@ -96,3 +137,47 @@ impl ReorgedBlocks {
         reorged_blocks.iter().map(|r| r.id).collect()
     }
 }
+
+#[cfg(test)]
+mod chain_reorg_tests {
+    use super::*;
+
+    #[test]
+    fn finalizes_blocks_with_min_confirmation_count() {
+        let finalized_block_number = FinalizedBlockNumber::from_min_confirmation_count(40, 1_000);
+
+        assert!(!finalized_block_number.is_in_confirmation_window(959));
+        assert!(finalized_block_number.is_in_confirmation_window(960));
+    }
+
+    #[test]
+    fn does_not_confirm_before_min_confirmation_count_blocks() {
+        let finalized_block_number = FinalizedBlockNumber::from_min_confirmation_count(40, 30);
+
+        assert!(!finalized_block_number.is_in_confirmation_window(20));
+    }
+
+    #[test]
+    fn stops_re_checking_at_the_finalized_block() {
+        let finalized_block_number = FinalizedBlockNumber::new(17_773_490);
+
+        assert_eq!(
+            finalized_block_number.deduct_from(17_773_550, 17_700_000),
+            17_773_490
+        );
+        assert_eq!(
+            finalized_block_number.deduct_from(17_773_550, 17_773_500),
+            17_773_500
+        );
+    }
+
+    #[test]
+    fn fetches_finalized_and_safe_blocks_by_their_tags() {
+        assert_eq!(Finality::MinConfirmationCount(40).get_block_tag(), None);
+        assert_eq!(
+            Finality::Finalized.get_block_tag(),
+            Some(BlockNumber::Finalized)
+        );
+        assert_eq!(Finality::Safe.get_block_tag(), Some(BlockNumber::Safe));
+    }
+}
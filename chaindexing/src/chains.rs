@@ -1,3 +1,9 @@
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::{Finality, ReorgDetection, RetryPolicy};
+
 /// Represents the network ID for an EVM Chain
 /// For example, `ChainId::Mainnet`, `ChainId::Polygon`, etc.
 pub type ChainId = ethers::types::Chain;
@@ -6,11 +12,40 @@ pub type ChainId = ethers::types::Chain;
 #[derive(Clone, Debug)]
 pub struct Chain {
     pub id: ChainId,
-    pub json_rpc_url: String,
+    /// JSON-RPC endpoints in order of preference
+    pub json_rpc_urls: Vec<String>,
+    pub ws_url: Option<String>,
+    pub(crate) json_rpc_timeout_ms: u64,
+    pub(crate) json_rpc_cool_down_ms: u64,
+    pub(crate) json_rpc_requests_per_second: Option<u32>,
+    pub(crate) json_rpc_method_costs: HashMap<String, u32>,
+    pub(crate) json_rpc_batch_size: usize,
+    pub(crate) trace_mode: Option<TraceMode>,
+    pub(crate) archive_dir: Option<PathBuf>,
+    pub(crate) recording_path: Option<PathBuf>,
+    pub(crate) finality: Option<Finality>,
+    pub(crate) reorg_detection: ReorgDetection,
+    pub(crate) blocks_per_batch: Option<u64>,
+    pub(crate) ingestion_rate_ms: Option<u64>,
+    pub(crate) handler_rate_ms: Option<u64>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+}
+
+/// JSON-RPC method used to trace the calls made to contract addresses
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceMode {
+    /// Parity/OpenEthereum's `trace_filter`, served by Erigon, Nethermind, Reth, etc.
+    TraceFilter,
+    /// Geth's `debug_traceBlockByNumber` with the `callTracer`.
+    /// Every block in range gets traced, so prefer `TraceFilter` where it is served.
+    DebugTraceBlock,
 }
 
 impl Chain {
-    /// Builds the chain network
+    /// Builds the chain network.
+    /// Accepts either a single JSON-RPC endpoint or an ordered list of them.
+    /// When an endpoint errors or times out, ingestion fails over to the next
+    /// healthy one while the failing endpoint cools down.
     ///
     ///
     /// # Example
@@ -18,11 +53,384 @@ impl Chain {
     /// use chaindexing::{Chain, ChainId};
     ///
     /// Chain::new(ChainId::Polygon, "https://polygon-mainnet.g.alchemy.com/v2/...");
+    ///
+    /// Chain::new(
+    ///     ChainId::Polygon,
+    ///     [
+    ///         "https://polygon-mainnet.g.alchemy.com/v2/...",
+    ///         "https://polygon-mainnet.infura.io/v3/...",
+    ///     ],
+    /// );
     /// ```
-    pub fn new(id: ChainId, json_rpc_url: &str) -> Self {
+    pub fn new(id: ChainId, json_rpc_urls: impl JsonRpcUrls) -> Self {
         Self {
             id,
-            json_rpc_url: json_rpc_url.to_string(),
+            json_rpc_urls: json_rpc_urls.to_json_rpc_urls(),
+            ws_url: None,
+            json_rpc_timeout_ms: 30_000,
+            json_rpc_cool_down_ms: 60_000,
+            json_rpc_requests_per_second: None,
+            json_rpc_method_costs: HashMap::new(),
+            json_rpc_batch_size: 50,
+            trace_mode: None,
+            archive_dir: None,
+            recording_path: None,
+            finality: None,
+            reorg_detection: ReorgDetection::default(),
+            blocks_per_batch: None,
+            ingestion_rate_ms: None,
+            handler_rate_ms: None,
+            retry_policy: None,
         }
     }
+
+    /// Ingests as soon as new blocks arrive by subscribing to `newHeads`
+    /// over WebSocket. Ingestion falls back to polling every `ingestion_rate_ms`
+    /// whenever the subscription drops.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Polygon, "https://polygon-mainnet.g.alchemy.com/v2/...")
+    ///     .with_ws_url("wss://polygon-mainnet.g.alchemy.com/v2/...");
+    /// ```
+    pub fn with_ws_url(mut self, ws_url: &str) -> Self {
+        self.ws_url = Some(ws_url.to_string());
+
+        self
+    }
+
+    /// Advance config: How long to wait for a JSON-RPC endpoint before
+    /// failing over to the next one.
+    /// Default is 30_000
+    pub fn with_json_rpc_timeout_ms(mut self, json_rpc_timeout_ms: u64) -> Self {
+        self.json_rpc_timeout_ms = json_rpc_timeout_ms;
+
+        self
+    }
+
+    /// Advance config: How long a failing JSON-RPC endpoint is skipped
+    /// before it is tried again.
+    /// Default is 60_000
+    pub fn with_json_rpc_cool_down_ms(mut self, json_rpc_cool_down_ms: u64) -> Self {
+        self.json_rpc_cool_down_ms = json_rpc_cool_down_ms;
+
+        self
+    }
+
+    /// Limits JSON-RPC requests made for this chain, across all its endpoints,
+    /// to a budget per second. Requests wait whenever the budget is exhausted.
+    /// Unlimited by default.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Polygon, "https://polygon-mainnet.g.alchemy.com/v2/...")
+    ///     .with_json_rpc_requests_per_second(330)
+    ///     .with_json_rpc_method_cost("eth_getLogs", 75)
+    ///     .with_json_rpc_method_cost("eth_getBlockByNumber", 16)
+    ///     .with_json_rpc_method_cost("eth_blockNumber", 10);
+    /// ```
+    pub fn with_json_rpc_requests_per_second(mut self, json_rpc_requests_per_second: u32) -> Self {
+        self.json_rpc_requests_per_second = Some(json_rpc_requests_per_second);
+
+        self
+    }
+
+    /// Weighs a JSON-RPC method against the budget set with
+    /// `with_json_rpc_requests_per_second`, for providers billing by compute units.
+    /// Methods cost 1 by default.
+    pub fn with_json_rpc_method_cost(mut self, method: &str, cost: u32) -> Self {
+        self.json_rpc_method_costs.insert(method.to_string(), cost);
+
+        self
+    }
+
+    /// Advance config: How many blocks to fetch per JSON-RPC batch request
+    /// when looking up the timestamps of ingested events. Blocks are not
+    /// fetched at all for logs that already carry `blockTimestamp`.
+    /// Default is 50
+    pub fn with_json_rpc_batch_size(mut self, json_rpc_batch_size: usize) -> Self {
+        self.json_rpc_batch_size = json_rpc_batch_size;
+
+        self
+    }
+
+    /// Ingests the calls made to contract addresses, including internal calls
+    /// and native transfers between contracts that emit no logs. Call traces
+    /// follow the same block ranges and confirmation window as events.
+    /// Not ingested by default.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId, TraceMode};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_trace_mode(TraceMode::TraceFilter);
+    /// ```
+    pub fn with_trace_mode(mut self, trace_mode: TraceMode) -> Self {
+        self.trace_mode = Some(trace_mode);
+
+        self
+    }
+
+    /// Backfills logs and blocks from local JSONL archive files instead of
+    /// JSON-RPC. Ingestion switches to the JSON-RPC endpoints past the last
+    /// archived block. See `ArchiveProvider` for the archive's layout.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_archive_dir("/var/lib/archives/mainnet");
+    /// ```
+    pub fn with_archive_dir(mut self, archive_dir: impl Into<PathBuf>) -> Self {
+        self.archive_dir = Some(archive_dir.into());
+
+        self
+    }
+
+    /// Appends every JSON-RPC request and its response to a JSONL recording file.
+    /// Ingestion can then be reproduced bit-for-bit by ingesting with a
+    /// `ReplayProvider` of the recording file.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_recording_path("/var/lib/recordings/mainnet.jsonl");
+    /// ```
+    pub fn with_recording_path(mut self, recording_path: impl Into<PathBuf>) -> Self {
+        self.recording_path = Some(recording_path.into());
+
+        self
+    }
+
+    /// Decides which ingested blocks still get re-checked for chain re-organizations.
+    /// Defaults to the config's minimum confirmation count.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId, Finality};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_finality(Finality::Finalized);
+    /// ```
+    pub fn with_finality(mut self, finality: Finality) -> Self {
+        self.finality = Some(finality);
+
+        self
+    }
+
+    /// Decides how chain re-organizations get detected.
+    /// Defaults to `ReorgDetection::Refetching`.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId, ReorgDetection};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_reorg_detection(ReorgDetection::ParentHashes);
+    /// ```
+    pub fn with_reorg_detection(mut self, reorg_detection: ReorgDetection) -> Self {
+        self.reorg_detection = reorg_detection;
+
+        self
+    }
+
+    /// The minimum confirmation count for detecting chain-reorganizations or uncled blocks.
+    /// Defaults to the config's minimum confirmation count.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Polygon, "https://polygon-mainnet.g.alchemy.com/v2/...")
+    ///     .with_min_confirmation_count(128);
+    /// ```
+    pub fn with_min_confirmation_count(mut self, min_confirmation_count: u8) -> Self {
+        self.finality = Some(Finality::MinConfirmationCount(min_confirmation_count));
+
+        self
+    }
+
+    /// How many blocks per batch should be ingested and handled.
+    /// Defaults to the config's blocks per batch.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Arbitrum, "https://arb-mainnet.g.alchemy.com/v2/...")
+    ///     .with_blocks_per_batch(2_000);
+    /// ```
+    pub fn with_blocks_per_batch(mut self, blocks_per_batch: u64) -> Self {
+        self.blocks_per_batch = Some(blocks_per_batch);
+
+        self
+    }
+
+    /// How often should the chain's events be ingested.
+    /// Defaults to the config's ingestion rate.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Arbitrum, "https://arb-mainnet.g.alchemy.com/v2/...")
+    ///     .with_ingestion_rate_ms(2_000);
+    /// ```
+    pub fn with_ingestion_rate_ms(mut self, ingestion_rate_ms: u64) -> Self {
+        self.ingestion_rate_ms = Some(ingestion_rate_ms);
+
+        self
+    }
+
+    /// How often should the chain's events be handled.
+    /// Defaults to the config's handler rate.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId};
+    ///
+    /// Chain::new(ChainId::Arbitrum, "https://arb-mainnet.g.alchemy.com/v2/...")
+    ///     .with_handler_rate_ms(1_000);
+    /// ```
+    pub fn with_handler_rate_ms(mut self, handler_rate_ms: u64) -> Self {
+        self.handler_rate_ms = Some(handler_rate_ms);
+
+        self
+    }
+
+    /// Decides how failed JSON-RPC requests get retried.
+    /// Defaults to the config's retry policy.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{Chain, ChainId, RetryPolicy};
+    ///
+    /// Chain::new(ChainId::Mainnet, "https://eth-mainnet.g.alchemy.com/v2/...")
+    ///     .with_retry_policy(RetryPolicy::new(3).with_max_backoff_ms(5_000));
+    /// ```
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+
+        self
+    }
+}
+
+/// Chunks chains to be processed concurrently. Chains processed at different
+/// rates never share a chunk, since each chunk runs at a single rate.
+pub(crate) fn chunk_by_rate(
+    chains: &[Chain],
+    chain_concurrency: u32,
+    get_rate_ms: impl Fn(&Chain) -> u64,
+) -> Vec<Vec<Chain>> {
+    let chunk_size = max(chains.len() / chain_concurrency as usize, 1);
+
+    let chains_by_rate_ms = chains.iter().fold(
+        BTreeMap::<u64, Vec<Chain>>::new(),
+        |mut chains_by_rate_ms, chain| {
+            chains_by_rate_ms.entry(get_rate_ms(chain)).or_default().push(chain.clone());
+
+            chains_by_rate_ms
+        },
+    );
+
+    chains_by_rate_ms
+        .values()
+        .flat_map(|chains| chains.chunks(chunk_size).map(|c| c.to_vec()))
+        .collect()
+}
+
+/// One or more JSON-RPC endpoints, in order of preference
+pub trait JsonRpcUrls {
+    fn to_json_rpc_urls(&self) -> Vec<String>;
+}
+
+impl JsonRpcUrls for &str {
+    fn to_json_rpc_urls(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl JsonRpcUrls for String {
+    fn to_json_rpc_urls(&self) -> Vec<String> {
+        vec![self.to_owned()]
+    }
+}
+
+impl<T: AsRef<str>> JsonRpcUrls for &[T] {
+    fn to_json_rpc_urls(&self) -> Vec<String> {
+        self.iter().map(|url| url.as_ref().to_string()).collect()
+    }
+}
+
+impl<T: AsRef<str>, const N: usize> JsonRpcUrls for [T; N] {
+    fn to_json_rpc_urls(&self) -> Vec<String> {
+        self.iter().map(|url| url.as_ref().to_string()).collect()
+    }
+}
+
+impl<T: AsRef<str>> JsonRpcUrls for Vec<T> {
+    fn to_json_rpc_urls(&self) -> Vec<String> {
+        self.iter().map(|url| url.as_ref().to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod chains_tests {
+    use super::*;
+
+    fn chain(id: ChainId) -> Chain {
+        Chain::new(id, "https://json-rpc.url")
+    }
+
+    fn get_ids(chunks: &[Vec<Chain>]) -> Vec<Vec<ChainId>> {
+        chunks.iter().map(|chains| chains.iter().map(|c| c.id).collect()).collect()
+    }
+
+    #[test]
+    fn chunks_chains_by_concurrency() {
+        let chains = [
+            chain(ChainId::Mainnet),
+            chain(ChainId::Polygon),
+            chain(ChainId::Arbitrum),
+            chain(ChainId::Optimism),
+        ];
+
+        let chunks = chunk_by_rate(&chains, 2, |_chain| 20_000);
+
+        assert_eq!(
+            get_ids(&chunks),
+            vec![
+                vec![ChainId::Mainnet, ChainId::Polygon],
+                vec![ChainId::Arbitrum, ChainId::Optimism]
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_chains_with_different_rates_apart() {
+        let chains = [
+            chain(ChainId::Mainnet),
+            chain(ChainId::Arbitrum).with_ingestion_rate_ms(2_000),
+            chain(ChainId::Polygon),
+            chain(ChainId::Optimism).with_ingestion_rate_ms(2_000),
+        ];
+
+        let chunks = chunk_by_rate(&chains, 1, |c| c.ingestion_rate_ms.unwrap_or(20_000));
+
+        assert_eq!(
+            get_ids(&chunks),
+            vec![
+                vec![ChainId::Arbitrum, ChainId::Optimism],
+                vec![ChainId::Mainnet, ChainId::Polygon]
+            ]
+        );
+    }
 }
@@ -2,15 +2,34 @@ use std::sync::Arc;
 
 use tokio::sync::Mutex;
 
-use crate::chain_reorg::MinConfirmationCount;
+use crate::chain_reorg::Finality;
 use crate::chains::Chain;
-use crate::nodes::{self, NodeHeartbeat};
+use crate::contracts::{self, ContractAddressError, ContractAddressStatus};
+use crate::handlers::block_handler::ChainBlockHandler;
+use crate::ingester::RetryPolicy;
+use crate::nodes::{self, NodeHeartbeat, NodeTaskError, NodeTaskErrors};
 use crate::pruning::PruningConfig;
-use crate::{ChaindexingRepo, Contract};
+use crate::{
+    BlockHandler, ChainId, ChaindexingRepo, Contract, ExecutesWithRawQuery, HasRawQueryClient,
+};
 
+#[derive(Clone)]
 pub enum ConfigError {
     NoContract,
     NoChain,
+    NoJsonRpcUrl(ChainId),
+    ZeroBlockHandlerInterval,
+    /// A contract's JSON ABI has no such event, by the contract's name and the event
+    UnknownEvent(String, String),
+    /// A contract's JSON ABI has several events by the name
+    OverloadedEvent(String, String),
+    /// A contract's event ABI is neither human-readable nor in its JSON ABI
+    InvalidEventAbi(String, String),
+    /// A spawned contract's spawning event has no such address parameter,
+    /// by the spawned contract's name, the event ABI and the parameter
+    NoSpawnedAddressParam(String, String, String),
+    /// A contract's version has more than event and side-effect handlers
+    UnsupportedContractVersion(String),
 }
 
 impl std::fmt::Debug for ConfigError {
@@ -22,6 +41,36 @@ impl std::fmt::Debug for ConfigError {
             ConfigError::NoChain => {
                 write!(f, "At least one chain is required")
             }
+            ConfigError::NoJsonRpcUrl(chain_id) => {
+                write!(f, "At least one JSON-RPC URL is required for {chain_id:?}")
+            }
+            ConfigError::ZeroBlockHandlerInterval => {
+                write!(f, "Block handlers' interval must be at least one block")
+            }
+            ConfigError::UnknownEvent(contract_name, event) => {
+                write!(f, "{contract_name} has no {event} event")
+            }
+            ConfigError::OverloadedEvent(contract_name, event) => {
+                write!(
+                    f,
+                    "{contract_name}'s {event} event is overloaded, use one of its signatures instead"
+                )
+            }
+            ConfigError::InvalidEventAbi(contract_name, event_abi) => {
+                write!(f, "{contract_name}'s event ABI is invalid: {event_abi}")
+            }
+            ConfigError::NoSpawnedAddressParam(contract_name, event_abi, address_param) => {
+                write!(
+                    f,
+                    "{contract_name}'s spawning event has no {address_param} address parameter: {event_abi}"
+                )
+            }
+            ConfigError::UnsupportedContractVersion(contract_name) => {
+                write!(
+                    f,
+                    "{contract_name}'s versions can only have event and side-effect handlers"
+                )
+            }
         }
     }
 }
@@ -53,8 +102,11 @@ pub struct Config<SharedState: Sync + Send + Clone> {
     pub chains: Vec<Chain>,
     pub repo: ChaindexingRepo,
     pub contracts: Vec<Contract<SharedState>>,
-    pub(crate) min_confirmation_count: MinConfirmationCount,
+    pub(crate) block_handlers: Vec<ChainBlockHandler>,
+    pub(crate) finality: Finality,
+    pub(crate) retry_policy: RetryPolicy,
     pub blocks_per_batch: u64,
+    pub max_addresses_per_filter: usize,
     pub handler_rate_ms: u64,
     pub ingestion_rate_ms: u64,
     pub chain_concurrency: u32,
@@ -66,6 +118,7 @@ pub struct Config<SharedState: Sync + Send + Clone> {
     pub max_concurrent_node_count: u16,
     pub optimization_config: Option<OptimizationConfig>,
     pub(crate) pruning_config: Option<PruningConfig>,
+    pub(crate) node_task_errors: NodeTaskErrors,
 }
 
 impl<SharedState: Sync + Send + Clone> Config<SharedState> {
@@ -74,8 +127,11 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
             repo,
             chains: vec![],
             contracts: vec![],
-            min_confirmation_count: MinConfirmationCount::new(40),
+            block_handlers: vec![],
+            finality: Finality::MinConfirmationCount(40),
+            retry_policy: RetryPolicy::default(),
             blocks_per_batch: 8_000,
+            max_addresses_per_filter: 100,
             handler_rate_ms: 4_000,
             ingestion_rate_ms: 20_000,
             chain_concurrency: 4,
@@ -87,6 +143,7 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
             max_concurrent_node_count: nodes::DEFAULT_MAX_CONCURRENT_NODE_COUNT,
             optimization_config: None,
             pruning_config: None,
+            node_task_errors: NodeTaskErrors::new(),
         }
     }
 
@@ -98,12 +155,29 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
     }
 
     // Includes contract in config
-    pub fn add_contract(mut self, contract: Contract<SharedState>) -> Self {
+    pub fn add_contract(mut self, mut contract: Contract<SharedState>) -> Self {
+        for other_contract in self.contracts.iter_mut() {
+            contract.link_factory(other_contract);
+            other_contract.link_factory(&mut contract);
+        }
         self.contracts.push(contract);
 
         self
     }
 
+    /// Includes a block handler, run every `interval` blocks of the chain.
+    /// An interval of 1 runs it on every block.
+    pub fn add_block_handler(
+        mut self,
+        chain_id: &ChainId,
+        interval: u64,
+        handler: impl BlockHandler + 'static,
+    ) -> Self {
+        self.block_handlers.push(ChainBlockHandler::new(chain_id, interval, handler));
+
+        self
+    }
+
     /// Allows managing derived app states (derived from indexed states)
     pub fn add_reset_query(mut self, reset_query: &str) -> Self {
         self.reset_queries.push(reset_query.to_string());
@@ -134,23 +208,43 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
         self
     }
 
-    /// The minimum confirmation count for detecting chain-reorganizations or uncled blocks
+    /// The minimum confirmation count for detecting chain-reorganizations or uncled blocks.
+    /// Applies to chains without a finality of their own. See `Chain::with_finality`
+    /// and `Chain::with_min_confirmation_count`.
     pub fn with_min_confirmation_count(mut self, min_confirmation_count: u8) -> Self {
-        self.min_confirmation_count = MinConfirmationCount::new(min_confirmation_count);
+        self.finality = Finality::MinConfirmationCount(min_confirmation_count);
+
+        self
+    }
+
+    /// Decides how failed JSON-RPC requests get retried. Applies to chains
+    /// without a retry policy of their own. See `Chain::with_retry_policy`.
+    /// Default retries 10 times, backing off for up to 60_000ms.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
 
         self
     }
 
     /// Advance config: How many blocks per batch should be ingested and handled.
-    /// Default is 8_000
+    /// Default is 8_000. See `Chain::with_blocks_per_batch` to override per chain.
     pub fn with_blocks_per_batch(mut self, blocks_per_batch: u64) -> Self {
         self.blocks_per_batch = blocks_per_batch;
 
         self
     }
 
+    /// Advance config: How many contract addresses sharing the same events and
+    /// blocks to ingest can be fetched with a single `eth_getLogs` request.
+    /// Default is 100
+    pub fn with_max_addresses_per_filter(mut self, max_addresses_per_filter: usize) -> Self {
+        self.max_addresses_per_filter = max_addresses_per_filter;
+
+        self
+    }
+
     /// Advance config: How often should the events handlers processes run.
-    /// Default is 4_000
+    /// Default is 4_000. See `Chain::with_handler_rate_ms` to override per chain.
     pub fn with_handler_rate_ms(mut self, handler_rate_ms: u64) -> Self {
         self.handler_rate_ms = handler_rate_ms;
 
@@ -158,7 +252,7 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
     }
 
     /// Advance config:  How often should the events ingester processes run.
-    /// Default is 20_000
+    /// Default is 20_000. See `Chain::with_ingestion_rate_ms` to override per chain.
     pub fn with_ingestion_rate_ms(mut self, ingestion_rate_ms: u64) -> Self {
         self.ingestion_rate_ms = ingestion_rate_ms;
 
@@ -221,6 +315,98 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
         self.optimization_config.is_some()
     }
 
+    /// Returns the most recent errors of failed ingestion and handler loops,
+    /// oldest first. Failed loops get restarted, so indexing carries on.
+    pub async fn get_node_task_errors(&self) -> Vec<NodeTaskError> {
+        self.node_task_errors.get_all().await
+    }
+
+    /// Stops indexing the contract address for good, see `chaindexing::exclude_contract`
+    pub async fn exclude_contract(
+        &self,
+        chain_id: &ChainId,
+        address: &str,
+    ) -> Result<(), ContractAddressError> {
+        self.update_contract_address_status(chain_id, address, ContractAddressStatus::Excluded)
+            .await
+    }
+    /// Pauses indexing the contract address, see `chaindexing::pause_contract`
+    pub async fn pause_contract(
+        &self,
+        chain_id: &ChainId,
+        address: &str,
+    ) -> Result<(), ContractAddressError> {
+        self.update_contract_address_status(chain_id, address, ContractAddressStatus::Paused)
+            .await
+    }
+    /// Resumes indexing the paused contract address, see `chaindexing::resume_contract`
+    pub async fn resume_contract(
+        &self,
+        chain_id: &ChainId,
+        address: &str,
+    ) -> Result<(), ContractAddressError> {
+        self.update_contract_address_status(chain_id, address, ContractAddressStatus::Active)
+            .await
+    }
+    async fn update_contract_address_status(
+        &self,
+        chain_id: &ChainId,
+        address: &str,
+        status: ContractAddressStatus,
+    ) -> Result<(), ContractAddressError> {
+        let address = contracts::parse_address(address)?;
+        let client = self.repo.get_client().await;
+
+        ChaindexingRepo::update_contract_address_status(
+            &client,
+            &address,
+            *chain_id as u64,
+            status,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_chain(&self, chain_id: &ChainId) -> Option<&Chain> {
+        self.chains.iter().find(|c| c.id == *chain_id)
+    }
+
+    /// Returns the chain's finality, falling back to the minimum confirmation count
+    pub(crate) fn get_finality(&self, chain_id: &ChainId) -> &Finality {
+        self.get_chain(chain_id)
+            .and_then(|c| c.finality.as_ref())
+            .unwrap_or(&self.finality)
+    }
+
+    /// Returns the chain's blocks per batch, falling back to the config's
+    pub(crate) fn get_blocks_per_batch(&self, chain_id: &ChainId) -> u64 {
+        self.get_chain(chain_id)
+            .and_then(|c| c.blocks_per_batch)
+            .unwrap_or(self.blocks_per_batch)
+    }
+
+    /// Returns the chain's ingestion rate, falling back to the config's
+    pub(crate) fn get_ingestion_rate_ms(&self, chain_id: &ChainId) -> u64 {
+        self.get_chain(chain_id)
+            .and_then(|c| c.ingestion_rate_ms)
+            .unwrap_or(self.ingestion_rate_ms)
+    }
+
+    /// Returns the chain's handler rate, falling back to the config's
+    pub(crate) fn get_handler_rate_ms(&self, chain_id: &ChainId) -> u64 {
+        self.get_chain(chain_id)
+            .and_then(|c| c.handler_rate_ms)
+            .unwrap_or(self.handler_rate_ms)
+    }
+
+    /// Returns the chain's retry policy, falling back to the config's
+    pub(crate) fn get_retry_policy(&self, chain_id: &ChainId) -> &RetryPolicy {
+        self.get_chain(chain_id)
+            .and_then(|c| c.retry_policy.as_ref())
+            .unwrap_or(&self.retry_policy)
+    }
+
     pub(super) fn get_node_election_rate_ms(&self) -> u64 {
         self.node_election_rate_ms.unwrap_or(self.ingestion_rate_ms)
     }
@@ -228,8 +414,14 @@ impl<SharedState: Sync + Send + Clone> Config<SharedState> {
     pub(super) fn validate(&self) -> Result<(), ConfigError> {
         if self.contracts.is_empty() {
             Err(ConfigError::NoContract)
+        } else if let Some(error) = self.contracts.iter().find_map(|c| c.get_config_error()) {
+            Err(error.clone())
         } else if self.chains.is_empty() {
             Err(ConfigError::NoChain)
+        } else if let Some(chain) = self.chains.iter().find(|c| c.json_rpc_urls.is_empty()) {
+            Err(ConfigError::NoJsonRpcUrl(chain.id))
+        } else if self.block_handlers.iter().any(|h| h.interval == 0) {
+            Err(ConfigError::ZeroBlockHandlerInterval)
         } else {
             Ok(())
         }
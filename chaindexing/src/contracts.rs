@@ -1,22 +1,26 @@
 use std::fmt::Debug;
 use std::{collections::HashMap, str::FromStr, sync::Arc};
 
+use crate::config::ConfigError;
 use crate::diesel::schema::chaindexing_contract_addresses;
-use crate::handlers::PureHandler;
+use crate::handlers::{CallHandler, PureHandler, TopicFilter, TransactionHandler};
 use crate::states::StateMigrations;
 use crate::ChainId;
 use crate::{EventHandler, SideEffectHandler};
 use diesel::{Identifiable, Insertable, Queryable};
 
-use ethers::types::U64;
+use ethers::types::{Topic, ValueOrArray, U64};
 use ethers::{
-    abi::{Address, Event, HumanReadableParser},
+    abi::{Abi, Address, Event, Function, HumanReadableParser, ParamType},
     types::H256,
 };
 use serde::Deserialize;
 
 pub type ContractEventTopic = H256;
 
+/// Topics of the logs to ingest, in `eth_getLogs` filter's format
+pub type LogTopics = [Option<Topic>; 4];
+
 #[derive(Debug, Clone)]
 pub struct ContractEvent {
     pub abi: String,
@@ -30,20 +34,121 @@ impl ContractEvent {
             value: HumanReadableParser::parse_event(abi).unwrap(),
         }
     }
+
+    /// Events sharing a signature, such as ERC20's and ERC721's `Transfer`,
+    /// can still differ in how many of their parameters are indexed
+    pub(crate) fn get_topics_count(&self) -> usize {
+        let indexed_params_count = self.value.inputs.iter().filter(|i| i.indexed).count();
+
+        if self.value.anonymous {
+            indexed_params_count
+        } else {
+            indexed_params_count + 1
+        }
+    }
 }
 
+/// Identifies the event ABI to decode a contract's logs with, by the contract's
+/// name, the index of its version, see `Contract::add_version`, the log's signature
+/// topic and the log's topics count. Anonymous events have no signature topic.
+pub type ContractEventKey = (String, usize, Option<ContractEventTopic>, usize);
+
 /// Human Readable ABI defined for ingesting events.
 /// For example, `event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)`
 pub type EventAbi = &'static str;
 
+/// Selector of a function, i.e. the first four bytes of a transaction's calldata
+pub type ContractFunctionSelector = [u8; 4];
+
+/// Identifies the function ABI to decode a contract's calldata with, by the
+/// contract's name and the calldata's selector
+pub type ContractFunctionKey = (String, ContractFunctionSelector);
+
+#[derive(Debug, Clone)]
+pub struct ContractFunction {
+    pub abi: String,
+    pub value: Function,
+}
+
+impl ContractFunction {
+    pub fn new(abi: &str) -> Self {
+        Self {
+            abi: abi.to_string(),
+            value: HumanReadableParser::parse_function(abi).unwrap(),
+        }
+    }
+}
+
+/// Human Readable ABI defined for ingesting transactions and calls.
+/// For example, `function transferFrom(address from, address to, uint256 tokenId)`
+pub type FunctionAbi = &'static str;
+
 /// Represents the template/specification/interface for a given contract.
 #[derive(Clone)]
 pub struct Contract<S: Send + Sync + Clone> {
     pub addresses: Vec<UnsavedContractAddress>,
     pub name: String,
-    pub pure_handlers: HashMap<EventAbi, Arc<dyn PureHandler>>,
-    pub side_effect_handlers: HashMap<EventAbi, Arc<dyn SideEffectHandler<SharedState = S>>>,
+    /// Handlers by their events' human-readable ABIs, resolved as the handlers get added
+    pub pure_handlers: HashMap<String, Arc<dyn PureHandler>>,
+    pub side_effect_handlers: HashMap<String, Arc<dyn SideEffectHandler<SharedState = S>>>,
+    pub transaction_handlers: HashMap<FunctionAbi, Arc<dyn TransactionHandler>>,
+    pub call_handlers: HashMap<FunctionAbi, Arc<dyn CallHandler>>,
     pub state_migrations: Vec<Arc<dyn StateMigrations>>,
+    /// Log topics of handlers with topic filters, resolved as the handlers get added
+    pure_handler_log_topics: HashMap<String, LogTopics>,
+    side_effect_handler_log_topics: HashMap<String, LogTopics>,
+    /// Resolves the events of handlers going by event names
+    json_abi: Option<Abi>,
+    /// The first misconfiguration found while building the contract,
+    /// reported when validating the config
+    config_error: Option<ConfigError>,
+    versions: Vec<ContractVersion<S>>,
+    spawners: Vec<ContractSpawner>,
+    /// Events spawning other contracts, ingested even without handlers
+    spawning_event_abis: Vec<String>,
+}
+
+/// A factory contract's event spawning the contract, see `Contract::spawned_by`
+#[derive(Clone, Debug)]
+struct ContractSpawner {
+    factory_contract_name: String,
+    event_abi: String,
+    address_param: String,
+}
+
+impl ContractSpawner {
+    /// Returns the event ABI if the event has the spawner's address parameter
+    fn validate_address_param(
+        &self,
+        contract_name: &str,
+        event_abi: String,
+    ) -> Result<String, ConfigError> {
+        let has_address_param = HumanReadableParser::parse_event(&event_abi).is_ok_and(|event| {
+            event
+                .inputs
+                .iter()
+                .any(|p| p.name == self.address_param && p.kind == ParamType::Address)
+        });
+
+        if has_address_param {
+            Ok(event_abi)
+        } else {
+            Err(ConfigError::NoSpawnedAddressParam(
+                contract_name.to_string(),
+                event_abi,
+                self.address_param.clone(),
+            ))
+        }
+    }
+}
+
+/// A contract's version its address switches to from the block number on
+#[derive(Clone)]
+struct ContractVersion<S: Send + Sync + Clone> {
+    chain_id: i64,
+    address: String,
+    from_block_number: u64,
+    contract: Contract<S>,
 }
 
 impl<S: Send + Sync + Clone> Contract<S> {
@@ -63,9 +168,53 @@ impl<S: Send + Sync + Clone> Contract<S> {
             name: name.to_string(),
             pure_handlers: HashMap::new(),
             side_effect_handlers: HashMap::new(),
+            transaction_handlers: HashMap::new(),
+            call_handlers: HashMap::new(),
+            pure_handler_log_topics: HashMap::new(),
+            side_effect_handler_log_topics: HashMap::new(),
+            json_abi: None,
+            config_error: None,
+            versions: vec![],
+            spawners: vec![],
+            spawning_event_abis: vec![],
         }
     }
 
+    /// Builds the contract's template/spec/interface from its standard JSON ABI,
+    /// i.e. a Foundry/Hardhat artifact or just its `abi` array. Event handlers
+    /// can then go by their events' names instead of human-readable ABIs.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::Contract;
+    ///
+    /// let json_abi = r#"[{
+    ///     "type": "event",
+    ///     "name": "Transfer",
+    ///     "anonymous": false,
+    ///     "inputs": [
+    ///         { "name": "from", "type": "address", "indexed": true },
+    ///         { "name": "to", "type": "address", "indexed": true },
+    ///         { "name": "value", "type": "uint256", "indexed": false }
+    ///     ]
+    /// }]"#;
+    ///
+    /// Contract::<()>::from_json_abi("ERC20", json_abi).unwrap();
+    /// ```
+    pub fn from_json_abi(name: &str, json_abi: &str) -> Result<Self, serde_json::Error> {
+        let json_abi = match serde_json::from_str(json_abi)? {
+            serde_json::Value::Object(mut artifact) if artifact.contains_key("abi") => {
+                artifact.remove("abi").unwrap()
+            }
+            json_abi => json_abi,
+        };
+
+        Ok(Self {
+            json_abi: Some(serde_json::from_value(json_abi)?),
+            ..Self::new(name)
+        })
+    }
+
     /// Adds a contract address to a contract
     pub fn add_address(
         mut self,
@@ -83,9 +232,133 @@ impl<S: Send + Sync + Clone> Contract<S> {
         self
     }
 
+    /// Adds a contract address whose ingestion stops at the end block,
+    /// e.g. for deprecated contracts
+    pub fn add_address_with_end_block(
+        mut self,
+        address: &str,
+        chain_id: &ChainId,
+        start_block_number: u64,
+        end_block_number: u64,
+    ) -> Self {
+        self.addresses.push(
+            UnsavedContractAddress::new(&self.name, address, chain_id, start_block_number)
+                .with_end_block_number(end_block_number),
+        );
+
+        self
+    }
+
+    /// Switches the address to the version's event ABIs and handlers from the
+    /// block number on, e.g. when upgrading the implementation of a proxy.
+    /// Versions are contract templates too, whose addresses and names get ignored.
+    /// Events get handled by the handlers of the address' version at their blocks.
+    ///
+    /// Versions can only have event and side-effect handlers. Transaction handlers,
+    /// call handlers, state migrations and spawners belong to the contract itself,
+    /// and versions with them get reported when validating the config.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::{ChainId, Contract};
+    ///
+    /// const PROXY_ADDRESS: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+    ///
+    /// Contract::<()>::new("Pool")
+    ///     .add_address(PROXY_ADDRESS, &ChainId::Mainnet, 10_000_000)
+    ///     .add_version(PROXY_ADDRESS, &ChainId::Mainnet, 12_000_000, Contract::new("PoolV2"));
+    /// ```
+    pub fn add_version(
+        mut self,
+        address: &str,
+        chain_id: &ChainId,
+        from_block_number: u64,
+        version: Contract<S>,
+    ) -> Self {
+        let has_unsupported_parts = !version.transaction_handlers.is_empty()
+            || !version.call_handlers.is_empty()
+            || !version.state_migrations.is_empty()
+            || !version.spawners.is_empty();
+        if has_unsupported_parts {
+            let error = ConfigError::UnsupportedContractVersion(self.name.clone());
+            self.config_error.get_or_insert(error);
+        }
+
+        self.versions.push(ContractVersion {
+            chain_id: *chain_id as i64,
+            address: address.to_lowercase(),
+            from_block_number,
+            contract: version,
+        });
+
+        self
+    }
+
+    /// Declares the factory contract's event spawning the contract, whose address
+    /// is the event's address parameter. Spawned contract addresses get included
+    /// as the factory's events get ingested, from the events' block numbers on.
+    /// Both contracts have to be added to the config.
+    ///
+    /// # Example
+    /// ```
+    /// use chaindexing::Contract;
+    ///
+    /// Contract::<()>::new("UniswapV3Pool").spawned_by(
+    ///     "UniswapV3Factory",
+    ///     "event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)",
+    ///     "pool",
+    /// );
+    /// ```
+    pub fn spawned_by(
+        mut self,
+        factory_contract_name: &str,
+        event_abi: EventAbi,
+        address_param: &str,
+    ) -> Self {
+        self.spawners.push(ContractSpawner {
+            factory_contract_name: factory_contract_name.to_string(),
+            event_abi: event_abi.to_string(),
+            address_param: address_param.to_string(),
+        });
+
+        self
+    }
+
+    /// Links the contract's spawners to the factory, so the factory ingests
+    /// the spawning events with their ABIs resolved. Spawning events without
+    /// the spawners' address parameters get reported when validating the config.
+    pub(crate) fn link_factory(&mut self, factory: &mut Contract<S>) {
+        for spawner in self.spawners.iter_mut() {
+            if spawner.factory_contract_name == factory.name {
+                let resolved_event_abi = factory
+                    .resolve_event_abi(&spawner.event_abi)
+                    .and_then(|event_abi| spawner.validate_address_param(&self.name, event_abi));
+
+                match resolved_event_abi {
+                    Ok(event_abi) => spawner.event_abi = event_abi,
+                    Err(error) => {
+                        self.config_error.get_or_insert(error);
+                        continue;
+                    }
+                }
+
+                if !factory.spawning_event_abis.contains(&spawner.event_abi) {
+                    factory.spawning_event_abis.push(spawner.event_abi.clone());
+                }
+            }
+        }
+    }
+
     /// Adds an event handler
     pub fn add_event_handler(mut self, handler: impl EventHandler + 'static) -> Self {
-        self.pure_handlers.insert(handler.abi(), Arc::new(handler));
+        let Some(event_abi) = self.resolve_handler_event_abi(handler.abi()) else {
+            return self;
+        };
+        match self.resolve_log_topics(&event_abi, handler.topic_filter()) {
+            Some(log_topics) => self.pure_handler_log_topics.insert(event_abi.clone(), log_topics),
+            None => self.pure_handler_log_topics.remove(&event_abi),
+        };
+        self.pure_handlers.insert(event_abi, Arc::new(handler));
 
         self
     }
@@ -95,7 +368,30 @@ impl<S: Send + Sync + Clone> Contract<S> {
         mut self,
         handler: impl SideEffectHandler<SharedState = S> + 'static,
     ) -> Self {
-        self.side_effect_handlers.insert(handler.abi(), Arc::new(handler));
+        let Some(event_abi) = self.resolve_handler_event_abi(handler.abi()) else {
+            return self;
+        };
+        match self.resolve_log_topics(&event_abi, handler.topic_filter()) {
+            Some(log_topics) => {
+                self.side_effect_handler_log_topics.insert(event_abi.clone(), log_topics)
+            }
+            None => self.side_effect_handler_log_topics.remove(&event_abi),
+        };
+        self.side_effect_handlers.insert(event_abi, Arc::new(handler));
+
+        self
+    }
+
+    /// Adds a transaction handler
+    pub fn add_transaction_handler(mut self, handler: impl TransactionHandler + 'static) -> Self {
+        self.transaction_handlers.insert(handler.abi(), Arc::new(handler));
+
+        self
+    }
+
+    /// Adds a call handler
+    pub fn add_call_handler(mut self, handler: impl CallHandler + 'static) -> Self {
+        self.call_handlers.insert(handler.abi(), Arc::new(handler));
 
         self
     }
@@ -107,26 +403,223 @@ impl<S: Send + Sync + Clone> Contract<S> {
         self
     }
 
-    pub(crate) fn get_event_abis(&self) -> Vec<EventAbi> {
+    /// Encodes the handler's topic filter as its event's log topics.
+    /// Panics for filters on parameters that are not indexed, so
+    /// misconfigured handlers fail when building the contract.
+    fn resolve_log_topics(
+        &self,
+        event_abi: &str,
+        topic_filter: Option<TopicFilter>,
+    ) -> Option<LogTopics> {
+        let topic_filter = topic_filter?;
+        let event = HumanReadableParser::parse_event(event_abi).unwrap();
+
+        let log_topics = topic_filter
+            .to_topics(&event)
+            .unwrap_or_else(|error| panic!("{}'s topic filter: {}", self.name, error));
+
+        Some(log_topics)
+    }
+
+    /// Resolves the handler's event ABI, recording the error for the config's
+    /// validation if it cannot get resolved
+    fn resolve_handler_event_abi(&mut self, event_abi: EventAbi) -> Option<String> {
+        self.resolve_event_abi(event_abi)
+            .map_err(|error| self.config_error.get_or_insert(error))
+            .ok()
+    }
+
+    /// Resolves event names and signatures to human-readable ABIs with the
+    /// contract's JSON ABI. Other event ABIs are already human-readable.
+    fn resolve_event_abi(&self, event_abi: &str) -> Result<String, ConfigError> {
+        let is_human_readable = event_abi.contains(char::is_whitespace);
+
+        let Some(json_abi) = self.json_abi.as_ref().filter(|_| !is_human_readable) else {
+            return match HumanReadableParser::parse_event(event_abi) {
+                Ok(_event) => Ok(event_abi.to_string()),
+                Err(_error) => Err(ConfigError::InvalidEventAbi(
+                    self.name.clone(),
+                    event_abi.to_string(),
+                )),
+            };
+        };
+
+        let unknown_event = || ConfigError::UnknownEvent(self.name.clone(), event_abi.to_string());
+
+        let event = if event_abi.contains('(') {
+            json_abi
+                .events()
+                .find(|e| get_event_signature(e) == event_abi)
+                .ok_or_else(unknown_event)?
+        } else {
+            match json_abi.events_by_name(event_abi).map_err(|_| unknown_event())? {
+                events if events.len() == 1 => &events[0],
+                _events => {
+                    return Err(ConfigError::OverloadedEvent(
+                        self.name.clone(),
+                        event_abi.to_string(),
+                    ))
+                }
+            }
+        };
+
+        Ok(to_human_readable_event_abi(event))
+    }
+
+    /// Returns the first misconfiguration of the contract or its versions, if any
+    pub(crate) fn get_config_error(&self) -> Option<&ConfigError> {
+        self.get_versions().find_map(|version| version.config_error.as_ref())
+    }
+
+    /// Returns the index of the address' version at the block number,
+    /// where 0 is the contract itself and `i` is its `i`th added version
+    pub(crate) fn get_version_index(
+        &self,
+        chain_id: i64,
+        address: &str,
+        block_number: u64,
+    ) -> usize {
+        self.versions
+            .iter()
+            .enumerate()
+            .filter(|(_index, v)| {
+                v.chain_id == chain_id
+                    && v.address == address.to_lowercase()
+                    && v.from_block_number <= block_number
+            })
+            .max_by_key(|(index, v)| (v.from_block_number, *index))
+            .map(|(index, _version)| index + 1)
+            .unwrap_or(0)
+    }
+
+    /// Returns the contract itself followed by its versions
+    fn get_versions(&self) -> impl Iterator<Item = &Contract<S>> {
+        std::iter::once(self).chain(self.versions.iter().map(|v| &v.contract))
+    }
+
+    /// Splits the block range wherever the address switches versions,
+    /// returning each part's block range with its version's log topics
+    pub(crate) fn get_log_topics_by_block_ranges(
+        &self,
+        chain_id: i64,
+        address: &str,
+        from_block_number: u64,
+        to_block_number: u64,
+    ) -> Vec<(u64, u64, Vec<LogTopics>)> {
+        let mut switch_block_numbers: Vec<_> = self
+            .versions
+            .iter()
+            .filter(|v| v.chain_id == chain_id && v.address == address.to_lowercase())
+            .map(|v| v.from_block_number)
+            .filter(|n| *n > from_block_number && *n <= to_block_number)
+            .collect();
+        switch_block_numbers.sort();
+        switch_block_numbers.dedup();
+
+        let from_block_numbers = std::iter::once(from_block_number).chain(switch_block_numbers);
+        let mut block_ranges: Vec<_> = from_block_numbers.map(|n| (n, to_block_number)).collect();
+        for index in 1..block_ranges.len() {
+            block_ranges[index - 1].1 = block_ranges[index].0 - 1;
+        }
+
+        let versions: Vec<_> = self.get_versions().collect();
+
+        block_ranges
+            .into_iter()
+            .map(|(from_block_number, to_block_number)| {
+                let version_index = self.get_version_index(chain_id, address, from_block_number);
+
+                (
+                    from_block_number,
+                    to_block_number,
+                    versions[version_index].get_log_topics(),
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn get_event_abis(&self) -> Vec<String> {
         let mut event_abis: Vec<_> = self.pure_handlers.clone().into_keys().collect();
-        let side_effect_abis: Vec<_> = self.pure_handlers.clone().into_keys().collect();
+        let side_effect_abis: Vec<_> = self.side_effect_handlers.clone().into_keys().collect();
 
         event_abis.extend(side_effect_abis);
+        event_abis.extend(self.spawning_event_abis.clone());
+        event_abis.sort();
         event_abis.dedup();
 
         event_abis
     }
 
-    pub(crate) fn get_event_topics(&self) -> Vec<ContractEventTopic> {
-        self.get_event_abis()
-            .iter()
-            .map(|abi| HumanReadableParser::parse_event(abi).unwrap().signature())
-            .collect()
+    /// Events without topic filters share the same log topics. Each topic filter
+    /// on the other events gets its own log topics.
+    pub(crate) fn get_log_topics(&self) -> Vec<LogTopics> {
+        let mut unfiltered_event_topics = vec![];
+        let mut log_topics = vec![];
+
+        for event_abi in self.get_event_abis() {
+            let event = HumanReadableParser::parse_event(&event_abi).unwrap();
+
+            match self.get_filtered_log_topics(&event_abi) {
+                // Anonymous events have no signature to filter logs by
+                None if event.anonymous => {
+                    let topics = [None, None, None, None];
+
+                    if !log_topics.contains(&topics) {
+                        log_topics.push(topics);
+                    }
+                }
+                Some(filtered_log_topics) => {
+                    for topics in filtered_log_topics {
+                        if !log_topics.contains(&topics) {
+                            log_topics.push(topics);
+                        }
+                    }
+                }
+                None => unfiltered_event_topics.push(event.signature()),
+            }
+        }
+
+        if !unfiltered_event_topics.is_empty() {
+            log_topics.insert(0, [Some(unfiltered_event_topics.into()), None, None, None]);
+        }
+
+        log_topics
+    }
+
+    /// An event gets filtered only when all its handlers have topic filters.
+    /// Spawning events never get filtered, as every spawned contract gets included.
+    fn get_filtered_log_topics(&self, event_abi: &str) -> Option<Vec<LogTopics>> {
+        if self.spawning_event_abis.iter().any(|abi| abi == event_abi) {
+            return None;
+        }
+
+        let pure_log_topics = self
+            .pure_handlers
+            .contains_key(event_abi)
+            .then(|| self.pure_handler_log_topics.get(event_abi).cloned());
+        let side_effect_log_topics = self
+            .side_effect_handlers
+            .contains_key(event_abi)
+            .then(|| self.side_effect_handler_log_topics.get(event_abi).cloned());
+
+        [pure_log_topics, side_effect_log_topics]
+            .into_iter()
+            .flatten()
+            .collect::<Option<Vec<_>>>()
+            .filter(|log_topics| !log_topics.is_empty())
     }
 
     pub(crate) fn build_events(&self) -> Vec<ContractEvent> {
         self.get_event_abis().iter().map(|abi| ContractEvent::new(abi)).collect()
     }
+
+    pub(crate) fn build_functions(&self) -> Vec<ContractFunction> {
+        self.transaction_handlers.keys().map(|abi| ContractFunction::new(abi)).collect()
+    }
+
+    pub(crate) fn build_call_functions(&self) -> Vec<ContractFunction> {
+        self.call_handlers.keys().map(|abi| ContractFunction::new(abi)).collect()
+    }
 }
 
 impl<S: Send + Sync + Clone> Debug for Contract<S> {
@@ -144,45 +637,219 @@ pub fn get_state_migrations<S: Send + Sync + Clone>(
     contracts.iter().flat_map(|c| c.state_migrations.clone()).collect()
 }
 
+/// Event handlers along with the log topics of their topic filters, if any,
+/// since the logs of an event get ingested for all of its handlers' filters
+pub type PureHandlerWithLogTopics = (Arc<dyn PureHandler>, Option<LogTopics>);
+pub type SideEffectHandlerWithLogTopics<S> = (
+    Arc<dyn SideEffectHandler<SharedState = S>>,
+    Option<LogTopics>,
+);
+
+/// Event handlers by their contracts' names, then the indices of their contracts'
+/// versions, see `Contract::get_version_index`, then their event ABIs, since
+/// different contracts, or versions of them, can handle the same event differently
+pub type VersionedEventHandlers<H> = HashMap<String, Vec<HashMap<String, H>>>;
+
 pub fn get_pure_handlers<S: Send + Sync + Clone>(
     contracts: &[Contract<S>],
-) -> HashMap<EventAbi, Arc<dyn PureHandler>> {
-    contracts.iter().fold(HashMap::new(), |mut handlers_by_event_abi, contract| {
-        contract.pure_handlers.iter().for_each(|(event_abi, handler)| {
-            handlers_by_event_abi.insert(event_abi, handler.clone());
-        });
-        handlers_by_event_abi
-    })
+) -> VersionedEventHandlers<PureHandlerWithLogTopics> {
+    contracts
+        .iter()
+        .map(|contract| {
+            let handlers_per_version = contract
+                .get_versions()
+                .map(|version| {
+                    version
+                        .pure_handlers
+                        .iter()
+                        .map(|(event_abi, handler)| {
+                            let log_topics = version.pure_handler_log_topics.get(event_abi);
+
+                            (event_abi.clone(), (handler.clone(), log_topics.cloned()))
+                        })
+                        .collect()
+                })
+                .collect();
+
+            (contract.name.clone(), handlers_per_version)
+        })
+        .collect()
 }
 
 pub fn get_side_effect_handlers<S: Send + Sync + Clone>(
     contracts: &[Contract<S>],
-) -> HashMap<EventAbi, Arc<dyn SideEffectHandler<SharedState = S>>> {
-    contracts.iter().fold(HashMap::new(), |mut handlers_by_event_abi, contract| {
-        contract.side_effect_handlers.iter().for_each(|(event_abi, handler)| {
-            handlers_by_event_abi.insert(event_abi, handler.clone());
-        });
-        handlers_by_event_abi
+) -> VersionedEventHandlers<SideEffectHandlerWithLogTopics<S>> {
+    contracts
+        .iter()
+        .map(|contract| {
+            let handlers_per_version = contract
+                .get_versions()
+                .map(|version| {
+                    version
+                        .side_effect_handlers
+                        .iter()
+                        .map(|(event_abi, handler)| {
+                            let log_topics = version.side_effect_handler_log_topics.get(event_abi);
+
+                            (event_abi.clone(), (handler.clone(), log_topics.cloned()))
+                        })
+                        .collect()
+                })
+                .collect();
+
+            (contract.name.clone(), handlers_per_version)
+        })
+        .collect()
+}
+
+/// Whether the log's topics match the log topics of a topic filter
+pub(crate) fn matches_log_topics(log_topics: &LogTopics, topics: &[H256]) -> bool {
+    log_topics.iter().enumerate().all(|(index, log_topic)| {
+        let topic = topics.get(index);
+
+        match log_topic {
+            None | Some(ValueOrArray::Value(None)) => true,
+            Some(ValueOrArray::Value(Some(hash))) => topic == Some(hash),
+            Some(ValueOrArray::Array(hashes)) => {
+                hashes.iter().any(|hash| hash.is_none() || hash.as_ref() == topic)
+            }
+        }
     })
 }
 
-pub fn group_event_topics_by_names<S: Send + Sync + Clone>(
+/// Contract addresses spawned by the factories' events, see `Contract::spawned_by`
+pub fn get_spawned_contract_addresses<S: Send + Sync + Clone>(
     contracts: &[Contract<S>],
-) -> HashMap<String, Vec<ContractEventTopic>> {
-    contracts.iter().fold(HashMap::new(), |mut topics_by_contract_name, contract| {
-        topics_by_contract_name.insert(contract.name.clone(), contract.get_event_topics());
+    events: &[crate::Event],
+) -> Vec<UnsavedContractAddress> {
+    let spawners: Vec<_> = contracts
+        .iter()
+        .flat_map(|contract| contract.spawners.iter().map(move |spawner| (contract, spawner)))
+        .collect();
 
-        topics_by_contract_name
-    })
+    events
+        .iter()
+        .flat_map(|event| {
+            spawners
+                .iter()
+                .filter(|(_contract, spawner)| {
+                    spawner.factory_contract_name == event.contract_name
+                        && spawner.event_abi == event.abi
+                })
+                .map(|(contract, spawner)| {
+                    UnsavedContractAddress::new(
+                        &contract.name,
+                        &event.get_params().get_address_string(&spawner.address_param),
+                        &event.get_chain_id(),
+                        event.get_block_number(),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Groups transaction handlers by their contract names, then their function ABIs,
+/// since different contracts can have functions with the same ABI
+pub fn get_transaction_handlers<S: Send + Sync + Clone>(
+    contracts: &[Contract<S>],
+) -> HashMap<String, HashMap<FunctionAbi, Arc<dyn TransactionHandler>>> {
+    contracts
+        .iter()
+        .map(|contract| (contract.name.clone(), contract.transaction_handlers.clone()))
+        .collect()
+}
+
+/// Groups call handlers by their contract names, then their function ABIs,
+/// like `get_transaction_handlers`
+pub fn get_call_handlers<S: Send + Sync + Clone>(
+    contracts: &[Contract<S>],
+) -> HashMap<String, HashMap<FunctionAbi, Arc<dyn CallHandler>>> {
+    contracts
+        .iter()
+        .map(|contract| (contract.name.clone(), contract.call_handlers.clone()))
+        .collect()
+}
+
+/// Groups events by their contract names and versions, signatures and topics counts,
+/// since different contracts, or versions of them, can have events with the same signature
+pub fn group_events_by_keys<S: Send + Sync + Clone>(
+    contracts: &[Contract<S>],
+) -> HashMap<ContractEventKey, ContractEvent> {
+    contracts
+        .iter()
+        .flat_map(|c| {
+            c.get_versions().enumerate().flat_map(move |(version_index, version)| {
+                version.build_events().into_iter().map(move |e| {
+                    let signature = (!e.value.anonymous).then(|| e.value.signature());
+
+                    (
+                        (
+                            c.name.clone(),
+                            version_index,
+                            signature,
+                            e.get_topics_count(),
+                        ),
+                        e,
+                    )
+                })
+            })
+        })
+        .collect()
+}
+
+/// Canonical signature of the event, e.g. `Transfer(address,address,uint256)`
+fn get_event_signature(event: &Event) -> String {
+    let param_types: Vec<_> = event.inputs.iter().map(|p| p.kind.to_string()).collect();
+
+    format!("{}({})", event.name, param_types.join(","))
 }
 
-pub fn group_events_by_topics<S: Send + Sync + Clone>(
+fn to_human_readable_event_abi(event: &Event) -> String {
+    let params: Vec<_> = event
+        .inputs
+        .iter()
+        .map(|p| {
+            let indexed = if p.indexed { " indexed" } else { "" };
+            let name = if p.name.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", p.name)
+            };
+
+            format!("{}{}{}", p.kind, indexed, name)
+        })
+        .collect();
+    let anonymous = if event.anonymous { " anonymous" } else { "" };
+
+    format!("event {}({}){}", event.name, params.join(", "), anonymous)
+}
+
+/// Groups functions by their contract names and selectors,
+/// since different contracts can have functions with the same selector
+pub fn group_functions_by_keys<S: Send + Sync + Clone>(
     contracts: &[Contract<S>],
-) -> HashMap<ContractEventTopic, ContractEvent> {
+) -> HashMap<ContractFunctionKey, ContractFunction> {
     contracts
         .iter()
-        .flat_map(|c| c.build_events())
-        .map(|e| (e.value.signature(), e))
+        .flat_map(|c| {
+            c.build_functions()
+                .into_iter()
+                .map(|f| ((c.name.clone(), f.value.short_signature()), f))
+        })
+        .collect()
+}
+
+/// Groups functions of call handlers like `group_functions_by_keys`
+pub fn group_call_functions_by_keys<S: Send + Sync + Clone>(
+    contracts: &[Contract<S>],
+) -> HashMap<ContractFunctionKey, ContractFunction> {
+    contracts
+        .iter()
+        .flat_map(|c| {
+            c.build_call_functions()
+                .into_iter()
+                .map(|f| ((c.name.clone(), f.value.short_signature()), f))
+        })
         .collect()
 }
 
@@ -194,6 +861,7 @@ pub struct UnsavedContractAddress {
     pub chain_id: i64,
     pub start_block_number: i64,
     next_block_number_to_ingest_from: i64,
+    pub end_block_number: Option<i64>,
 }
 
 impl UnsavedContractAddress {
@@ -211,8 +879,15 @@ impl UnsavedContractAddress {
             chain_id: *chain_id as i64,
             start_block_number,
             next_block_number_to_ingest_from: start_block_number,
+            end_block_number: None,
         }
     }
+
+    pub fn with_end_block_number(mut self, end_block_number: u64) -> Self {
+        self.end_block_number = Some(end_block_number as i64);
+
+        self
+    }
 }
 
 // N/B: The order has to match ./schema.rs to stop diesel from mixing up fields
@@ -229,6 +904,10 @@ pub struct ContractAddress {
     pub start_block_number: i64,
     pub address: String,
     pub contract_name: String,
+    #[diesel(deserialize_as = String)]
+    pub status: ContractAddressStatus,
+    /// Last block to ingest, if any
+    pub end_block_number: Option<i64>,
 }
 
 impl ContractAddress {
@@ -255,3 +934,524 @@ impl ContractAddress {
         )
     }
 }
+
+/// Errors from updating contract addresses
+pub enum ContractAddressError {
+    InvalidAddress(String),
+}
+
+impl Debug for ContractAddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContractAddressError::InvalidAddress(address) => {
+                write!(f, "{address} is not a valid contract address")
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_address(address: &str) -> Result<Address, ContractAddressError> {
+    address
+        .parse()
+        .map_err(|_| ContractAddressError::InvalidAddress(address.to_string()))
+}
+
+/// Whether a contract address is being indexed. Only active addresses get
+/// ingested and handled, see `chaindexing::pause_contract`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractAddressStatus {
+    Active,
+    /// Indexing resumes from where it stopped once the address is active again
+    Paused,
+    /// Indexing stops for good, even when the address gets included again
+    Excluded,
+    /// Every block up to the end block got ingested and is final.
+    /// The address' ingested events still get handled.
+    Completed,
+}
+
+impl ContractAddressStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContractAddressStatus::Active => "active",
+            ContractAddressStatus::Paused => "paused",
+            ContractAddressStatus::Excluded => "excluded",
+            ContractAddressStatus::Completed => "completed",
+        }
+    }
+
+    /// Statuses an address can get switched to this status from
+    pub(crate) fn get_switchable_statuses(&self) -> &'static [ContractAddressStatus] {
+        match self {
+            ContractAddressStatus::Active => &[ContractAddressStatus::Paused],
+            ContractAddressStatus::Paused => &[ContractAddressStatus::Active],
+            ContractAddressStatus::Excluded => &[
+                ContractAddressStatus::Active,
+                ContractAddressStatus::Paused,
+                ContractAddressStatus::Completed,
+            ],
+            ContractAddressStatus::Completed => &[ContractAddressStatus::Active],
+        }
+    }
+}
+
+impl TryFrom<String> for ContractAddressStatus {
+    type Error = String;
+
+    fn try_from(status: String) -> Result<Self, Self::Error> {
+        match status.as_str() {
+            "active" => Ok(ContractAddressStatus::Active),
+            "paused" => Ok(ContractAddressStatus::Paused),
+            "excluded" => Ok(ContractAddressStatus::Excluded),
+            "completed" => Ok(ContractAddressStatus::Completed),
+            _ => Err(format!("Unknown contract address status: {status}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod contracts_tests {
+    use super::*;
+    use crate::handlers::{SideEffectHandlerContext, TransactionHandlerContext};
+    use crate::{EventContext, U256};
+
+    struct TestHandler(EventAbi);
+
+    #[crate::augmenting_std::async_trait]
+    impl EventHandler for TestHandler {
+        fn abi(&self) -> &'static str {
+            self.0
+        }
+        async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl TransactionHandler for TestHandler {
+        fn abi(&self) -> &'static str {
+            self.0
+        }
+        async fn handle_transaction<'a>(&self, _context: TransactionHandlerContext<'a>) {}
+    }
+
+    struct FilteredTestHandler(EventAbi, TopicFilter);
+
+    #[crate::augmenting_std::async_trait]
+    impl EventHandler for FilteredTestHandler {
+        fn abi(&self) -> &'static str {
+            self.0
+        }
+        fn topic_filter(&self) -> Option<TopicFilter> {
+            Some(self.1.clone())
+        }
+        async fn handle_event<'a, 'b>(&self, _context: EventContext<'a, 'b>) {}
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl SideEffectHandler for FilteredTestHandler {
+        type SharedState = ();
+
+        fn abi(&self) -> &'static str {
+            self.0
+        }
+        fn topic_filter(&self) -> Option<TopicFilter> {
+            Some(self.1.clone())
+        }
+        async fn handle_event<'a>(&self, _context: SideEffectHandlerContext<'a, ()>) {}
+    }
+
+    const ARTIFACT: &str = r#"{
+        "abi": [
+            {
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "tokenId", "type": "uint256", "indexed": true }
+                ]
+            },
+            {
+                "type": "event",
+                "name": "Deposit",
+                "anonymous": false,
+                "inputs": [{ "name": "amount", "type": "uint256", "indexed": false }]
+            },
+            {
+                "type": "event",
+                "name": "Deposit",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "account", "type": "address", "indexed": true },
+                    { "name": "amount", "type": "uint256", "indexed": false }
+                ]
+            },
+            {
+                "type": "event",
+                "name": "OrderFilled",
+                "anonymous": true,
+                "inputs": [
+                    { "name": "maker", "type": "address", "indexed": true },
+                    {
+                        "name": "order",
+                        "type": "tuple",
+                        "indexed": false,
+                        "components": [
+                            { "name": "amount", "type": "uint256" },
+                            { "name": "tokens", "type": "address[]" }
+                        ]
+                    }
+                ]
+            }
+        ],
+        "bytecode": { "object": "0x" }
+    }"#;
+
+    fn get_json_event(name: &str, params_count: usize) -> Event {
+        let json_abi: serde_json::Value = serde_json::from_str(ARTIFACT).unwrap();
+        let json_abi: Abi = serde_json::from_value(json_abi["abi"].clone()).unwrap();
+
+        json_abi
+            .events_by_name(name)
+            .unwrap()
+            .iter()
+            .find(|e| e.inputs.len() == params_count)
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn resolves_event_names_with_json_abis() {
+        let contract = Contract::<()>::from_json_abi("BAYC", ARTIFACT)
+            .unwrap()
+            .add_event_handler(TestHandler("Transfer"));
+
+        assert_eq!(
+            contract.get_event_abis(),
+            vec![
+                "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_overloaded_events_by_signatures() {
+        let contract = Contract::<()>::from_json_abi("WETH", ARTIFACT)
+            .unwrap()
+            .add_event_handler(TestHandler("Deposit(address,uint256)"));
+
+        let contract_event = ContractEvent::new(&contract.get_event_abis()[0]);
+        assert_eq!(contract_event.value, get_json_event("Deposit", 2));
+    }
+
+    #[test]
+    fn does_not_resolve_overloaded_events_by_names() {
+        let contract = Contract::<()>::from_json_abi("WETH", ARTIFACT)
+            .unwrap()
+            .add_event_handler(TestHandler("Deposit"));
+
+        assert!(matches!(
+            contract.get_config_error(),
+            Some(ConfigError::OverloadedEvent(contract_name, event))
+                if contract_name == "WETH" && event == "Deposit"
+        ));
+        assert!(contract.get_event_abis().is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_events_and_signatures() {
+        for event_abi in ["Swap", "Deposit(address)"] {
+            let contract = Contract::<()>::from_json_abi("WETH", ARTIFACT)
+                .unwrap()
+                .add_event_handler(TestHandler(event_abi));
+
+            assert!(matches!(
+                contract.get_config_error(),
+                Some(ConfigError::UnknownEvent(contract_name, event))
+                    if contract_name == "WETH" && event == event_abi
+            ));
+        }
+    }
+
+    #[test]
+    fn reports_invalid_event_abis_without_json_abis() {
+        let contract = Contract::<()>::new("WETH").add_event_handler(TestHandler("Deposit"));
+
+        assert!(matches!(
+            contract.get_config_error(),
+            Some(ConfigError::InvalidEventAbi(_contract_name, _event_abi))
+        ));
+    }
+
+    #[test]
+    fn resolves_anonymous_events_with_tuple_params() {
+        let contract = Contract::<()>::from_json_abi("Exchange", ARTIFACT)
+            .unwrap()
+            .add_event_handler(TestHandler("OrderFilled"));
+
+        let contract_event = ContractEvent::new(&contract.get_event_abis()[0]);
+        assert_eq!(contract_event.value, get_json_event("OrderFilled", 2));
+        assert_eq!(contract_event.get_topics_count(), 1);
+        assert_eq!(
+            contract.get_log_topics(),
+            vec![[None, None, None, None] as LogTopics]
+        );
+    }
+
+    #[test]
+    fn keeps_human_readable_event_abis() {
+        let event_abi =
+            "event Approval(address indexed owner, address indexed spender, uint256 value)";
+        let contract = Contract::<()>::from_json_abi("ERC20", ARTIFACT)
+            .unwrap()
+            .add_event_handler(TestHandler(event_abi));
+
+        assert_eq!(contract.get_event_abis(), vec![event_abi]);
+    }
+
+    const PROXY_ADDRESS: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+
+    fn upgraded_contract() -> Contract<()> {
+        let erc721_transfer_abi =
+            "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)";
+        let erc20_transfer_abi =
+            "event Transfer(address indexed from, address indexed to, uint256 value)";
+
+        Contract::<()>::new("Token")
+            .add_event_handler(TestHandler(erc721_transfer_abi))
+            .add_version(
+                PROXY_ADDRESS,
+                &ChainId::Mainnet,
+                200,
+                Contract::new("TokenV2").add_event_handler(TestHandler(erc20_transfer_abi)),
+            )
+            .add_version(
+                PROXY_ADDRESS,
+                &ChainId::Mainnet,
+                300,
+                Contract::new("TokenV3"),
+            )
+    }
+
+    #[test]
+    fn gets_versions_of_addresses_by_block_numbers() {
+        let contract = upgraded_contract();
+        let chain_id = ChainId::Mainnet as i64;
+
+        assert_eq!(contract.get_version_index(chain_id, PROXY_ADDRESS, 199), 0);
+        assert_eq!(contract.get_version_index(chain_id, PROXY_ADDRESS, 200), 1);
+        assert_eq!(contract.get_version_index(chain_id, PROXY_ADDRESS, 350), 2);
+        assert_eq!(
+            contract.get_version_index(ChainId::Polygon as i64, PROXY_ADDRESS, 350),
+            0
+        );
+    }
+
+    #[test]
+    fn splits_block_ranges_at_version_switches() {
+        let contract = upgraded_contract();
+
+        let block_ranges: Vec<_> = contract
+            .get_log_topics_by_block_ranges(ChainId::Mainnet as i64, PROXY_ADDRESS, 150, 350)
+            .into_iter()
+            .map(|(from_block_number, to_block_number, log_topics)| {
+                (from_block_number, to_block_number, log_topics.len())
+            })
+            .collect();
+
+        assert_eq!(
+            block_ranges,
+            vec![(150, 199, 1), (200, 299, 1), (300, 350, 0)]
+        );
+    }
+
+    #[test]
+    fn groups_events_of_versions_apart() {
+        let events_by_keys = group_events_by_keys(&[upgraded_contract()]);
+        let transfer_signature = ContractEvent::new(
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+        )
+        .value
+        .signature();
+
+        assert!(events_by_keys.contains_key(&(
+            "Token".to_string(),
+            0,
+            Some(transfer_signature),
+            4
+        )));
+        assert!(events_by_keys.contains_key(&(
+            "Token".to_string(),
+            1,
+            Some(transfer_signature),
+            3
+        )));
+        assert_eq!(events_by_keys.len(), 2);
+    }
+
+    #[test]
+    fn keeps_handlers_of_versions_apart() {
+        let contract = Contract::<()>::new("Token")
+            .add_event_handler(FilteredTestHandler(
+                TRANSFER_ABI,
+                TopicFilter::new().with_values("tokenId", [U256::from(1)]),
+            ))
+            .add_version(
+                PROXY_ADDRESS,
+                &ChainId::Mainnet,
+                200,
+                Contract::new("TokenV2").add_event_handler(FilteredTestHandler(
+                    TRANSFER_ABI,
+                    TopicFilter::new().with_values("tokenId", [U256::from(2)]),
+                )),
+            );
+
+        let transfer_signature = ContractEvent::new(TRANSFER_ABI).value.signature();
+        let get_topics = |token_id: u64| {
+            vec![
+                transfer_signature,
+                to_topic(PROXY_ADDRESS),
+                to_topic(PROXY_ADDRESS),
+                H256::from_low_u64_be(token_id),
+            ]
+        };
+
+        let pure_handlers = get_pure_handlers(&[contract]);
+        let get_log_topics = |version_index: usize| {
+            let (_handler, log_topics) = &pure_handlers["Token"][version_index][TRANSFER_ABI];
+
+            log_topics.clone().unwrap()
+        };
+
+        assert_eq!(pure_handlers["Token"].len(), 2);
+        assert!(matches_log_topics(&get_log_topics(0), &get_topics(1)));
+        assert!(!matches_log_topics(&get_log_topics(0), &get_topics(2)));
+        assert!(matches_log_topics(&get_log_topics(1), &get_topics(2)));
+    }
+
+    #[test]
+    fn reports_versions_with_more_than_event_handlers() {
+        let contract = Contract::<()>::new("Token").add_version(
+            PROXY_ADDRESS,
+            &ChainId::Mainnet,
+            200,
+            Contract::new("TokenV2").add_transaction_handler(TestHandler(
+                "function transferFrom(address from, address to, uint256 tokenId)",
+            )),
+        );
+
+        assert!(matches!(
+            contract.get_config_error(),
+            Some(ConfigError::UnsupportedContractVersion(contract_name)) if contract_name == "Token"
+        ));
+        assert!(upgraded_contract().get_config_error().is_none());
+    }
+
+    #[test]
+    fn ingests_spawning_events_of_linked_factories() {
+        let mut factory = Contract::<()>::from_json_abi("Factory", ARTIFACT).unwrap();
+        let mut vault = Contract::<()>::new("Vault").spawned_by("Factory", "Transfer", "to");
+
+        vault.link_factory(&mut factory);
+
+        let transfer_abi =
+            "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)";
+        assert_eq!(factory.get_event_abis(), vec![transfer_abi]);
+        assert_eq!(vault.spawners[0].event_abi, transfer_abi);
+        assert_eq!(factory.get_filtered_log_topics(transfer_abi), None);
+    }
+
+    #[test]
+    fn reports_spawning_events_without_address_params() {
+        for address_param in ["tokenId", "pool"] {
+            let mut factory = Contract::<()>::from_json_abi("Factory", ARTIFACT).unwrap();
+            let mut vault =
+                Contract::<()>::new("Vault").spawned_by("Factory", "Transfer", address_param);
+
+            vault.link_factory(&mut factory);
+
+            assert!(matches!(
+                vault.get_config_error(),
+                Some(ConfigError::NoSpawnedAddressParam(contract_name, _event_abi, param))
+                    if contract_name == "Vault" && param == address_param
+            ));
+            assert!(factory.get_event_abis().is_empty());
+        }
+    }
+
+    const TRANSFER_ABI: &str =
+        "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)";
+
+    fn to_topic(address: &str) -> H256 {
+        H256::from(address.parse::<Address>().unwrap())
+    }
+
+    #[test]
+    fn ingests_events_of_side_effect_handlers() {
+        let vault: Address = PROXY_ADDRESS.parse().unwrap();
+        let contract = Contract::<()>::new("BAYC").add_side_effect_handler(FilteredTestHandler(
+            TRANSFER_ABI,
+            TopicFilter::new().with_values("to", [vault]),
+        ));
+
+        assert_eq!(contract.get_event_abis(), vec![TRANSFER_ABI]);
+        assert_eq!(contract.get_log_topics().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "BAYC's topic filter: value is not an indexed parameter of Transfer")]
+    fn rejects_topic_filters_on_non_indexed_params() {
+        Contract::<()>::new("BAYC").add_event_handler(FilteredTestHandler(
+            TRANSFER_ABI,
+            TopicFilter::new().with_values("value", [U256::from(1)]),
+        ));
+    }
+
+    #[test]
+    fn keeps_topic_filters_of_handlers_apart() {
+        let contract = Contract::<()>::new("BAYC")
+            .add_event_handler(FilteredTestHandler(
+                TRANSFER_ABI,
+                TopicFilter::new().with_values("tokenId", [U256::from(1)]),
+            ))
+            .add_side_effect_handler(FilteredTestHandler(
+                TRANSFER_ABI,
+                TopicFilter::new().with_values("tokenId", [U256::from(2)]),
+            ));
+        let transfer_signature = ContractEvent::new(TRANSFER_ABI).value.signature();
+        let get_topics = |token_id: u64| {
+            vec![
+                transfer_signature,
+                to_topic(PROXY_ADDRESS),
+                to_topic(PROXY_ADDRESS),
+                H256::from_low_u64_be(token_id),
+            ]
+        };
+
+        let pure_handlers = get_pure_handlers(std::slice::from_ref(&contract));
+        let side_effect_handlers = get_side_effect_handlers(std::slice::from_ref(&contract));
+        let (_handler, pure_log_topics) = &pure_handlers["BAYC"][0][TRANSFER_ABI];
+        let (_handler, side_effect_log_topics) = &side_effect_handlers["BAYC"][0][TRANSFER_ABI];
+        let pure_log_topics = pure_log_topics.as_ref().unwrap();
+        let side_effect_log_topics = side_effect_log_topics.as_ref().unwrap();
+
+        assert_eq!(contract.get_log_topics().len(), 2);
+        assert!(matches_log_topics(pure_log_topics, &get_topics(1)));
+        assert!(!matches_log_topics(pure_log_topics, &get_topics(2)));
+        assert!(matches_log_topics(side_effect_log_topics, &get_topics(2)));
+        assert!(!matches_log_topics(side_effect_log_topics, &get_topics(1)));
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        assert_eq!(
+            parse_address(PROXY_ADDRESS).unwrap(),
+            PROXY_ADDRESS.parse::<Address>().unwrap()
+        );
+        assert!(matches!(
+            parse_address("0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D' OR '1'='1"),
+            Err(ContractAddressError::InvalidAddress(_))
+        ));
+    }
+}
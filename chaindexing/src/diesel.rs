@@ -19,6 +19,8 @@ pub mod schema {
           start_block_number -> Int8,
           address -> VarChar,
           contract_name -> VarChar,
+          status -> VarChar,
+          end_block_number -> Nullable<Int8>,
       }
     }
 
@@ -41,6 +43,47 @@ pub mod schema {
       }
     }
 
+    diesel::table! {
+      chaindexing_transactions (id) {
+          id -> Uuid,
+          chain_id -> Int8,
+          contract_address -> VarChar,
+          contract_name -> VarChar,
+          abi -> Text,
+          parameters -> Json,
+          from_address -> VarChar,
+          value -> VarChar,
+          gas_used -> Int8,
+          succeeded -> Nullable<Bool>,
+          block_hash -> VarChar,
+          block_number -> Int8,
+          block_timestamp -> Int8,
+          transaction_hash -> VarChar,
+          transaction_index -> Int4,
+      }
+    }
+
+    diesel::table! {
+      chaindexing_call_traces (id) {
+          id -> Uuid,
+          chain_id -> Int8,
+          contract_address -> VarChar,
+          contract_name -> VarChar,
+          abi -> Nullable<Text>,
+          parameters -> Nullable<Json>,
+          call_type -> VarChar,
+          from_address -> VarChar,
+          value -> VarChar,
+          input -> Text,
+          error -> Nullable<Text>,
+          trace_address -> VarChar,
+          block_hash -> VarChar,
+          block_number -> Int8,
+          transaction_hash -> VarChar,
+          transaction_index -> Int4,
+      }
+    }
+
     diesel::table! {
       chaindexing_reorged_blocks (id) {
           id -> Int4,
@@ -50,6 +93,41 @@ pub mod schema {
       }
     }
 
+    diesel::table! {
+      chaindexing_blocks (chain_id, number) {
+          chain_id -> Int8,
+          number -> Int8,
+          hash -> VarChar,
+          parent_hash -> Nullable<VarChar>,
+          timestamp -> Int8,
+      }
+    }
+
+    diesel::table! {
+      chaindexing_block_hashes (chain_id, number) {
+          chain_id -> Int8,
+          number -> Int8,
+          hash -> VarChar,
+          parent_hash -> VarChar,
+      }
+    }
+
+    diesel::table! {
+      chaindexing_quarantined_logs (id) {
+          id -> Uuid,
+          chain_id -> Int8,
+          contract_address -> VarChar,
+          contract_name -> Nullable<VarChar>,
+          topics -> Json,
+          data -> Text,
+          block_hash -> Nullable<VarChar>,
+          block_number -> Nullable<Int8>,
+          transaction_hash -> Nullable<VarChar>,
+          log_index -> Nullable<Int4>,
+          reason -> Text,
+      }
+    }
+
     diesel::allow_tables_to_appear_in_same_query!(
         chaindexing_contract_addresses,
         chaindexing_events,
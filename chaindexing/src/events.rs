@@ -1,43 +1,117 @@
 mod event;
+mod quarantined_log;
 
 pub use event::{Event, EventParam, PartialEvent};
+pub use quarantined_log::QuarantinedLog;
 
 use std::collections::HashMap;
 
 use crate::{contracts, ChainId, Contract, ContractAddress};
 use ethers::types::{Block, Log, TxHash, U64};
 
+/// Decodes the logs into events. Logs that none of their contract's event ABIs
+/// can decode get quarantined instead.
 pub fn get<S: Send + Sync + Clone>(
     logs: &[Log],
     contracts: &[Contract<S>],
     contract_addresses: &[ContractAddress],
     chain_id: &ChainId,
     blocks_by_number: &HashMap<U64, Block<TxHash>>,
-) -> Vec<Event> {
-    let events_by_topics = contracts::group_events_by_topics(contracts);
+) -> (Vec<Event>, Vec<QuarantinedLog>) {
+    let events_by_keys = contracts::group_events_by_keys(contracts);
+    let contracts_by_name: HashMap<_, _> = contracts.iter().map(|c| (c.name.as_str(), c)).collect();
     let contract_addresses_by_address =
         ContractAddress::group_contract_addresses_by_address_and_chain_id(contract_addresses);
 
-    logs.iter()
-        .map(
-            |log @ Log {
-                 topics,
-                 address,
-                 block_number,
-                 ..
-             }| {
-                let contract_address =
-                    contract_addresses_by_address.get(&(*address, *chain_id)).unwrap();
-                let block = blocks_by_number.get(&block_number.unwrap()).unwrap();
-
-                Event::new(
+    let mut events = vec![];
+    let mut quarantined_logs = vec![];
+
+    for log @ Log {
+        topics,
+        address,
+        block_number,
+        ..
+    } in logs
+    {
+        let Some(contract_address) = contract_addresses_by_address.get(&(*address, *chain_id))
+        else {
+            let reason = "No contract address matches the log's address";
+            quarantined_logs.push(QuarantinedLog::new(log, chain_id, None, reason));
+            continue;
+        };
+        let contract_name = &contract_address.contract_name;
+        let Some((block_number, block)) =
+            block_number.and_then(|n| blocks_by_number.get(&n).map(|block| (n, block)))
+        else {
+            let reason = "The log is pending or its block is missing";
+            quarantined_logs.push(QuarantinedLog::new(
+                log,
+                chain_id,
+                Some(contract_name),
+                reason,
+            ));
+            continue;
+        };
+        // Upgraded contracts decode logs with the ABIs of their versions at the logs' blocks
+        let version_index = contracts_by_name
+            .get(contract_name.as_str())
+            .map(|contract| {
+                contract.get_version_index(
+                    contract_address.chain_id,
+                    &contract_address.address,
+                    block_number.as_u64(),
+                )
+            })
+            .unwrap_or(0);
+
+        let get_contract_event = |signature| {
+            events_by_keys.get(&(
+                contract_name.clone(),
+                version_index,
+                signature,
+                topics.len(),
+            ))
+        };
+        let Some(contract_event) = topics
+            .first()
+            .and_then(|topic| get_contract_event(Some(*topic)))
+            .or_else(|| get_contract_event(None))
+        else {
+            let reason = format!(
+                "No event ABI of {contract_name} with {} topics matches the log",
+                topics.len()
+            );
+            quarantined_logs.push(QuarantinedLog::new(
+                log,
+                chain_id,
+                Some(contract_name),
+                &reason,
+            ));
+            continue;
+        };
+
+        match Event::new(
+            log,
+            contract_event,
+            chain_id,
+            contract_name,
+            block.timestamp.as_u64() as i64,
+        ) {
+            Ok(event) => events.push(event),
+            Err(error) => {
+                let reason = format!(
+                    "Failed decoding the log with {}: {error}",
+                    contract_event.abi
+                );
+                quarantined_logs.push(QuarantinedLog::new(
                     log,
-                    events_by_topics.get(&topics[0]).unwrap(),
                     chain_id,
-                    &contract_address.contract_name,
-                    block.timestamp.as_u64() as i64,
-                )
-            },
-        )
-        .collect()
+                    Some(contract_name),
+                    &reason,
+                ));
+            }
+        }
+    }
+
+    (events, quarantined_logs)
 }
@@ -3,10 +3,11 @@ use std::hash::{Hash, Hasher};
 
 use crate::diesel::schema::chaindexing_events;
 use diesel::{Insertable, Queryable};
-use ethers::abi::{LogParam, Token};
-use ethers::types::{Address, Log, I256, U256, U64};
+use ethers::abi::{Error as AbiError, LogParam, Token};
+use ethers::types::{Address, Log, H256, I256, U256, U64};
 use ethers::utils::format_ether;
 
+use crate::blocks::Block;
 use crate::{ChainId, ContractEvent};
 use uuid::Uuid;
 
@@ -48,50 +49,102 @@ pub struct PartialEvent {
     pub log_index: i64,
 }
 
+/// Events are identified by their logs, like their ids, see `Event::get_id`.
+/// Events ingested before ids got derived from logs still match their logs.
 impl PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
         self.chain_id == other.chain_id
-            && self.contract_address == other.contract_address
-            && self.abi == other.abi
             && self.block_hash == other.block_hash
+            && self.transaction_hash == other.transaction_hash
+            && self.log_index == other.log_index
     }
 }
 
 impl Hash for Event {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.chain_id.hash(state);
-        self.contract_address.hash(state);
-        self.abi.hash(state);
         self.block_hash.hash(state);
+        self.transaction_hash.hash(state);
+        self.log_index.hash(state);
     }
 }
 
 impl Event {
+    /// Decodes the log with the event's ABI, failing for logs that do not match it
     pub fn new(
         log: &Log,
         event: &ContractEvent,
         chain_id: &ChainId,
         contract_name: &str,
         block_timestamp: i64,
-    ) -> Self {
-        let log_params = event.value.parse_log(log.clone().into()).unwrap().params;
+    ) -> Result<Self, AbiError> {
+        let log_params = event.value.parse_log(log.clone().into())?.params;
         let parameters = Self::log_params_to_parameters(&log_params);
+        let block_hash = hashes::h256_to_string(&log.block_hash.unwrap()).to_lowercase();
+        let transaction_hash =
+            hashes::h256_to_string(&log.transaction_hash.unwrap()).to_lowercase();
+        let log_index = log.log_index.unwrap().as_u32() as i32;
 
-        Self {
-            id: uuid::Uuid::new_v4(),
+        Ok(Self {
+            id: Self::get_id(chain_id, &block_hash, &transaction_hash, log_index),
             chain_id: *chain_id as i64,
             contract_address: utils::address_to_string(&log.address).to_lowercase(),
             contract_name: contract_name.to_owned(),
             abi: event.abi.clone(),
             parameters: serde_json::to_value(parameters).unwrap(),
             topics: serde_json::to_value(&log.topics).unwrap(),
-            block_hash: hashes::h256_to_string(&log.block_hash.unwrap()).to_lowercase(),
+            block_hash,
             block_number: log.block_number.unwrap().as_u64() as i64,
             block_timestamp,
-            transaction_hash: hashes::h256_to_string(&log.transaction_hash.unwrap()).to_lowercase(),
+            transaction_hash,
             transaction_index: log.transaction_index.unwrap().as_u32() as i32,
-            log_index: log.log_index.unwrap().as_u32() as i32,
+            log_index,
             removed: log.removed.unwrap(),
+        })
+    }
+
+    /// Derives the event's id from its log, so the same log always gets the same id,
+    /// however many times it gets ingested. Logs of reorged blocks get new ids.
+    pub(crate) fn get_id(
+        chain_id: &ChainId,
+        block_hash: &str,
+        transaction_hash: &str,
+        log_index: i32,
+    ) -> Uuid {
+        let name = format!(
+            "{}:{}:{}:{}",
+            *chain_id as u64, block_hash, transaction_hash, log_index
+        );
+
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    }
+
+    /// Stands in for the event when block handlers mutate states, so their
+    /// state versions get backtracked with the block. Ordered after the
+    /// block's actual events.
+    pub(crate) fn for_block(block: &Block, contract_address: &str) -> Self {
+        let name = format!(
+            "{}:{}:{}",
+            block.chain_id,
+            block.hash,
+            contract_address.to_lowercase()
+        );
+
+        Self {
+            id: Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()),
+            chain_id: block.chain_id,
+            contract_address: contract_address.to_lowercase(),
+            contract_name: "".to_string(),
+            abi: "".to_string(),
+            parameters: serde_json::json!({}),
+            topics: serde_json::json!([]),
+            block_hash: block.hash.to_owned(),
+            block_number: block.number,
+            block_timestamp: block.timestamp,
+            transaction_hash: "".to_string(),
+            transaction_index: i32::MAX,
+            log_index: i32::MAX,
+            removed: false,
         }
     }
 
@@ -99,6 +152,11 @@ impl Event {
         self.abi.as_str()
     }
 
+    /// Returns the topics of the event's log
+    pub(crate) fn get_topics(&self) -> Vec<H256> {
+        serde_json::from_value(self.topics.clone()).unwrap()
+    }
+
     /// Returns the event's block number
     pub fn get_block_number(&self) -> u64 {
         self.block_number as u64
@@ -347,3 +405,81 @@ mod event_param_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod event_tests {
+    use std::str::FromStr;
+
+    use ethers::types::{Bytes, H160, H256};
+
+    use super::*;
+
+    fn transfer_log(transaction_hash: &str, log_index: u64) -> Log {
+        Log {
+            address: H160::from_str("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f08D").unwrap(),
+            topics: vec![
+                H256::from_str(
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                )
+                .unwrap(),
+                H256::from(H160::from_low_u64_be(1)),
+                H256::from(H160::from_low_u64_be(2)),
+                H256::from_low_u64_be(1661),
+            ],
+            data: Bytes::default(),
+            block_hash: Some(H256::from_low_u64_be(18115958)),
+            block_number: Some(18115958.into()),
+            transaction_hash: Some(H256::from_str(transaction_hash).unwrap()),
+            transaction_index: Some(89.into()),
+            log_index: Some(log_index.into()),
+            removed: Some(false),
+            ..Default::default()
+        }
+    }
+
+    fn transfer_event(log: &Log) -> Event {
+        let contract_event = ContractEvent::new(
+            "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+        );
+
+        Event::new(
+            log,
+            &contract_event,
+            &ChainId::Mainnet,
+            "BoredApeYachtClub",
+            0,
+        )
+        .unwrap()
+    }
+
+    const TRANSACTION_HASH: &str =
+        "0x83d751998ff98cd609bc9b18bb36bdef8659cde2f74d6d7a1b0fef2c2bf8f839";
+
+    #[test]
+    fn derives_the_same_id_from_the_same_log() {
+        let log = transfer_log(TRANSACTION_HASH, 4);
+
+        assert_eq!(transfer_event(&log).id, transfer_event(&log).id);
+        assert_eq!(transfer_event(&log), transfer_event(&log));
+    }
+
+    #[test]
+    fn tells_apart_events_of_the_same_block() {
+        let event = transfer_event(&transfer_log(TRANSACTION_HASH, 4));
+        let next_event = transfer_event(&transfer_log(TRANSACTION_HASH, 5));
+
+        assert_ne!(event.id, next_event.id);
+        assert_ne!(event, next_event);
+    }
+
+    #[test]
+    fn derives_new_ids_for_logs_of_reorged_blocks() {
+        let log = transfer_log(TRANSACTION_HASH, 4);
+        let reorged_log = Log {
+            block_hash: Some(H256::from_low_u64_be(28115958)),
+            ..log.clone()
+        };
+
+        assert_ne!(transfer_event(&log).id, transfer_event(&reorged_log).id);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::diesel::schema::chaindexing_quarantined_logs;
+use diesel::{Insertable, Queryable};
+use ethers::types::Log;
+use uuid::Uuid;
+
+use serde::Deserialize;
+
+use super::Event;
+use crate::ChainId;
+
+/// Logs of contract addresses that none of the contract's event ABIs could
+/// decode. They get kept aside for inspection instead of halting ingestion.
+/// Logs still pending or of unknown contract addresses get quarantined too,
+/// without their blocks' positions or contract names.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Queryable, Insertable)]
+#[diesel(table_name = chaindexing_quarantined_logs)]
+pub struct QuarantinedLog {
+    pub id: Uuid,
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub contract_name: Option<String>,
+    pub topics: serde_json::Value,
+    pub data: String,
+    pub block_hash: Option<String>,
+    pub block_number: Option<i64>,
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<i32>,
+    /// Why decoding the log failed
+    pub reason: String,
+}
+
+impl QuarantinedLog {
+    pub fn new(log: &Log, chain_id: &ChainId, contract_name: Option<&str>, reason: &str) -> Self {
+        let block_hash = log.block_hash.map(|hash| format!("{hash:?}"));
+        let transaction_hash = log.transaction_hash.map(|hash| format!("{hash:?}"));
+        let log_index = log.log_index.map(|index| index.as_u32() as i32);
+
+        Self {
+            id: Self::get_id(log, chain_id, &block_hash, &transaction_hash, log_index),
+            chain_id: *chain_id as i64,
+            contract_address: format!("{:?}", log.address),
+            contract_name: contract_name.map(|name| name.to_string()),
+            topics: serde_json::to_value(&log.topics).unwrap(),
+            data: log.data.to_string(),
+            block_hash,
+            block_number: log.block_number.map(|number| number.as_u64() as i64),
+            transaction_hash,
+            log_index,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Logs get identified by their positions like events, or by their
+    /// contents while they have no positions yet
+    fn get_id(
+        log: &Log,
+        chain_id: &ChainId,
+        block_hash: &Option<String>,
+        transaction_hash: &Option<String>,
+        log_index: Option<i32>,
+    ) -> Uuid {
+        match (block_hash, transaction_hash, log_index) {
+            (Some(block_hash), Some(transaction_hash), Some(log_index)) => {
+                Event::get_id(chain_id, block_hash, transaction_hash, log_index)
+            }
+            _ => {
+                let name = format!(
+                    "{}:{}",
+                    *chain_id as u64,
+                    serde_json::to_string(log).unwrap()
+                );
+
+                Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+            }
+        }
+    }
+}
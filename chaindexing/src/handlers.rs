@@ -1,70 +1,112 @@
-use std::cmp::max;
 use std::fmt::Debug;
 use std::{sync::Arc, time::Duration};
 
+pub(crate) mod block_handler;
+mod call_handler;
+mod handle_blocks;
 mod handle_events;
 mod handler_context;
 mod maybe_handle_chain_reorg;
 mod pure_handler;
 mod side_effect_handler;
+mod topic_filter;
+mod transaction_handler;
 
+pub use block_handler::{BlockHandler, BlockHandlerContext};
+pub use call_handler::{CallHandler, CallHandlerContext};
 pub use handler_context::HandlerContext;
 pub use pure_handler::{PureHandler, PureHandlerContext};
 pub use side_effect_handler::{SideEffectHandler, SideEffectHandlerContext};
+pub use topic_filter::TopicFilter;
+pub use transaction_handler::{TransactionHandler, TransactionHandlerContext};
 
+use futures_util::FutureExt;
 use tokio::{sync::Mutex, time::interval};
 
+use crate::chains;
 use crate::deferred_futures::DeferredFutures;
 use crate::nodes::NodeTask;
-use crate::Config;
 use crate::{contracts, states, HasRawQueryClient};
+use crate::{Chain, Config};
 
 pub async fn start<S: Send + Sync + Clone + Debug + 'static>(config: &Config<S>) -> NodeTask {
     let node_task = NodeTask::new();
-    let config = config.clone();
-
-    node_task
-        .add_subtask(tokio::spawn({
-            let node_task = node_task.clone();
 
-            // MultiChainStates are indexed in an order-agnostic fashion, so no need for txn client
-            let repo_client_for_mcs = Arc::new(Mutex::new(config.repo.get_client().await));
-            let deferred_mutations_for_mcs = DeferredFutures::new();
+    // MultiChainStates are indexed in an order-agnostic fashion, so no need for txn client
+    let repo_client_for_mcs = Arc::new(Mutex::new(config.repo.get_client().await));
+    let deferred_mutations_for_mcs = DeferredFutures::new();
+
+    for chains in get_chunked_chains(config) {
+        let subtask_name = format!(
+            "handlers: {:?}",
+            chains.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
+        let config = config.clone();
+        let repo_client_for_mcs = repo_client_for_mcs.clone();
+        let deferred_mutations_for_mcs = deferred_mutations_for_mcs.clone();
+
+        node_task
+            .add_supervised_subtask(&subtask_name, move || {
+                let chains = chains.clone();
+                let config = config.clone();
+                let repo_client_for_mcs = repo_client_for_mcs.clone();
+                let deferred_mutations_for_mcs = deferred_mutations_for_mcs.clone();
+
+                async move {
+                    // Chunks only hold chains handled at the same rate
+                    let handler_rate_ms = config.get_handler_rate_ms(&chains[0].id);
+                    let mut interval = interval(Duration::from_millis(handler_rate_ms));
+                    let chain_ids_with_blocks_per_batch: Vec<_> = chains
+                        .iter()
+                        .map(|c| (c.id as u64, config.get_blocks_per_batch(&c.id)))
+                        .collect();
+
+                    let repo_client = Arc::new(Mutex::new(config.repo.get_client().await));
+                    let pure_handlers = contracts::get_pure_handlers(&config.contracts);
+                    let side_effect_handlers =
+                        contracts::get_side_effect_handlers(&config.contracts);
+                    let transaction_handlers =
+                        contracts::get_transaction_handlers(&config.contracts);
+                    let call_handlers = contracts::get_call_handlers(&config.contracts);
+
+                    loop {
+                        handle_events::run(
+                            &config.contracts,
+                            &pure_handlers,
+                            &side_effect_handlers,
+                            &transaction_handlers,
+                            &call_handlers,
+                            &chain_ids_with_blocks_per_batch,
+                            (&repo_client, &repo_client_for_mcs),
+                            &deferred_mutations_for_mcs,
+                            &config.shared_state,
+                        )
+                        .await;
 
-            async move {
-                for chain_ids in get_chunked_chain_ids(&config) {
-                    let config = config.clone();
-                    let repo_client_for_mcs = repo_client_for_mcs.clone();
-                    let deferred_mutations_for_mcs = deferred_mutations_for_mcs.clone();
-
-                    node_task
-                        .clone()
-                        .add_subtask(tokio::spawn(async move {
-                            let mut interval =
-                                interval(Duration::from_millis(config.handler_rate_ms));
-
-                            let repo_client = Arc::new(Mutex::new(config.repo.get_client().await));
-                            let pure_handlers = contracts::get_pure_handlers(&config.contracts);
-                            let side_effect_handlers =
-                                contracts::get_side_effect_handlers(&config.contracts);
-
-                            loop {
-                                handle_events::run(
-                                    &pure_handlers,
-                                    &side_effect_handlers,
-                                    (&chain_ids, config.blocks_per_batch),
-                                    (&repo_client, &repo_client_for_mcs),
-                                    &deferred_mutations_for_mcs,
-                                    &config.shared_state,
-                                )
-                                .await;
-
-                                interval.tick().await;
-                            }
-                        }))
+                        handle_blocks::run(
+                            &config.block_handlers,
+                            &chain_ids_with_blocks_per_batch,
+                            (&repo_client, &repo_client_for_mcs),
+                            &deferred_mutations_for_mcs,
+                        )
                         .await;
+
+                        interval.tick().await;
+                    }
                 }
+                .boxed()
+            })
+            .await;
+    }
 
+    let config = config.clone();
+
+    node_task
+        .add_supervised_subtask("handlers: chain reorgs", move || {
+            let config = config.clone();
+            let deferred_mutations_for_mcs = deferred_mutations_for_mcs.clone();
+
+            async move {
                 let mut repo_client = config.repo.get_client().await;
 
                 let state_migrations = contracts::get_state_migrations(&config.contracts);
@@ -80,18 +122,17 @@ pub async fn start<S: Send + Sync + Clone + Debug + 'static>(config: &Config<S>)
                     interval.tick().await;
                 }
             }
-        }))
+            .boxed()
+        })
         .await;
 
     node_task
 }
 
-fn get_chunked_chain_ids<S: Send + Sync + Clone + Debug + 'static>(
+fn get_chunked_chains<S: Send + Sync + Clone + Debug + 'static>(
     config: &Config<S>,
-) -> Vec<Vec<u64>> {
-    let chain_ids: Vec<_> = config.chains.iter().map(|c| c.id as u64).collect();
-    let chain_ids_count = chain_ids.len();
-    let chunk_size = max(chain_ids_count / config.chain_concurrency as usize, 1);
-
-    chain_ids.chunks(chunk_size).map(|c| c.to_vec()).collect()
+) -> Vec<Vec<Chain>> {
+    chains::chunk_by_rate(&config.chains, config.chain_concurrency, |chain| {
+        config.get_handler_rate_ms(&chain.id)
+    })
 }
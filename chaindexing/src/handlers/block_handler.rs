@@ -0,0 +1,187 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::blocks::Block;
+use crate::deferred_futures::DeferredFutures;
+use crate::events::Event;
+use crate::{ChainId, ChaindexingRepoClient, ChaindexingRepoTxnClient};
+
+use super::pure_handler::PureHandlerContext;
+
+/// Block handlers run every N blocks of a chain, whether any event fired or not.
+/// Useful for time-based states such as TWAP snapshots, daily rollups and expiry sweeps.
+/// They run after the events, transactions and calls of the same block get handled.
+#[crate::augmenting_std::async_trait]
+pub trait BlockHandler: Send + Sync {
+    async fn handle_block<'a, 'b>(&self, context: BlockHandlerContext<'a, 'b>);
+}
+
+/// Block's context in a block handler
+#[derive(Clone)]
+pub struct BlockHandlerContext<'a, 'b> {
+    pub block: Block,
+    pub(crate) repo_client: &'a ChaindexingRepoTxnClient<'a>,
+    pub(crate) repo_client_for_mcs: Arc<Mutex<ChaindexingRepoClient>>,
+    pub(crate) deferred_mutations_for_mcs: DeferredFutures<'b>,
+}
+
+impl<'a, 'b> BlockHandlerContext<'a, 'b> {
+    pub fn new(
+        block: &Block,
+        repo_client: &'a ChaindexingRepoTxnClient<'a>,
+        repo_client_for_mcs: &Arc<Mutex<ChaindexingRepoClient>>,
+        deferred_mutations_for_mcs: &DeferredFutures<'b>,
+    ) -> Self {
+        Self {
+            block: block.clone(),
+            repo_client,
+            repo_client_for_mcs: repo_client_for_mcs.clone(),
+            deferred_mutations_for_mcs: deferred_mutations_for_mcs.clone(),
+        }
+    }
+
+    /// Returns the block's number
+    pub fn get_block_number(&self) -> u64 {
+        self.block.number as u64
+    }
+    /// Returns the block's hash
+    pub fn get_block_hash(&self) -> &str {
+        &self.block.hash
+    }
+    /// Returns the block's timestamp
+    pub fn get_block_timestamp(&self) -> u64 {
+        self.block.timestamp as u64
+    }
+
+    /// Returns a context for creating, updating and deleting the
+    /// ContractStates of a contract address at this block
+    pub fn for_contract_address(&self, contract_address: &str) -> PureHandlerContext<'a, 'b> {
+        PureHandlerContext::new(
+            &Event::for_block(&self.block, contract_address),
+            self.repo_client,
+            &self.repo_client_for_mcs,
+            &self.deferred_mutations_for_mcs,
+        )
+    }
+
+    /// Returns a context for creating, updating and deleting
+    /// the chain's ChainStates at this block
+    pub fn for_chain(&self) -> PureHandlerContext<'a, 'b> {
+        self.for_contract_address("")
+    }
+
+    pub fn get_client(&self) -> &ChaindexingRepoTxnClient<'a> {
+        self.repo_client
+    }
+}
+
+/// Block handler registered for a chain
+#[derive(Clone)]
+pub struct ChainBlockHandler {
+    pub chain_id: ChainId,
+    pub interval: u64,
+    pub handler: Arc<dyn BlockHandler>,
+}
+
+impl ChainBlockHandler {
+    pub fn new(chain_id: &ChainId, interval: u64, handler: impl BlockHandler + 'static) -> Self {
+        Self {
+            chain_id: *chain_id,
+            interval,
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn is_handled_block(&self, block_number: u64) -> bool {
+        block_number.is_multiple_of(self.interval)
+    }
+}
+
+impl Debug for ChainBlockHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChainBlockHandler")
+            .field("chain_id", &self.chain_id)
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+pub fn get_for_chain(
+    block_handlers: &[ChainBlockHandler],
+    chain_id: u64,
+) -> Vec<ChainBlockHandler> {
+    block_handlers
+        .iter()
+        .filter(|h| h.chain_id as u64 == chain_id)
+        .cloned()
+        .collect()
+}
+
+/// Returns the block numbers within `from..to` at which any of the block handlers run
+pub fn get_handled_block_numbers(
+    block_handlers: &[ChainBlockHandler],
+    from_block_number: u64,
+    to_block_number: u64,
+) -> Vec<u64> {
+    if block_handlers.is_empty() {
+        return vec![];
+    }
+
+    (from_block_number..to_block_number)
+        .filter(|block_number| block_handlers.iter().any(|h| h.is_handled_block(*block_number)))
+        .collect()
+}
+
+#[cfg(test)]
+mod block_handler_tests {
+    use super::*;
+
+    struct SnapshotHandler;
+
+    #[crate::augmenting_std::async_trait]
+    impl BlockHandler for SnapshotHandler {
+        async fn handle_block<'a, 'b>(&self, _context: BlockHandlerContext<'a, 'b>) {}
+    }
+
+    #[test]
+    fn returns_block_numbers_of_every_interval() {
+        let block_handlers = [
+            ChainBlockHandler::new(&ChainId::Mainnet, 4, SnapshotHandler),
+            ChainBlockHandler::new(&ChainId::Mainnet, 6, SnapshotHandler),
+        ];
+
+        assert_eq!(
+            get_handled_block_numbers(&block_handlers, 10, 25),
+            vec![12, 16, 18, 20, 24]
+        );
+    }
+
+    #[test]
+    fn returns_every_block_number_for_an_interval_of_one() {
+        let block_handlers = [ChainBlockHandler::new(
+            &ChainId::Mainnet,
+            1,
+            SnapshotHandler,
+        )];
+
+        assert_eq!(
+            get_handled_block_numbers(&block_handlers, 7, 10),
+            vec![7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn returns_only_block_handlers_of_the_chain() {
+        let block_handlers = [
+            ChainBlockHandler::new(&ChainId::Mainnet, 4, SnapshotHandler),
+            ChainBlockHandler::new(&ChainId::Polygon, 6, SnapshotHandler),
+        ];
+
+        let polygon_block_handlers = get_for_chain(&block_handlers, ChainId::Polygon as u64);
+
+        assert_eq!(polygon_block_handlers.len(), 1);
+        assert_eq!(polygon_block_handlers[0].interval, 6);
+    }
+}
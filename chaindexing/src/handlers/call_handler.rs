@@ -0,0 +1,40 @@
+use crate::call_traces::CallTrace;
+use crate::{ChaindexingRepoTxnClient, EventParam};
+
+/// Call handlers handle the calls made to a contract's addresses, including
+/// internal calls from other contracts, calling the function being handled.
+/// Needs the contract's chain to be traced with `Chain::with_trace_mode`.
+#[crate::augmenting_std::async_trait]
+pub trait CallHandler: Send + Sync {
+    /// The human-readable ABI of the function being handled.
+    /// For example, ERC721's transferFrom function's abi is:
+    /// `function transferFrom(address from, address to, uint256 tokenId)`.
+    fn abi(&self) -> &'static str;
+    async fn handle_call<'a>(&self, context: CallHandlerContext<'a>);
+}
+
+/// Call's context in a call handler
+#[derive(Clone)]
+pub struct CallHandlerContext<'a> {
+    pub call_trace: CallTrace,
+    pub(crate) repo_client: &'a ChaindexingRepoTxnClient<'a>,
+}
+
+impl<'a> CallHandlerContext<'a> {
+    pub fn new(call_trace: &CallTrace, repo_client: &'a ChaindexingRepoTxnClient<'a>) -> Self {
+        Self {
+            call_trace: call_trace.clone(),
+            repo_client,
+        }
+    }
+
+    /// Returns the arguments the function was called with
+    pub fn get_call_params(&self) -> EventParam {
+        // Only calls to handled functions get handled, and those always get decoded
+        self.call_trace.get_params().unwrap()
+    }
+
+    pub fn get_client(&self) -> &ChaindexingRepoTxnClient<'a> {
+        self.repo_client
+    }
+}
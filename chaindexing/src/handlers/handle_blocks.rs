@@ -0,0 +1,76 @@
+use std::cmp::min;
+
+use crate::deferred_futures::DeferredFutures;
+use crate::{ChaindexingRepo, ChaindexingRepoClientMutex};
+use crate::{ExecutesWithRawQuery, HasRawQueryClient, LoadsDataWithRawQuery};
+
+use super::block_handler::{self, BlockHandlerContext, ChainBlockHandler};
+
+pub async fn run<'a>(
+    block_handlers: &[ChainBlockHandler],
+    chain_ids_with_blocks_per_batch: &[(u64, u64)],
+    (repo_client, repo_client_for_mcs): (&ChaindexingRepoClientMutex, &ChaindexingRepoClientMutex),
+    deferred_mutations_for_mcs: &DeferredFutures<'a>,
+) {
+    for &(chain_id, blocks_per_batch) in chain_ids_with_blocks_per_batch {
+        let block_handlers = block_handler::get_for_chain(block_handlers, chain_id);
+
+        if block_handlers.is_empty() {
+            continue;
+        }
+
+        let client = repo_client.clone();
+        let mut client = client.lock().await;
+
+        // Ends at the first block not handled by every contract address of the chain yet
+        let block_handler_range =
+            ChaindexingRepo::load_block_handler_range(&client, chain_id).await;
+
+        if let Some((from_block_number, to_block_number)) = block_handler_range.get() {
+            let to_block_number = min(to_block_number, from_block_number + blocks_per_batch);
+
+            let blocks =
+                ChaindexingRepo::load_blocks(&client, chain_id, from_block_number, to_block_number)
+                    .await;
+
+            let mut next_block_number = to_block_number;
+
+            // Block handlers mutate ChainStates, so they have to be handled orderly
+            let txn_client = ChaindexingRepo::get_txn_client(&mut client).await;
+
+            for block_number in block_handler::get_handled_block_numbers(
+                &block_handlers,
+                from_block_number,
+                to_block_number,
+            ) {
+                // Headers get cached while ingesting, so the rest waits on the ingester
+                let Some(block) = blocks.iter().find(|b| b.number as u64 == block_number) else {
+                    next_block_number = block_number;
+                    break;
+                };
+
+                for block_handler in
+                    block_handlers.iter().filter(|h| h.is_handled_block(block_number))
+                {
+                    let handler_context = BlockHandlerContext::new(
+                        block,
+                        &txn_client,
+                        repo_client_for_mcs,
+                        deferred_mutations_for_mcs,
+                    );
+
+                    block_handler.handler.handle_block(handler_context).await;
+                }
+            }
+
+            ChaindexingRepo::update_next_block_number_for_block_handlers(
+                &txn_client,
+                chain_id,
+                next_block_number,
+            )
+            .await;
+
+            ChaindexingRepo::commit_txns(txn_client).await;
+        }
+    }
+}
@@ -4,28 +4,45 @@ use std::{collections::HashMap, sync::Arc};
 use futures_util::StreamExt;
 use tokio::sync::Mutex;
 
+use crate::call_traces::CallTrace;
+use crate::contracts::{self, ContractAddressStatus, LogTopics, VersionedEventHandlers};
+use crate::contracts::{PureHandlerWithLogTopics, SideEffectHandlerWithLogTopics};
 use crate::deferred_futures::DeferredFutures;
 use crate::streams::ContractAddressesStream;
-use crate::{ChaindexingRepo, ChaindexingRepoClientMutex};
-use crate::{EventAbi, ExecutesWithRawQuery, HasRawQueryClient, LoadsDataWithRawQuery};
+use crate::transactions::Transaction;
+use crate::{ChaindexingRepo, ChaindexingRepoClientMutex, Contract, Event, FunctionAbi};
+use crate::{ExecutesWithRawQuery, HasRawQueryClient, LoadsDataWithRawQuery};
 
-use super::pure_handler::{PureHandler, PureHandlerContext};
-use super::side_effect_handler::{SideEffectHandler, SideEffectHandlerContext};
+use super::call_handler::{CallHandler, CallHandlerContext};
+use super::pure_handler::PureHandlerContext;
+use super::side_effect_handler::SideEffectHandlerContext;
+use super::transaction_handler::{TransactionHandler, TransactionHandlerContext};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run<'a, S: Send + Sync + Clone + Debug>(
-    pure_handlers: &HashMap<EventAbi, Arc<dyn PureHandler>>,
-    side_effect_handlers: &HashMap<EventAbi, Arc<dyn SideEffectHandler<SharedState = S>>>,
-    (chain_ids, blocks_per_batch): (&[u64], u64),
+    contracts: &[Contract<S>],
+    pure_handlers: &VersionedEventHandlers<PureHandlerWithLogTopics>,
+    side_effect_handlers: &VersionedEventHandlers<SideEffectHandlerWithLogTopics<S>>,
+    transaction_handlers: &HashMap<String, HashMap<FunctionAbi, Arc<dyn TransactionHandler>>>,
+    call_handlers: &HashMap<String, HashMap<FunctionAbi, Arc<dyn CallHandler>>>,
+    chain_ids_with_blocks_per_batch: &[(u64, u64)],
     (repo_client, repo_client_for_mcs): (&ChaindexingRepoClientMutex, &ChaindexingRepoClientMutex),
     deferred_mutations_for_mcs: &DeferredFutures<'a>,
     shared_state: &Option<Arc<Mutex<S>>>,
 ) {
-    for chain_id in chain_ids {
+    for &(chain_id, blocks_per_batch) in chain_ids_with_blocks_per_batch {
         let mut contract_addresses_stream =
-            ContractAddressesStream::new(repo_client, *chain_id as i64).with_chunk_size(200);
+            ContractAddressesStream::new(repo_client, chain_id as i64)
+                .with_chunk_size(200)
+                // Events ingested up to completed addresses' end blocks still get handled
+                .with_statuses(&[
+                    ContractAddressStatus::Active,
+                    ContractAddressStatus::Completed,
+                ]);
 
         while let Some(contract_addresses) = contract_addresses_stream.next().await {
             for contract_address in contract_addresses {
+                let contract = contracts.iter().find(|c| c.name == contract_address.contract_name);
                 let from_block_number = contract_address.next_block_number_to_handle_from as u64;
 
                 let client = repo_client.clone();
@@ -34,19 +51,84 @@ pub async fn run<'a, S: Send + Sync + Clone + Debug>(
                 // return ordered by block_number and log_index
                 let events = ChaindexingRepo::load_events(
                     &client,
-                    *chain_id,
+                    chain_id,
                     &contract_address.address,
                     from_block_number,
                     blocks_per_batch,
                 )
                 .await;
+                // return ordered by block_number and transaction_index
+                let transactions = ChaindexingRepo::load_transactions(
+                    &client,
+                    chain_id,
+                    &contract_address.address,
+                    from_block_number,
+                    blocks_per_batch,
+                )
+                .await;
+                // return ordered by block_number and transaction_index
+                let call_traces = ChaindexingRepo::load_call_traces(
+                    &client,
+                    chain_id,
+                    &contract_address.address,
+                    from_block_number,
+                    blocks_per_batch,
+                )
+                .await;
+                let (events, transactions, call_traces) =
+                    take_common_block_range(events, transactions, call_traces, blocks_per_batch);
 
                 // ChainStates which include ContractState have to be handled orderly
                 let txn_client = ChaindexingRepo::get_txn_client(&mut client).await;
 
-                for event in &events {
+                for handleable in order(&events, &transactions, &call_traces) {
+                    let event = match handleable {
+                        Handleable::Event(event) => event,
+                        Handleable::Transaction(transaction) => {
+                            if let Some(handler) = transaction_handlers
+                                .get(&transaction.contract_name)
+                                .and_then(|handlers| handlers.get(transaction.get_abi()))
+                            {
+                                let handler_context =
+                                    TransactionHandlerContext::new(transaction, &txn_client);
+
+                                handler.handle_transaction(handler_context).await;
+                            }
+
+                            continue;
+                        }
+                        Handleable::CallTrace(call_trace) => {
+                            if let Some(handler) = call_trace.get_abi().and_then(|abi| {
+                                call_handlers
+                                    .get(&call_trace.contract_name)
+                                    .and_then(|handlers| handlers.get(abi))
+                            }) {
+                                let handler_context =
+                                    CallHandlerContext::new(call_trace, &txn_client);
+
+                                handler.handle_call(handler_context).await;
+                            }
+
+                            continue;
+                        }
+                    };
+
+                    // Upgraded contracts handle events with the handlers of their versions
+                    let version_index = contract
+                        .map(|contract| {
+                            contract.get_version_index(
+                                contract_address.chain_id,
+                                &contract_address.address,
+                                event.get_block_number(),
+                            )
+                        })
+                        .unwrap_or(0);
+
                     {
-                        if let Some(handler) = pure_handlers.get(event.get_abi()) {
+                        if let Some((handler, _log_topics)) =
+                            get_event_handler(pure_handlers, event, version_index)
+                                .filter(|(_handler, log_topics)| matches(event, log_topics))
+                        {
                             let handler_context = PureHandlerContext::new(
                                 event,
                                 &txn_client,
@@ -61,7 +143,10 @@ pub async fn run<'a, S: Send + Sync + Clone + Debug>(
                     {
                         if event.block_number >= contract_address.next_block_number_for_side_effects
                         {
-                            if let Some(handler) = side_effect_handlers.get(event.get_abi()) {
+                            if let Some((handler, _log_topics)) =
+                                get_event_handler(side_effect_handlers, event, version_index)
+                                    .filter(|(_handler, log_topics)| matches(event, log_topics))
+                            {
                                 let handler_context =
                                     SideEffectHandlerContext::new(event, &txn_client, shared_state);
 
@@ -71,13 +156,31 @@ pub async fn run<'a, S: Send + Sync + Clone + Debug>(
                     }
                 }
 
-                if let Some(last_event) = events.last() {
-                    let next_block_number_to_handle_from = last_event.block_number as u64 + 1;
+                let last_block_number = [
+                    events.last().map(|e| e.block_number),
+                    transactions.last().map(|t| t.block_number),
+                    call_traces.last().map(|c| c.block_number),
+                ]
+                .into_iter()
+                .flatten()
+                .max();
+
+                let next_block_number_to_handle_from = match last_block_number {
+                    Some(last_block_number) => Some(last_block_number as u64 + 1),
+                    // Everything ingested got handled, so block handlers need not wait on it
+                    None if contract_address.next_block_number_to_ingest_from
+                        > contract_address.next_block_number_to_handle_from =>
+                    {
+                        Some(contract_address.next_block_number_to_ingest_from as u64)
+                    }
+                    None => None,
+                };
 
+                if let Some(next_block_number_to_handle_from) = next_block_number_to_handle_from {
                     ChaindexingRepo::update_next_block_number_to_handle_from(
                         &txn_client,
                         &contract_address.address,
-                        *chain_id,
+                        chain_id,
                         next_block_number_to_handle_from,
                     )
                     .await;
@@ -88,7 +191,7 @@ pub async fn run<'a, S: Send + Sync + Clone + Debug>(
                         ChaindexingRepo::update_next_block_number_for_side_effects(
                             &txn_client,
                             &contract_address.address,
-                            *chain_id,
+                            chain_id,
                             next_block_number_to_handle_from,
                         )
                         .await;
@@ -100,3 +203,90 @@ pub async fn run<'a, S: Send + Sync + Clone + Debug>(
         }
     }
 }
+
+fn get_event_handler<'h, H>(
+    handlers: &'h VersionedEventHandlers<H>,
+    event: &Event,
+    version_index: usize,
+) -> Option<&'h H> {
+    handlers.get(&event.contract_name)?.get(version_index)?.get(event.get_abi())
+}
+
+/// Logs of an event get ingested for all of its handlers' topic filters,
+/// so each handler only handles the events matching its own filter
+fn matches(event: &Event, log_topics: &Option<LogTopics>) -> bool {
+    match log_topics {
+        Some(log_topics) => contracts::matches_log_topics(log_topics, &event.get_topics()),
+        None => true,
+    }
+}
+
+enum Handleable<'a> {
+    Event(&'a Event),
+    Transaction(&'a Transaction),
+    CallTrace(&'a CallTrace),
+}
+
+/// Transactions get handled before the calls they made, in call tree order,
+/// and those before the events they emitted
+fn order<'a>(
+    events: &'a [Event],
+    transactions: &'a [Transaction],
+    call_traces: &'a [CallTrace],
+) -> Vec<Handleable<'a>> {
+    let mut handleables: Vec<_> = transactions
+        .iter()
+        .map(Handleable::Transaction)
+        .chain(call_traces.iter().map(Handleable::CallTrace))
+        .chain(events.iter().map(Handleable::Event))
+        .collect();
+
+    // Stable, so events keep their log order within a transaction
+    handleables.sort_by_key(|handleable| match handleable {
+        Handleable::Transaction(t) => (t.block_number, t.transaction_index, 0, vec![]),
+        Handleable::CallTrace(c) => (
+            c.block_number,
+            c.transaction_index,
+            1,
+            c.get_trace_address(),
+        ),
+        Handleable::Event(e) => (e.block_number, e.transaction_index, 2, vec![]),
+    });
+
+    handleables
+}
+
+/// Events, transactions and call traces get loaded with separate limits.
+/// When any reaches its limit, the others must not get handled past its
+/// last block, or the rest of that block range would get skipped.
+fn take_common_block_range(
+    mut events: Vec<Event>,
+    mut transactions: Vec<Transaction>,
+    mut call_traces: Vec<CallTrace>,
+    limit: u64,
+) -> (Vec<Event>, Vec<Transaction>, Vec<CallTrace>) {
+    let max_block_number = [
+        get_last_block_number_at_limit(&events, |e| e.block_number, limit),
+        get_last_block_number_at_limit(&transactions, |t| t.block_number, limit),
+        get_last_block_number_at_limit(&call_traces, |c| c.block_number, limit),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+
+    if let Some(max_block_number) = max_block_number {
+        events.retain(|e| e.block_number <= max_block_number);
+        transactions.retain(|t| t.block_number <= max_block_number);
+        call_traces.retain(|c| c.block_number <= max_block_number);
+    }
+
+    (events, transactions, call_traces)
+}
+
+fn get_last_block_number_at_limit<T>(
+    entries: &[T],
+    get_block_number: fn(&T) -> i64,
+    limit: u64,
+) -> Option<i64> {
+    entries.last().filter(|_| entries.len() as u64 >= limit).map(get_block_number)
+}
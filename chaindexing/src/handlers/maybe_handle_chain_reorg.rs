@@ -22,7 +22,13 @@ pub async fn run(repo_client: &mut ChaindexingRepoClient, table_names: &Vec<Stri
                 *chain_id as u64,
                 *block_number as u64,
             )
-            .await
+            .await;
+            ChaindexingRepo::rewind_next_block_number_for_block_handlers(
+                &repo_txn_client,
+                *chain_id as u64,
+                *block_number as u64,
+            )
+            .await;
         }
 
         let reorged_block_ids = ReorgedBlocks::get_ids(&reorged_blocks);
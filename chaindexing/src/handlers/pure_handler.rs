@@ -7,6 +7,7 @@ use crate::events::Event;
 use crate::{ChaindexingRepoClient, ChaindexingRepoTxnClient, EventParam};
 
 use super::handler_context::HandlerContext;
+use super::TopicFilter;
 
 /// Pure handlers do not contain any side effects. They are simple reducers
 /// that derive or index states deterministically.
@@ -16,7 +17,14 @@ pub trait PureHandler: Send + Sync {
     /// For example, Uniswap's PoolCreated event's abi is:
     /// `PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)`.
     /// The chain explorer's event section can also be used to infer this.
+    /// For contracts built from JSON ABIs, the event's name, or its signature
+    /// if overloaded, e.g. `PoolCreated(address,address,uint24,int24,address)`, also works.
     fn abi(&self) -> &'static str;
+    /// Only ingests events whose indexed parameters match the filter.
+    /// All of the event's logs get ingested by default.
+    fn topic_filter(&self) -> Option<TopicFilter> {
+        None
+    }
     async fn handle_event<'a, 'b>(&self, context: PureHandlerContext<'a, 'b>);
 }
 
@@ -7,6 +7,7 @@ use crate::events::Event;
 use crate::{ChaindexingRepoTxnClient, EventParam};
 
 use super::handler_context::HandlerContext;
+use super::TopicFilter;
 
 /// SideEffectHandlers are event handlers that help handle side-effects for events.
 /// This is useful for handling events only ONCE and can rely on a non-deterministic
@@ -22,7 +23,14 @@ pub trait SideEffectHandler: Send + Sync {
     /// For example, Uniswap's PoolCreated event's abi is:
     /// `PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)`.
     /// The chain explorer's event section can also be used to infer this.
+    /// For contracts built from JSON ABIs, the event's name, or its signature
+    /// if overloaded, e.g. `PoolCreated(address,address,uint24,int24,address)`, also works.
     fn abi(&self) -> &'static str;
+    /// Only ingests events whose indexed parameters match the filter.
+    /// All of the event's logs get ingested by default.
+    fn topic_filter(&self) -> Option<TopicFilter> {
+        None
+    }
     async fn handle_event<'a>(&self, context: SideEffectHandlerContext<'a, Self::SharedState>);
 }
 
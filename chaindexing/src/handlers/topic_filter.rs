@@ -0,0 +1,154 @@
+use ethers::abi::{Event, RawTopicFilter, Token, Tokenizable, Topic as AbiTopic};
+use ethers::types::ValueOrArray;
+
+use crate::contracts::LogTopics;
+
+/// Filters an event by the values of its indexed parameters. Providers then
+/// only return the matching logs instead of every log of the event.
+///
+/// # Example
+/// ```
+/// use chaindexing::{Address, TopicFilter};
+///
+/// let vault: Address = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d".parse().unwrap();
+///
+/// // Only `Transfer`s to the vault
+/// TopicFilter::new().with_values("to", [vault]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TopicFilter {
+    values_by_param_name: Vec<(String, Vec<Token>)>,
+}
+
+impl TopicFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Matches events whose indexed `param_name` is any of the values.
+    /// Values for different parameters must all match.
+    pub fn with_values<T: Tokenizable>(
+        mut self,
+        param_name: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.values_by_param_name.push((
+            param_name.to_string(),
+            values.into_iter().map(|value| value.into_token()).collect(),
+        ));
+
+        self
+    }
+
+    /// Encodes the values as the event's log topics, by the types of its indexed parameters.
+    /// Fails for parameters that are not indexed or values that do not match their types.
+    pub(crate) fn to_topics(&self, event: &Event) -> Result<LogTopics, String> {
+        let indexed_param_names: Vec<_> =
+            event.inputs.iter().filter(|p| p.indexed).map(|p| p.name.as_str()).collect();
+
+        if let Some((param_name, _values)) = self
+            .values_by_param_name
+            .iter()
+            .find(|(param_name, _values)| !indexed_param_names.contains(&param_name.as_str()))
+        {
+            return Err(format!(
+                "{} is not an indexed parameter of {}",
+                param_name, event.name
+            ));
+        }
+
+        let get_raw_topic = |index: usize| match indexed_param_names.get(index) {
+            Some(param_name) => self
+                .values_by_param_name
+                .iter()
+                .find(|(name, _values)| name == param_name)
+                .map(|(_name, values)| AbiTopic::OneOf(values.clone()))
+                .unwrap_or(AbiTopic::Any),
+            None => AbiTopic::Any,
+        };
+
+        let topic_filter = event
+            .filter(RawTopicFilter {
+                topic0: get_raw_topic(0),
+                topic1: get_raw_topic(1),
+                topic2: get_raw_topic(2),
+            })
+            .map_err(|_| format!("Topic filter values do not match {}'s types", event.name))?;
+
+        Ok([
+            topic_filter.topic0,
+            topic_filter.topic1,
+            topic_filter.topic2,
+            topic_filter.topic3,
+        ]
+        .map(|topic| match topic {
+            AbiTopic::Any => None,
+            AbiTopic::OneOf(hashes) => {
+                Some(ValueOrArray::Array(hashes.into_iter().map(Some).collect()))
+            }
+            AbiTopic::This(hash) => Some(ValueOrArray::Value(Some(hash))),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod topic_filter_tests {
+    use ethers::abi::HumanReadableParser;
+    use ethers::types::{Address, H256, U256};
+
+    use super::*;
+
+    fn transfer_event() -> Event {
+        HumanReadableParser::parse_event(
+            "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn encodes_values_of_indexed_params_as_topics() {
+        let to: Address = "0xb518b3136e491101f22b77f385fe22269c515188".parse().unwrap();
+
+        let topics =
+            TopicFilter::new().with_values("to", [to]).to_topics(&transfer_event()).unwrap();
+
+        assert_eq!(
+            topics[0],
+            Some(ValueOrArray::Value(Some(transfer_event().signature())))
+        );
+        assert_eq!(topics[1], None);
+        assert_eq!(
+            topics[2],
+            Some(ValueOrArray::Array(vec![Some(H256::from(to))]))
+        );
+        assert_eq!(topics[3], None);
+    }
+
+    #[test]
+    fn encodes_numbers_as_topics() {
+        let topics = TopicFilter::new()
+            .with_values("tokenId", [U256::from(1), U256::from(2)])
+            .to_topics(&transfer_event())
+            .unwrap();
+
+        assert_eq!(
+            topics[3],
+            Some(ValueOrArray::Array(vec![
+                Some(H256::from_low_u64_be(1)),
+                Some(H256::from_low_u64_be(2))
+            ]))
+        );
+    }
+
+    #[test]
+    fn rejects_non_indexed_params() {
+        let topics = TopicFilter::new()
+            .with_values("value", [U256::from(1)])
+            .to_topics(&transfer_event());
+
+        assert_eq!(
+            topics,
+            Err("value is not an indexed parameter of Transfer".to_string())
+        );
+    }
+}
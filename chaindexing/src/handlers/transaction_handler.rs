@@ -0,0 +1,40 @@
+use crate::transactions::Transaction;
+use crate::{ChaindexingRepoTxnClient, EventParam};
+
+/// Transaction handlers handle the transactions sent to a contract's addresses,
+/// calling the function being handled. Failed transactions get handled too,
+/// so their status can be checked with `Transaction::is_successful`.
+#[crate::augmenting_std::async_trait]
+pub trait TransactionHandler: Send + Sync {
+    /// The human-readable ABI of the function being handled.
+    /// For example, ERC721's transferFrom function's abi is:
+    /// `function transferFrom(address from, address to, uint256 tokenId)`.
+    /// The chain explorer's contract section can also be used to infer this.
+    fn abi(&self) -> &'static str;
+    async fn handle_transaction<'a>(&self, context: TransactionHandlerContext<'a>);
+}
+
+/// Transaction's context in a transaction handler
+#[derive(Clone)]
+pub struct TransactionHandlerContext<'a> {
+    pub transaction: Transaction,
+    pub(crate) repo_client: &'a ChaindexingRepoTxnClient<'a>,
+}
+
+impl<'a> TransactionHandlerContext<'a> {
+    pub fn new(transaction: &Transaction, repo_client: &'a ChaindexingRepoTxnClient<'a>) -> Self {
+        Self {
+            transaction: transaction.clone(),
+            repo_client,
+        }
+    }
+
+    /// Returns the arguments the function was called with
+    pub fn get_transaction_params(&self) -> EventParam {
+        self.transaction.get_params()
+    }
+
+    pub fn get_client(&self) -> &ChaindexingRepoTxnClient<'a> {
+        self.repo_client
+    }
+}
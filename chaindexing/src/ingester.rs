@@ -1,22 +1,33 @@
+mod block_hashes;
+mod blocks;
+mod blocks_per_batch;
+mod call_traces;
 mod error;
 mod filters;
 mod ingest_events;
 mod maybe_handle_chain_reorg;
+mod new_heads;
 mod provider;
+mod transactions;
 
+pub use blocks_per_batch::AdaptiveBlocksPerBatch;
 pub use error::IngesterError;
-pub use provider::{Provider, ProviderError};
+pub use provider::{
+    ArchiveProvider, Provider, ProviderError, ProviderErrorKind, RecordingProvider, ReplayProvider,
+    RetryPolicy,
+};
 
-use std::cmp::max;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::Utc;
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 use tokio::sync::Mutex;
 use tokio::time::interval;
 
+use crate::chain_reorg::ReorgDetection;
+use crate::chains;
 use crate::contracts;
 use crate::nodes::NodeTask;
 use crate::pruning::PruningConfig;
@@ -33,48 +44,113 @@ use crate::{ExecutesWithRawQuery, HasRawQueryClient, Repo};
 pub async fn start<S: Sync + Send + Clone + 'static>(config: &Config<S>) -> NodeTask {
     let node_task = NodeTask::new();
 
+    for chain in config.chains.iter().filter(|c| c.ws_url.is_some()) {
+        let subtask_name = format!("ingester: {:?}", chain.id);
+        let chain = chain.clone();
+        let config = config.clone();
+
+        node_task
+            .add_supervised_subtask(&subtask_name, move || {
+                let chain = chain.clone();
+                let config = config.clone();
+
+                async move {
+                    let ws_url = chain.ws_url.clone().unwrap();
+
+                    new_heads::run(&chain, &ws_url, &config).await.map_err(|e| format!("{e:?}"))
+                }
+                .boxed()
+            })
+            .await;
+    }
+
     for chains in get_chunked_chains(config) {
+        let subtask_name = format!(
+            "ingester: {:?}",
+            chains.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
         let config = config.clone();
 
         node_task
-            .add_subtask(tokio::spawn(async move {
-                let mut interval = interval(Duration::from_millis(config.ingestion_rate_ms));
-                let mut last_pruned_at_per_chain_id = HashMap::new();
-
-                loop {
-                    for chain in chains.iter() {
-                        let provider = provider::get(&chain.json_rpc_url);
-                        let repo_client = Arc::new(Mutex::new(config.repo.get_client().await));
-                        let pool = config.repo.get_pool(1).await;
-                        let conn = ChaindexingRepo::get_conn(&pool).await;
-                        let conn = Arc::new(Mutex::new(conn));
-
-                        ingest_for_chain(
-                            &chain.id,
-                            provider,
-                            conn.clone(),
-                            &repo_client,
-                            &config,
-                            &mut last_pruned_at_per_chain_id,
-                        )
-                        .await
-                        .unwrap();
-                    }
+            .add_supervised_subtask(&subtask_name, move || {
+                let chains = chains.clone();
+                let config = config.clone();
+
+                async move {
+                    // Chunks only hold chains ingested at the same rate
+                    let ingestion_rate_ms = config.get_ingestion_rate_ms(&chains[0].id);
+                    let mut interval = interval(Duration::from_millis(ingestion_rate_ms));
+                    let mut last_pruned_at_per_chain_id = HashMap::new();
+                    // Providers and batch sizes outlive each run to keep track of
+                    // their endpoints' health and the block ranges they accept
+                    let providers: Vec<_> = chains.iter().map(provider::get).collect();
+                    let mut blocks_per_batch_per_chain: Vec<_> = chains
+                        .iter()
+                        .map(|chain| {
+                            AdaptiveBlocksPerBatch::new(config.get_blocks_per_batch(&chain.id))
+                        })
+                        .collect();
+
+                    loop {
+                        for ((chain, provider), blocks_per_batch) in chains
+                            .iter()
+                            .zip(providers.iter())
+                            .zip(blocks_per_batch_per_chain.iter_mut())
+                        {
+                            ingest_chain(
+                                chain,
+                                provider,
+                                &config,
+                                &mut last_pruned_at_per_chain_id,
+                                blocks_per_batch,
+                            )
+                            .await
+                            .map_err(|e| format!("{e:?}"))?;
+                        }
 
-                    interval.tick().await;
+                        interval.tick().await;
+                    }
                 }
-            }))
+                .boxed()
+            })
             .await;
     }
 
     node_task
 }
 
+/// Chunks chains that are polled every `ingestion_rate_ms`.
+/// Chains subscribed to `newHeads` get ingested on their own.
 pub fn get_chunked_chains<S: Send + Sync + Clone + 'static>(config: &Config<S>) -> Vec<Vec<Chain>> {
-    let chains: Vec<_> = config.chains.clone();
-    let chunk_size = max(chains.len() / config.chain_concurrency as usize, 1);
+    let chains: Vec<_> = config.chains.iter().filter(|c| c.ws_url.is_none()).cloned().collect();
+
+    chains::chunk_by_rate(&chains, config.chain_concurrency, |chain| {
+        config.get_ingestion_rate_ms(&chain.id)
+    })
+}
 
-    chains.chunks(chunk_size).map(|c| c.to_vec()).collect()
+async fn ingest_chain<S: Send + Sync + Clone>(
+    chain: &Chain,
+    provider: &Arc<impl Provider>,
+    config: &Config<S>,
+    last_pruned_at_per_chain_id: &mut HashMap<u64, u64>,
+    blocks_per_batch: &mut AdaptiveBlocksPerBatch,
+) -> Result<(), IngesterError> {
+    let repo_client = Arc::new(Mutex::new(config.repo.get_client().await));
+    let pool = config.repo.get_pool(1).await;
+    let conn = ChaindexingRepo::get_conn(&pool).await;
+    let conn = Arc::new(Mutex::new(conn));
+
+    ingest_for_chain(
+        &chain.id,
+        provider.clone(),
+        conn.clone(),
+        &repo_client,
+        config,
+        last_pruned_at_per_chain_id,
+        blocks_per_batch,
+    )
+    .await
 }
 
 pub async fn ingest_for_chain<'a, S: Send + Sync + Clone>(
@@ -88,8 +164,36 @@ pub async fn ingest_for_chain<'a, S: Send + Sync + Clone>(
         ..
     }: &Config<S>,
     last_pruned_at_per_chain_id: &mut HashMap<u64, u64>,
+    blocks_per_batch: &mut AdaptiveBlocksPerBatch,
 ) -> Result<(), IngesterError> {
-    let current_block_number = provider::fetch_current_block_number(&provider).await;
+    let retry_policy = config.get_retry_policy(chain_id);
+    let current_block_number =
+        provider::fetch_current_block_number(&provider, retry_policy).await?;
+    let tracks_block_hashes = config
+        .get_chain(chain_id)
+        .map(|c| c.reorg_detection == ReorgDetection::ParentHashes)
+        .unwrap_or(false);
+
+    if tracks_block_hashes {
+        let finalized_block_number = provider::fetch_finalized_block_number(
+            &provider,
+            retry_policy,
+            config.get_finality(chain_id),
+            current_block_number,
+        )
+        .await?;
+
+        block_hashes::track(
+            &mut *conn.lock().await,
+            &provider,
+            retry_policy,
+            chain_id,
+            current_block_number,
+            &finalized_block_number,
+        )
+        .await?;
+    }
+
     let mut contract_addresses_stream =
         ContractAddressesStream::new(repo_client, *chain_id as i64).with_chunk_size(5);
 
@@ -100,10 +204,9 @@ pub async fn ingest_for_chain<'a, S: Send + Sync + Clone>(
         let mut conn = conn.lock().await;
         let repo_client = &*repo_client.lock().await;
 
-        ingest_events::run(
+        complete_ended_contract_addresses(
             &mut conn,
-            repo_client,
-            contract_addresses.clone(),
+            &contract_addresses,
             &provider,
             chain_id,
             current_block_number,
@@ -111,15 +214,30 @@ pub async fn ingest_for_chain<'a, S: Send + Sync + Clone>(
         )
         .await?;
 
-        maybe_handle_chain_reorg::run(
+        ingest_events::run(
             &mut conn,
-            contract_addresses,
+            repo_client,
+            contract_addresses.clone(),
             &provider,
             chain_id,
             current_block_number,
             config,
+            blocks_per_batch,
         )
         .await?;
+
+        if !tracks_block_hashes {
+            maybe_handle_chain_reorg::run(
+                &mut conn,
+                contract_addresses,
+                &provider,
+                chain_id,
+                current_block_number,
+                config,
+                blocks_per_batch,
+            )
+            .await?;
+        }
     }
 
     maybe_prune(
@@ -151,6 +269,11 @@ async fn maybe_prune<S: Send + Sync + Clone>(
                 pruning_config.get_min_block_number(current_block_number);
 
             ChaindexingRepo::prune_events(repo_client, min_pruning_block_number, chain_id).await;
+            ChaindexingRepo::prune_transactions(repo_client, min_pruning_block_number, chain_id)
+                .await;
+            ChaindexingRepo::prune_call_traces(repo_client, min_pruning_block_number, chain_id)
+                .await;
+            ChaindexingRepo::prune_blocks(repo_client, min_pruning_block_number, chain_id).await;
 
             let state_migrations = contracts::get_state_migrations(contracts);
             let state_table_names = states::get_all_table_names(&state_migrations);
@@ -166,6 +289,48 @@ async fn maybe_prune<S: Send + Sync + Clone>(
     }
 }
 
+/// Completes contract addresses ingested up to their end blocks, once the
+/// end blocks are final and no longer get confirmed
+async fn complete_ended_contract_addresses<'a, S: Send + Sync + Clone>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    contract_addresses: &[ContractAddress],
+    provider: &Arc<impl Provider>,
+    chain_id: &ChainId,
+    current_block_number: u64,
+    config: &Config<S>,
+) -> Result<(), IngesterError> {
+    let ended_contract_addresses: Vec<_> = contract_addresses
+        .iter()
+        .filter(|ca| {
+            ca.end_block_number.is_some_and(|end_block_number| {
+                ca.next_block_number_to_ingest_from > end_block_number
+            })
+        })
+        .collect();
+
+    if ended_contract_addresses.is_empty() {
+        return Ok(());
+    }
+
+    let finalized_block_number = provider::fetch_finalized_block_number(
+        provider,
+        config.get_retry_policy(chain_id),
+        config.get_finality(chain_id),
+        current_block_number,
+    )
+    .await?;
+
+    let completed_contract_address_ids: Vec<_> = ended_contract_addresses
+        .iter()
+        .filter(|ca| ca.end_block_number.unwrap() as u64 <= finalized_block_number.get())
+        .map(|ca| ca.id)
+        .collect();
+
+    ChaindexingRepo::complete_contract_addresses(conn, &completed_contract_address_ids).await;
+
+    Ok(())
+}
+
 fn filter_uningested_contract_addresses(
     contract_addresses: &[ContractAddress],
     current_block_number: u64,
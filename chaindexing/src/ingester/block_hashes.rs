@@ -0,0 +1,287 @@
+use std::cmp::min;
+use std::sync::Arc;
+
+use ethers::types::U64;
+use futures_util::FutureExt;
+
+use super::provider::{self, Provider, ProviderError, RetryPolicy};
+use super::IngesterError;
+use crate::blocks::BlockHash;
+use crate::chain_reorg::{FinalizedBlockNumber, UnsavedReorgedBlock};
+use crate::{ChainId, ChaindexingRepo, ChaindexingRepoConn, Repo};
+
+/// Bounds the blocks tracked per run, for chains that are far behind
+const MAX_BLOCK_HASHES_PER_RUN: u64 = 256;
+
+/// Tracks the hashes of the chain's blocks that are not final yet. When fetched
+/// blocks stop linking to the tracked hashes, the fork point gets recorded as
+/// a reorged block and everything ingested from the fork point gets ingested again.
+pub async fn track<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    chain_id: &ChainId,
+    current_block_number: u64,
+    finalized_block_number: &FinalizedBlockNumber,
+) -> Result<(), IngesterError> {
+    let finalized_block_number = finalized_block_number.get();
+
+    // Final blocks can no longer fork off
+    ChaindexingRepo::prune_block_hashes(conn, *chain_id as i64, finalized_block_number as i64)
+        .await;
+    let tracked_block_hashes = ChaindexingRepo::get_block_hashes(conn, *chain_id as i64).await;
+
+    let from_block_number = tracked_block_hashes
+        .last()
+        .map(|b| b.number as u64 + 1)
+        .unwrap_or(finalized_block_number);
+    let to_block_number = min(
+        current_block_number,
+        from_block_number + MAX_BLOCK_HASHES_PER_RUN - 1,
+    );
+
+    if from_block_number > to_block_number {
+        return Ok(());
+    }
+
+    let block_numbers: Vec<_> = (from_block_number..=to_block_number).collect();
+    let fetched_block_hashes: Vec<_> =
+        provider::fetch_blocks(provider, retry_policy, &block_numbers)
+            .await?
+            .iter()
+            // Pending blocks have no hash to be tracked by
+            .filter(|block| block.hash.is_some())
+            .map(|block| BlockHash::new(chain_id, block))
+            .collect();
+
+    let Some(first_fetched_block_hash) = fetched_block_hashes.first() else {
+        return Ok(());
+    };
+
+    match find_fork_point(
+        provider,
+        retry_policy,
+        chain_id,
+        &tracked_block_hashes,
+        first_fetched_block_hash,
+    )
+    .await?
+    {
+        Some(fork_point) => {
+            let reorged_block = UnsavedReorgedBlock::new(fork_point, chain_id);
+
+            ChaindexingRepo::run_in_transaction(conn, move |conn| {
+                async move {
+                    ChaindexingRepo::create_reorged_block(conn, &reorged_block).await;
+                    ChaindexingRepo::rewind_to_reorged_block(conn, &reorged_block).await;
+
+                    Ok(())
+                }
+                .boxed()
+            })
+            .await?;
+        }
+        None => {
+            let linked_block_hashes = get_linked(&fetched_block_hashes);
+
+            ChaindexingRepo::upsert_block_hashes(conn, &linked_block_hashes).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks back from the fetched block through its canonical parents until a parent
+/// hash matches a tracked hash. The fork point is the earliest tracked block that
+/// is no longer canonical, if any.
+async fn find_fork_point(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    chain_id: &ChainId,
+    tracked_block_hashes: &[BlockHash],
+    fetched_block_hash: &BlockHash,
+) -> Result<Option<i64>, ProviderError> {
+    let Some(last_tracked_block_number) = tracked_block_hashes.last().map(|b| b.number) else {
+        return Ok(None);
+    };
+    let mut block_hash = fetched_block_hash.clone();
+
+    loop {
+        let Some(tracked_parent) =
+            tracked_block_hashes.iter().find(|b| b.number + 1 == block_hash.number)
+        else {
+            // Every tracked block forked off
+            return Ok(tracked_block_hashes.first().map(|b| b.number));
+        };
+
+        if tracked_parent.is_parent_of(&block_hash) {
+            return Ok(Some(block_hash.number).filter(|n| *n <= last_tracked_block_number));
+        }
+
+        let canonical_parent = provider::fetch_block(
+            provider,
+            retry_policy,
+            U64::from(tracked_parent.number as u64),
+        )
+        .await?;
+        block_hash = BlockHash::new(chain_id, &canonical_parent);
+    }
+}
+
+/// Returns the fetched block hashes up to the first one not linked to its
+/// predecessor, since blocks fetched apart can straddle a reorg
+fn get_linked(block_hashes: &[BlockHash]) -> Vec<BlockHash> {
+    let linked_count = block_hashes
+        .windows(2)
+        .position(|pair| !pair[0].is_parent_of(&pair[1]))
+        .map(|position| position + 1)
+        .unwrap_or(block_hashes.len());
+
+    block_hashes[..linked_count].to_vec()
+}
+
+#[cfg(test)]
+mod block_hashes_tests {
+    use std::collections::HashMap;
+
+    use ethers::types::{Block, Filter, Log, TxHash, H256};
+
+    use super::*;
+    use crate::ingester::ProviderError;
+
+    fn block(number: u64, hash: H256, parent_hash: H256) -> Block<TxHash> {
+        Block {
+            number: Some(U64::from(number)),
+            hash: Some(hash),
+            parent_hash,
+            ..Default::default()
+        }
+    }
+
+    fn chain_of(from_block_number: u64, count: usize) -> Vec<Block<TxHash>> {
+        let mut blocks: Vec<Block<TxHash>> = vec![];
+
+        for number in from_block_number..from_block_number + count as u64 {
+            let parent_hash = blocks.last().map(|b| b.hash.unwrap()).unwrap_or(H256::random());
+            blocks.push(block(number, H256::random(), parent_hash));
+        }
+
+        blocks
+    }
+
+    fn to_block_hashes(blocks: &[Block<TxHash>]) -> Vec<BlockHash> {
+        blocks.iter().map(|b| BlockHash::new(&ChainId::Mainnet, b)).collect()
+    }
+
+    #[derive(Clone)]
+    struct CanonicalProvider {
+        blocks_by_number: HashMap<U64, Block<TxHash>>,
+    }
+
+    impl CanonicalProvider {
+        fn new(blocks: &[Block<TxHash>]) -> Self {
+            Self {
+                blocks_by_number: blocks.iter().map(|b| (b.number.unwrap(), b.clone())).collect(),
+            }
+        }
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl Provider for CanonicalProvider {
+        async fn get_block_number(&self) -> Result<U64, ProviderError> {
+            Ok(*self.blocks_by_number.keys().max().unwrap())
+        }
+
+        async fn get_logs(&self, _filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+            Ok(vec![])
+        }
+
+        async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+            Ok(self.blocks_by_number[&block_number].clone())
+        }
+    }
+
+    /// Forks the chain off at the block, returning the new canonical chain
+    fn fork_at(blocks: &[Block<TxHash>], fork_block_number: u64) -> Vec<Block<TxHash>> {
+        let mut canonical_blocks: Vec<_> = blocks
+            .iter()
+            .filter(|b| b.number.unwrap().as_u64() < fork_block_number)
+            .cloned()
+            .collect();
+
+        for number in fork_block_number..=blocks.last().unwrap().number.unwrap().as_u64() + 1 {
+            let parent_hash =
+                canonical_blocks.last().map(|b| b.hash.unwrap()).unwrap_or(H256::random());
+            canonical_blocks.push(block(number, H256::random(), parent_hash));
+        }
+
+        canonical_blocks
+    }
+
+    #[tokio::test]
+    async fn does_not_find_a_fork_point_when_fetched_blocks_link_to_tracked_ones() {
+        let blocks = chain_of(100, 6);
+        let provider = Arc::new(CanonicalProvider::new(&blocks));
+        let block_hashes = to_block_hashes(&blocks);
+
+        let fork_point = find_fork_point(
+            &provider,
+            &RetryPolicy::default(),
+            &ChainId::Mainnet,
+            &block_hashes[..5],
+            &block_hashes[5],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fork_point, None);
+    }
+
+    #[tokio::test]
+    async fn finds_the_fork_point_by_parent_hash_mismatch() {
+        let blocks = chain_of(100, 5);
+        let canonical_blocks = fork_at(&blocks, 102);
+        let provider = Arc::new(CanonicalProvider::new(&canonical_blocks));
+
+        let fork_point = find_fork_point(
+            &provider,
+            &RetryPolicy::default(),
+            &ChainId::Mainnet,
+            &to_block_hashes(&blocks),
+            &to_block_hashes(&canonical_blocks)[5],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fork_point, Some(102));
+    }
+
+    #[tokio::test]
+    async fn finds_the_earliest_tracked_block_when_every_tracked_block_forked_off() {
+        let blocks = chain_of(100, 5);
+        let canonical_blocks = fork_at(&blocks, 99);
+        let provider = Arc::new(CanonicalProvider::new(&canonical_blocks));
+
+        let fork_point = find_fork_point(
+            &provider,
+            &RetryPolicy::default(),
+            &ChainId::Mainnet,
+            &to_block_hashes(&blocks),
+            to_block_hashes(&canonical_blocks).last().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fork_point, Some(100));
+    }
+
+    #[test]
+    fn keeps_only_linked_block_hashes() {
+        let mut block_hashes = to_block_hashes(&chain_of(100, 4));
+        block_hashes[2].parent_hash = format!("{:?}", H256::random());
+
+        let linked_block_hashes = get_linked(&block_hashes);
+
+        assert_eq!(linked_block_hashes, block_hashes[..2].to_vec());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use ethers::types::{Block as EthersBlock, Log, TxHash, U64};
+
+use super::filters::Filter;
+use super::provider::{self, Provider, ProviderError, RetryPolicy};
+use crate::blocks::Block;
+use crate::handlers::block_handler::{self, ChainBlockHandler};
+use crate::{ChainId, ChaindexingRepo, ChaindexingRepoConn, Repo};
+
+/// Reads the logs' blocks through `chaindexing_blocks`, fetching only blocks
+/// that are not cached yet or whose cached hash no longer matches the logs'.
+/// Pending logs have no blocks yet and get quarantined with `events::get`.
+pub async fn get_by_number<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    chain_id: &ChainId,
+    logs: &[Log],
+) -> Result<HashMap<U64, EthersBlock<TxHash>>, ProviderError> {
+    let block_numbers: Vec<_> = logs
+        .iter()
+        .filter_map(|log| log.block_number)
+        .map(|n| n.as_u64() as i64)
+        .collect();
+
+    let cached_blocks = ChaindexingRepo::get_blocks(conn, *chain_id as i64, &block_numbers).await;
+    let mut blocks_by_number: HashMap<_, _> = cached_blocks
+        .iter()
+        .map(|block| (U64::from(block.number), block.to_ethers_block()))
+        .collect();
+
+    let uncached_logs: Vec<_> = logs
+        .iter()
+        .filter(
+            |log| match log.block_number.map(|n| blocks_by_number.get(&n)) {
+                Some(Some(cached_block)) => cached_block.hash != log.block_hash,
+                Some(None) => true,
+                None => false,
+            },
+        )
+        .cloned()
+        .collect();
+
+    if !uncached_logs.is_empty() {
+        let fetched_blocks_by_number =
+            provider::fetch_blocks_by_number(provider, retry_policy, &uncached_logs).await?;
+
+        let fetched_blocks: Vec<_> = fetched_blocks_by_number
+            .values()
+            // Pending blocks have no hash to be cached by
+            .filter(|block| block.hash.is_some())
+            .map(|block| Block::new(chain_id, block))
+            .collect();
+        ChaindexingRepo::upsert_blocks(conn, &fetched_blocks).await;
+
+        blocks_by_number.extend(fetched_blocks_by_number);
+    }
+
+    Ok(blocks_by_number)
+}
+
+/// Caches the headers of blocks that block handlers run at within the filters'
+/// block ranges, so block handlers never have to fetch them
+pub async fn cache_handled<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    chain_id: &ChainId,
+    block_handlers: &[ChainBlockHandler],
+    filters: &[Filter],
+) -> Result<(), ProviderError> {
+    let block_numbers = get_handled_block_numbers(block_handlers, filters);
+
+    let cached_blocks = ChaindexingRepo::get_blocks(conn, *chain_id as i64, &block_numbers).await;
+    let cached_block_numbers: HashSet<_> = cached_blocks.iter().map(|b| b.number).collect();
+
+    let uncached_block_numbers: Vec<_> = block_numbers
+        .iter()
+        .filter(|n| !cached_block_numbers.contains(n))
+        .map(|n| *n as u64)
+        .collect();
+
+    if !uncached_block_numbers.is_empty() {
+        let fetched_blocks =
+            provider::fetch_blocks(provider, retry_policy, &uncached_block_numbers).await?;
+
+        let fetched_blocks: Vec<_> = fetched_blocks
+            .iter()
+            .filter(|block| block.hash.is_some())
+            .map(|block| Block::new(chain_id, block))
+            .collect();
+        ChaindexingRepo::upsert_blocks(conn, &fetched_blocks).await;
+    }
+
+    Ok(())
+}
+
+/// Refetches the cached headers of blocks that block handlers ran at, recaching
+/// the ones whose hash changed. Returns the numbers of these reorged blocks.
+pub async fn get_reorged_handled<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    chain_id: &ChainId,
+    block_handlers: &[ChainBlockHandler],
+    filters: &[Filter],
+) -> Result<Vec<i64>, ProviderError> {
+    let block_numbers = get_handled_block_numbers(block_handlers, filters);
+
+    let cached_blocks = ChaindexingRepo::get_blocks(conn, *chain_id as i64, &block_numbers).await;
+
+    if cached_blocks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let cached_block_numbers: Vec<_> = cached_blocks.iter().map(|b| b.number as u64).collect();
+    let fetched_blocks =
+        provider::fetch_blocks(provider, retry_policy, &cached_block_numbers).await?;
+
+    let reorged_blocks: Vec<_> = fetched_blocks
+        .iter()
+        .filter(|block| block.hash.is_some())
+        .map(|block| Block::new(chain_id, block))
+        .filter(|block| {
+            cached_blocks.iter().any(|cached_block| {
+                cached_block.number == block.number && cached_block.hash != block.hash
+            })
+        })
+        .collect();
+
+    ChaindexingRepo::upsert_blocks(conn, &reorged_blocks).await;
+
+    Ok(reorged_blocks.iter().map(|b| b.number).collect())
+}
+
+fn get_handled_block_numbers(block_handlers: &[ChainBlockHandler], filters: &[Filter]) -> Vec<i64> {
+    let block_numbers: BTreeSet<_> = filters
+        .iter()
+        .flat_map(|filter| {
+            let from_block_number = filter.value.get_from_block().unwrap().as_u64();
+            let to_block_number = filter.value.get_to_block().unwrap().as_u64();
+
+            block_handler::get_handled_block_numbers(
+                block_handlers,
+                from_block_number,
+                to_block_number + 1,
+            )
+        })
+        .map(|n| n as i64)
+        .collect();
+
+    block_numbers.into_iter().collect()
+}
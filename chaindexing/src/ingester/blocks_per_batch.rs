@@ -0,0 +1,87 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+/// Remembers how many blocks each contract address can fetch per `eth_getLogs`
+/// request after a provider rejected a wider range. Narrowed batches grow back
+/// by a quarter after every batch the provider accepts, until they reach the
+/// configured `blocks_per_batch` again.
+#[derive(Clone, Debug)]
+pub struct AdaptiveBlocksPerBatch {
+    blocks_per_batch: u64,
+    narrowed_by_contract_address_id: HashMap<i64, u64>,
+}
+
+impl AdaptiveBlocksPerBatch {
+    pub fn new(blocks_per_batch: u64) -> Self {
+        Self {
+            blocks_per_batch,
+            narrowed_by_contract_address_id: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, contract_address_id: i64) -> u64 {
+        match self.narrowed_by_contract_address_id.get(&contract_address_id) {
+            Some(narrowed_blocks_per_batch) => {
+                min(*narrowed_blocks_per_batch, self.blocks_per_batch)
+            }
+            None => self.blocks_per_batch,
+        }
+    }
+
+    pub fn narrow(&mut self, contract_address_id: i64, accepted_blocks_per_batch: u64) {
+        self.narrowed_by_contract_address_id
+            .insert(contract_address_id, accepted_blocks_per_batch);
+    }
+
+    pub fn grow(&mut self, contract_address_id: i64) {
+        if let Some(narrowed_blocks_per_batch) =
+            self.narrowed_by_contract_address_id.get(&contract_address_id).cloned()
+        {
+            let grown_blocks_per_batch =
+                narrowed_blocks_per_batch + max(narrowed_blocks_per_batch / 4, 1);
+
+            if grown_blocks_per_batch >= self.blocks_per_batch {
+                self.narrowed_by_contract_address_id.remove(&contract_address_id);
+            } else {
+                self.narrowed_by_contract_address_id
+                    .insert(contract_address_id, grown_blocks_per_batch);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod adaptive_blocks_per_batch_tests {
+    use super::*;
+
+    #[test]
+    fn returns_configured_blocks_per_batch_by_default() {
+        let adaptive_blocks_per_batch = AdaptiveBlocksPerBatch::new(8_000);
+
+        assert_eq!(adaptive_blocks_per_batch.get(1), 8_000);
+    }
+
+    #[test]
+    fn returns_narrowed_blocks_per_batch_per_contract_address() {
+        let mut adaptive_blocks_per_batch = AdaptiveBlocksPerBatch::new(8_000);
+
+        adaptive_blocks_per_batch.narrow(1, 1_000);
+
+        assert_eq!(adaptive_blocks_per_batch.get(1), 1_000);
+        assert_eq!(adaptive_blocks_per_batch.get(2), 8_000);
+    }
+
+    #[test]
+    fn grows_back_to_configured_blocks_per_batch() {
+        let mut adaptive_blocks_per_batch = AdaptiveBlocksPerBatch::new(2_000);
+
+        adaptive_blocks_per_batch.narrow(1, 1_000);
+        adaptive_blocks_per_batch.grow(1);
+        assert_eq!(adaptive_blocks_per_batch.get(1), 1_250);
+
+        for _ in 0..3 {
+            adaptive_blocks_per_batch.grow(1);
+        }
+        assert_eq!(adaptive_blocks_per_batch.get(1), 2_000);
+    }
+}
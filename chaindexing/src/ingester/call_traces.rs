@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use super::filters::Filter;
+use super::provider::{self, Provider, ProviderError, RetryPolicy};
+use crate::call_traces::{self, CallTrace};
+use crate::{ChainId, Contract, ContractAddress, TraceMode};
+
+/// Fetches the calls made to the filters' contract addresses
+#[allow(clippy::too_many_arguments)]
+pub async fn get<S: Send + Sync + Clone>(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filters: &[Filter],
+    trace_mode: Option<&TraceMode>,
+    max_addresses_per_filter: usize,
+    contracts: &[Contract<S>],
+    contract_addresses: &[ContractAddress],
+    chain_id: &ChainId,
+) -> Result<Vec<CallTrace>, ProviderError> {
+    match trace_mode {
+        Some(trace_mode) if !filters.is_empty() => {
+            let traced_calls = provider::fetch_call_traces(
+                provider,
+                retry_policy,
+                filters,
+                trace_mode,
+                max_addresses_per_filter,
+            )
+            .await?;
+
+            Ok(call_traces::get(
+                &traced_calls,
+                contracts,
+                contract_addresses,
+                chain_id,
+            ))
+        }
+        _ => Ok(vec![]),
+    }
+}
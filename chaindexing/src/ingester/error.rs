@@ -1,8 +1,11 @@
+use super::ProviderError;
 use crate::RepoError;
 
 #[derive(Debug)]
 pub enum IngesterError {
     RepoConnectionError,
+    /// Fatal provider errors, or transient ones still failing after every retry
+    ProviderError(ProviderError),
     GenericError(String),
 }
 
@@ -14,3 +17,9 @@ impl From<RepoError> for IngesterError {
         }
     }
 }
+
+impl From<ProviderError> for IngesterError {
+    fn from(value: ProviderError) -> Self {
+        IngesterError::ProviderError(value)
+    }
+}
@@ -1,38 +1,168 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ethers::types::{Address, Filter as EthersFilter};
 use std::cmp::min;
 
+use super::blocks_per_batch::AdaptiveBlocksPerBatch;
 use crate::chain_reorg::Execution;
-use crate::contracts;
 use crate::contracts::Contract;
-use crate::contracts::ContractEventTopic;
-use crate::ContractAddress;
+use crate::contracts::LogTopics;
+use crate::handlers::block_handler::ChainBlockHandler;
+use crate::{ContractAddress, TraceMode};
 
 pub fn get<S: Send + Sync + Clone>(
     contract_addresses: &[ContractAddress],
     contracts: &[Contract<S>],
     current_block_number: u64,
-    blocks_per_batch: u64,
+    blocks_per_batch: &AdaptiveBlocksPerBatch,
     execution: &Execution,
 ) -> Vec<Filter> {
-    let topics_by_contract_name = contracts::group_event_topics_by_names(contracts);
+    let contracts_by_name: HashMap<_, _> = contracts.iter().map(|c| (c.name.as_str(), c)).collect();
 
     contract_addresses
         .iter()
         .filter_map(|contract_address| {
-            topics_by_contract_name.get(contract_address.contract_name.as_str()).and_then(
-                |topics| {
-                    Filter::maybe_new(
-                        contract_address,
-                        topics,
-                        current_block_number,
-                        blocks_per_batch,
-                        execution,
-                    )
-                },
+            let contract = contracts_by_name.get(contract_address.contract_name.as_str())?;
+            let filter = Filter::maybe_new(
+                contract_address,
+                &Default::default(),
+                current_block_number,
+                blocks_per_batch.get(contract_address.id),
+                execution,
+            )?;
+
+            Some((contract, contract_address.chain_id, filter))
+        })
+        .flat_map(|(contract, chain_id, filter)| {
+            let from_block_number = filter.value.get_from_block().unwrap().as_u64();
+            let to_block_number = filter.value.get_to_block().unwrap().as_u64();
+
+            // Upgraded contracts switch log topics at their versions' blocks
+            contract
+                .get_log_topics_by_block_ranges(
+                    chain_id,
+                    &filter.address,
+                    from_block_number,
+                    to_block_number,
+                )
+                .into_iter()
+                .flat_map(move |(from_block_number, to_block_number, log_topics)| {
+                    let filter = filter.clone();
+
+                    log_topics.into_iter().map(move |topics| Filter {
+                        value: EthersFilter {
+                            topics,
+                            ..filter
+                                .value
+                                .clone()
+                                .from_block(from_block_number)
+                                .to_block(to_block_number)
+                        },
+                        ..filter.clone()
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Block ranges of transactions to ingest, for contract addresses with transaction handlers.
+/// These filters carry no topics as they are never sent to `eth_getLogs`.
+pub fn get_for_transactions<S: Send + Sync + Clone>(
+    contract_addresses: &[ContractAddress],
+    contracts: &[Contract<S>],
+    current_block_number: u64,
+    blocks_per_batch: &AdaptiveBlocksPerBatch,
+    execution: &Execution,
+) -> Vec<Filter> {
+    let contract_names_with_transaction_handlers: HashSet<_> = contracts
+        .iter()
+        .filter(|contract| !contract.transaction_handlers.is_empty())
+        .map(|contract| contract.name.as_str())
+        .collect();
+
+    let contract_addresses: Vec<_> = contract_addresses
+        .iter()
+        .filter(|contract_address| {
+            contract_names_with_transaction_handlers
+                .contains(contract_address.contract_name.as_str())
+        })
+        .cloned()
+        .collect();
+
+    get_block_ranges(
+        &contract_addresses,
+        current_block_number,
+        blocks_per_batch,
+        execution,
+    )
+}
+
+/// Block ranges of call traces to ingest, for every contract address of traced chains
+pub fn get_for_call_traces(
+    contract_addresses: &[ContractAddress],
+    trace_mode: Option<&TraceMode>,
+    current_block_number: u64,
+    blocks_per_batch: &AdaptiveBlocksPerBatch,
+    execution: &Execution,
+) -> Vec<Filter> {
+    match trace_mode {
+        Some(_trace_mode) => get_block_ranges(
+            contract_addresses,
+            current_block_number,
+            blocks_per_batch,
+            execution,
+        ),
+        None => vec![],
+    }
+}
+
+/// Block ranges of block headers to cache, for chains with block handlers
+pub fn get_for_blocks(
+    contract_addresses: &[ContractAddress],
+    block_handlers: &[ChainBlockHandler],
+    current_block_number: u64,
+    blocks_per_batch: &AdaptiveBlocksPerBatch,
+    execution: &Execution,
+) -> Vec<Filter> {
+    if block_handlers.is_empty() {
+        vec![]
+    } else {
+        get_block_ranges(
+            contract_addresses,
+            current_block_number,
+            blocks_per_batch,
+            execution,
+        )
+    }
+}
+
+fn get_block_ranges(
+    contract_addresses: &[ContractAddress],
+    current_block_number: u64,
+    blocks_per_batch: &AdaptiveBlocksPerBatch,
+    execution: &Execution,
+) -> Vec<Filter> {
+    contract_addresses
+        .iter()
+        .filter_map(|contract_address| {
+            Filter::maybe_new(
+                contract_address,
+                &Default::default(),
+                current_block_number,
+                blocks_per_batch.get(contract_address.id),
+                execution,
             )
         })
+        .map(|mut filter| {
+            // Unlike eth_getLogs' block ranges, blocks past the chain's head cannot be fetched
+            let to_block_number = min(
+                filter.value.get_to_block().unwrap().as_u64(),
+                current_block_number,
+            );
+            filter.value = filter.value.to_block(to_block_number);
+
+            filter
+        })
         .collect()
 }
 
@@ -73,7 +203,7 @@ pub struct Filter {
 impl Filter {
     fn maybe_new(
         contract_address: &ContractAddress,
-        topics: &[ContractEventTopic],
+        topics: &LogTopics,
         current_block_number: u64,
         blocks_per_batch: u64,
         execution: &Execution,
@@ -83,10 +213,12 @@ impl Filter {
             next_block_number_to_ingest_from,
             start_block_number,
             address,
+            end_block_number,
             ..
         } = contract_address;
 
         let next_block_number_to_ingest_from = *next_block_number_to_ingest_from as u64;
+        let end_block_number = end_block_number.map(|n| n as u64).unwrap_or(u64::MAX);
 
         match execution {
             Execution::Main => Some((
@@ -96,14 +228,15 @@ impl Filter {
                     current_block_number,
                 ),
             )),
-            Execution::Confirmation(min_confirmation_count) => {
+            Execution::Confirmation(finalized_block_number) => {
                 // TODO: Move logic to higher level
-                if min_confirmation_count.is_in_confirmation_window(
-                    next_block_number_to_ingest_from,
-                    current_block_number,
-                ) {
+                if finalized_block_number
+                    .is_in_confirmation_window(next_block_number_to_ingest_from)
+                    // Final end blocks need no confirmation
+                    && end_block_number > finalized_block_number.get()
+                {
                     Some((
-                        min_confirmation_count.deduct_from(
+                        finalized_block_number.deduct_from(
                             next_block_number_to_ingest_from,
                             *start_block_number as u64,
                         ),
@@ -114,14 +247,251 @@ impl Filter {
                 }
             }
         }
+        .map(|(from_block_number, to_block_number)| {
+            (from_block_number, min(to_block_number, end_block_number))
+        })
+        .filter(|(from_block_number, to_block_number)| from_block_number <= to_block_number)
         .map(|(from_block_number, to_block_number)| Filter {
             contract_address_id: *contract_address_id,
             address: address.to_string(),
+            value: EthersFilter {
+                topics: topics.clone(),
+                ..EthersFilter::new()
+                    .address(address.parse::<Address>().unwrap())
+                    .from_block(from_block_number)
+                    .to_block(to_block_number)
+            },
+        })
+    }
+}
+
+/// Merges filters sharing the same topics and block range into filters over
+/// all their addresses, so they get fetched with a single `eth_getLogs` request.
+/// Logs get routed back to their contract addresses by the logs' address.
+pub fn merge(filters: &[Filter], max_addresses_per_filter: usize) -> Vec<MergedFilter> {
+    let mut filter_values = vec![];
+    let mut filters_by_value: HashMap<EthersFilter, Vec<&Filter>> = HashMap::new();
+
+    for filter in filters {
+        let filter_value = EthersFilter {
+            address: None,
+            ..filter.value.clone()
+        };
+
+        filters_by_value
+            .entry(filter_value.clone())
+            .or_insert_with(|| {
+                filter_values.push(filter_value);
+
+                vec![]
+            })
+            .push(filter);
+    }
+
+    filter_values
+        .iter()
+        .flat_map(|filter_value| {
+            filters_by_value[filter_value]
+                .chunks(max_addresses_per_filter.max(1))
+                .map(|filters| MergedFilter {
+                    contract_address_ids: filters.iter().map(|f| f.contract_address_id).collect(),
+                    value: filter_value.clone().address(
+                        filters
+                            .iter()
+                            .map(|f| f.address.parse::<Address>().unwrap())
+                            .collect::<Vec<_>>(),
+                    ),
+                })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct MergedFilter {
+    pub contract_address_ids: Vec<i64>,
+    pub value: EthersFilter,
+}
+
+impl MergedFilter {
+    pub fn get_blocks_count(&self) -> u64 {
+        let from_block_number = self.value.get_from_block().unwrap().as_u64();
+        let to_block_number = self.value.get_to_block().unwrap().as_u64();
+
+        to_block_number - from_block_number
+    }
+
+    /// Splits the filter's block range in half.
+    /// Returns `None` for filters covering a single block.
+    pub fn split(&self) -> Option<(MergedFilter, MergedFilter)> {
+        let from_block_number = self.value.get_from_block().unwrap().as_u64();
+        let to_block_number = self.value.get_to_block().unwrap().as_u64();
+
+        if from_block_number >= to_block_number {
+            return None;
+        }
+
+        let middle_block_number = from_block_number + (to_block_number - from_block_number) / 2;
+
+        Some((
+            self.with_block_range(from_block_number, middle_block_number),
+            self.with_block_range(middle_block_number + 1, to_block_number),
+        ))
+    }
+
+    fn with_block_range(&self, from_block_number: u64, to_block_number: u64) -> MergedFilter {
+        MergedFilter {
+            value: self.value.clone().from_block(from_block_number).to_block(to_block_number),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+    use crate::chain_reorg::FinalizedBlockNumber;
+    use crate::contracts::ContractAddressStatus;
+    use ethers::types::ValueOrArray;
+
+    fn filter(contract_address_id: i64, from_block_number: u64, to_block_number: u64) -> Filter {
+        let address = format!("0xb7e8a7c96b2d4d4d4a6ee3a3fb71b0ad8d2ae6{contract_address_id:02}");
+
+        Filter {
+            contract_address_id,
+            address: address.clone(),
             value: EthersFilter::new()
                 .address(address.parse::<Address>().unwrap())
-                .topic0(topics.to_vec())
                 .from_block(from_block_number)
                 .to_block(to_block_number),
-        })
+        }
+    }
+
+    fn merged_filter(from_block_number: u64, to_block_number: u64) -> MergedFilter {
+        merge(&[filter(1, from_block_number, to_block_number)], 10).pop().unwrap()
+    }
+
+    fn get_block_range(filter: &MergedFilter) -> (u64, u64) {
+        (
+            filter.value.get_from_block().unwrap().as_u64(),
+            filter.value.get_to_block().unwrap().as_u64(),
+        )
+    }
+
+    fn get_addresses_count(filter: &MergedFilter) -> usize {
+        match filter.value.address.as_ref().unwrap() {
+            ValueOrArray::Value(_) => 1,
+            ValueOrArray::Array(addresses) => addresses.len(),
+        }
+    }
+
+    #[test]
+    fn merges_filters_with_the_same_block_range() {
+        let merged_filters = merge(&[filter(1, 100, 200), filter(2, 100, 200)], 10);
+
+        assert_eq!(merged_filters.len(), 1);
+        assert_eq!(merged_filters[0].contract_address_ids, vec![1, 2]);
+        assert_eq!(get_addresses_count(&merged_filters[0]), 2);
+    }
+
+    #[test]
+    fn does_not_merge_filters_with_different_block_ranges() {
+        let merged_filters = merge(&[filter(1, 100, 200), filter(2, 150, 200)], 10);
+
+        assert_eq!(merged_filters.len(), 2);
+    }
+
+    #[test]
+    fn caps_addresses_per_merged_filter() {
+        let filters: Vec<_> = (1..=5).map(|id| filter(id, 100, 200)).collect();
+
+        let merged_filters = merge(&filters, 2);
+
+        assert_eq!(
+            merged_filters.iter().map(get_addresses_count).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+    }
+
+    #[test]
+    fn splits_block_range_in_half() {
+        let (first_half, second_half) = merged_filter(100, 200).split().unwrap();
+
+        assert_eq!(get_block_range(&first_half), (100, 150));
+        assert_eq!(get_block_range(&second_half), (151, 200));
+    }
+
+    #[test]
+    fn splits_two_blocks_into_single_blocks() {
+        let (first_half, second_half) = merged_filter(100, 101).split().unwrap();
+
+        assert_eq!(get_block_range(&first_half), (100, 100));
+        assert_eq!(get_block_range(&second_half), (101, 101));
+    }
+
+    #[test]
+    fn does_not_split_a_single_block() {
+        assert!(merged_filter(100, 100).split().is_none());
+    }
+
+    fn ended_contract_address(
+        next_block_number_to_ingest_from: i64,
+        end_block_number: i64,
+    ) -> ContractAddress {
+        ContractAddress {
+            id: 1,
+            chain_id: 1,
+            next_block_number_to_ingest_from,
+            next_block_number_to_handle_from: 0,
+            next_block_number_for_side_effects: 0,
+            start_block_number: 0,
+            address: format!("{:?}", Address::random()),
+            contract_name: "Token".to_string(),
+            status: ContractAddressStatus::Active,
+            end_block_number: Some(end_block_number),
+        }
+    }
+
+    fn get_filter_block_range(filter: &Filter) -> (u64, u64) {
+        (
+            filter.value.get_from_block().unwrap().as_u64(),
+            filter.value.get_to_block().unwrap().as_u64(),
+        )
+    }
+
+    #[test]
+    fn stops_ingesting_at_end_blocks() {
+        let contract_address = ended_contract_address(100, 150);
+        let filter = |contract_address| {
+            Filter::maybe_new(
+                contract_address,
+                &Default::default(),
+                1000,
+                200,
+                &Execution::Main,
+            )
+        };
+
+        assert_eq!(
+            get_filter_block_range(&filter(&contract_address).unwrap()),
+            (100, 150)
+        );
+        assert!(filter(&ended_contract_address(151, 150)).is_none());
+    }
+
+    #[test]
+    fn stops_confirming_final_end_blocks() {
+        let contract_address = ended_contract_address(151, 150);
+        let filter = |finalized_block_number| {
+            Filter::maybe_new(
+                &contract_address,
+                &Default::default(),
+                1000,
+                200,
+                &Execution::Confirmation(&FinalizedBlockNumber::new(finalized_block_number)),
+            )
+        };
+
+        assert_eq!(get_filter_block_range(&filter(140).unwrap()), (140, 150));
+        assert!(filter(150).is_none());
     }
 }
@@ -3,18 +3,24 @@ use std::sync::Arc;
 
 use futures_util::FutureExt;
 
+use super::blocks;
+use super::blocks_per_batch::AdaptiveBlocksPerBatch;
+use super::call_traces;
 use super::filters::{self, Filter};
 use super::provider::{self, Provider};
+use super::transactions;
 use super::IngesterError;
 
 use crate::chain_reorg::Execution;
+use crate::handlers::block_handler;
 use crate::Config;
-use crate::{events, ChainId};
+use crate::{contracts, events, ChainId};
 use crate::{
     ChaindexingRepo, ChaindexingRepoClient, ChaindexingRepoConn, ContractAddress,
     LoadsDataWithRawQuery, Repo,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run<'a, S: Send + Sync + Clone>(
     conn: &mut ChaindexingRepoConn<'a>,
     repo_client: &ChaindexingRepoClient,
@@ -22,37 +28,128 @@ pub async fn run<'a, S: Send + Sync + Clone>(
     provider: &Arc<impl Provider>,
     chain_id: &ChainId,
     current_block_number: u64,
-    Config {
+    config @ Config {
+        chains,
         contracts,
-        blocks_per_batch,
+        block_handlers,
+        max_addresses_per_filter,
         ..
     }: &Config<S>,
+    blocks_per_batch: &mut AdaptiveBlocksPerBatch,
 ) -> Result<(), IngesterError> {
+    let trace_mode = chains.iter().find(|c| c.id == *chain_id).and_then(|c| c.trace_mode.as_ref());
+    let block_handlers = block_handler::get_for_chain(block_handlers, *chain_id as u64);
+    let retry_policy = config.get_retry_policy(chain_id);
+
     let filters = filters::get(
         &contract_addresses,
         contracts,
         current_block_number,
-        *blocks_per_batch,
+        blocks_per_batch,
         &Execution::Main,
     );
 
     let filters = remove_already_ingested_filters(&filters, &contract_addresses, repo_client).await;
 
-    if !filters.is_empty() {
-        let logs = provider::fetch_logs(provider, &filters).await;
-        let blocks_by_tx_hash = provider::fetch_blocks_by_number(provider, &logs).await;
-        let events = events::get(
+    let transaction_filters = filters::get_for_transactions(
+        &contract_addresses,
+        contracts,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Main,
+    );
+
+    let call_trace_filters = filters::get_for_call_traces(
+        &contract_addresses,
+        trace_mode,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Main,
+    );
+
+    let block_filters = filters::get_for_blocks(
+        &contract_addresses,
+        &block_handlers,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Main,
+    );
+
+    if !filters.is_empty()
+        || !transaction_filters.is_empty()
+        || !call_trace_filters.is_empty()
+        || !block_filters.is_empty()
+    {
+        let logs = provider::fetch_logs(
+            provider,
+            retry_policy,
+            &filters,
+            *max_addresses_per_filter,
+            blocks_per_batch,
+        )
+        .await?;
+        let blocks_by_number =
+            blocks::get_by_number(conn, provider, retry_policy, chain_id, &logs).await?;
+        let (events, quarantined_logs) = events::get(
             &logs,
             contracts,
             &contract_addresses,
             chain_id,
-            &blocks_by_tx_hash,
+            &blocks_by_number,
         );
+        let spawned_contract_addresses =
+            contracts::get_spawned_contract_addresses(contracts, &events);
+        let transactions = transactions::get(
+            conn,
+            provider,
+            retry_policy,
+            &transaction_filters,
+            contracts,
+            &contract_addresses,
+            chain_id,
+        )
+        .await?;
+        let call_traces = call_traces::get(
+            provider,
+            retry_policy,
+            &call_trace_filters,
+            trace_mode,
+            *max_addresses_per_filter,
+            contracts,
+            &contract_addresses,
+            chain_id,
+        )
+        .await?;
+        blocks::cache_handled(
+            conn,
+            provider,
+            retry_policy,
+            chain_id,
+            &block_handlers,
+            &block_filters,
+        )
+        .await?;
+
         let contract_addresses = contract_addresses.clone();
+        let filters = [
+            filters,
+            transaction_filters,
+            call_trace_filters,
+            block_filters,
+        ]
+        .concat();
 
         ChaindexingRepo::run_in_transaction(conn, move |conn| {
             async move {
                 ChaindexingRepo::create_events(conn, &events.clone()).await;
+                ChaindexingRepo::create_quarantined_logs(conn, &quarantined_logs.clone()).await;
+                ChaindexingRepo::create_spawned_contract_addresses(
+                    conn,
+                    &spawned_contract_addresses.clone(),
+                )
+                .await;
+                ChaindexingRepo::create_transactions(conn, &transactions.clone()).await;
+                ChaindexingRepo::create_call_traces(conn, &call_traces.clone()).await;
 
                 update_next_block_numbers_to_ingest_from(conn, &contract_addresses, &filters).await;
 
@@ -1,17 +1,23 @@
 use std::collections::HashSet;
+use std::hash::Hash;
 use std::sync::Arc;
 
 use futures_util::FutureExt;
-use std::cmp::min;
 
+use crate::call_traces::CallTrace;
 use crate::chain_reorg::{Execution, UnsavedReorgedBlock};
+use crate::contracts;
 use crate::events::{self, Event};
+use crate::handlers::block_handler;
+use crate::transactions::Transaction;
 use crate::Config;
 use crate::{ChainId, ChaindexingRepo, ChaindexingRepoConn, ContractAddress, Repo};
 
+use super::blocks;
+use super::blocks_per_batch::AdaptiveBlocksPerBatch;
 use super::filters::{self, Filter};
 use super::Provider;
-use super::{provider, IngesterError};
+use super::{call_traces, provider, transactions, IngesterError};
 
 pub async fn run<'a, S: Send + Sync + Clone>(
     conn: &mut ChaindexingRepoConn<'a>,
@@ -19,27 +25,79 @@ pub async fn run<'a, S: Send + Sync + Clone>(
     provider: &Arc<impl Provider>,
     chain_id: &ChainId,
     current_block_number: u64,
-    Config {
+    config @ Config {
         contracts,
-        min_confirmation_count,
-        blocks_per_batch,
+        block_handlers,
+        max_addresses_per_filter,
         ..
     }: &Config<S>,
+    blocks_per_batch: &mut AdaptiveBlocksPerBatch,
 ) -> Result<(), IngesterError> {
+    let trace_mode = config.get_chain(chain_id).and_then(|c| c.trace_mode.as_ref());
+    let retry_policy = config.get_retry_policy(chain_id);
+    let finalized_block_number = provider::fetch_finalized_block_number(
+        provider,
+        retry_policy,
+        config.get_finality(chain_id),
+        current_block_number,
+    )
+    .await?;
+    let block_handlers = block_handler::get_for_chain(block_handlers, *chain_id as u64);
+
     let filters = filters::get(
         &contract_addresses,
         contracts,
         current_block_number,
-        *blocks_per_batch,
-        &Execution::Confirmation(min_confirmation_count),
+        blocks_per_batch,
+        &Execution::Confirmation(&finalized_block_number),
+    );
+
+    let transaction_filters = filters::get_for_transactions(
+        &contract_addresses,
+        contracts,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Confirmation(&finalized_block_number),
+    );
+
+    let call_trace_filters = filters::get_for_call_traces(
+        &contract_addresses,
+        trace_mode,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Confirmation(&finalized_block_number),
     );
 
-    if !filters.is_empty() {
+    let block_filters = filters::get_for_blocks(
+        &contract_addresses,
+        &block_handlers,
+        current_block_number,
+        blocks_per_batch,
+        &Execution::Confirmation(&finalized_block_number),
+    );
+
+    if !filters.is_empty()
+        || !transaction_filters.is_empty()
+        || !call_trace_filters.is_empty()
+        || !block_filters.is_empty()
+    {
         let already_ingested_events = get_already_ingested_events(conn, &filters).await;
-        let logs = provider::fetch_logs(provider, &filters).await;
-        let blocks_by_number = provider::fetch_blocks_by_number(provider, &logs).await;
+        let already_ingested_transactions =
+            get_already_ingested_transactions(conn, &transaction_filters).await;
+        let already_ingested_call_traces =
+            get_already_ingested_call_traces(conn, &call_trace_filters).await;
+        let logs = provider::fetch_logs(
+            provider,
+            retry_policy,
+            &filters,
+            *max_addresses_per_filter,
+            blocks_per_batch,
+        )
+        .await?;
+        let blocks_by_number =
+            blocks::get_by_number(conn, provider, retry_policy, chain_id, &logs).await?;
 
-        let provider_events = events::get(
+        let (provider_events, quarantined_logs) = events::get(
             &logs,
             contracts,
             &contract_addresses,
@@ -47,10 +105,68 @@ pub async fn run<'a, S: Send + Sync + Clone>(
             &blocks_by_number,
         );
 
-        if let Some(added_and_removed_events) =
-            get_provider_added_and_removed_events(&already_ingested_events, &provider_events)
+        let provider_transactions = transactions::get(
+            conn,
+            provider,
+            retry_policy,
+            &transaction_filters,
+            contracts,
+            &contract_addresses,
+            chain_id,
+        )
+        .await?;
+
+        let provider_call_traces = call_traces::get(
+            provider,
+            retry_policy,
+            &call_trace_filters,
+            trace_mode,
+            *max_addresses_per_filter,
+            contracts,
+            &contract_addresses,
+            chain_id,
+        )
+        .await?;
+
+        // Both are unique by their logs' contents, so creating them again changes nothing
+        ChaindexingRepo::create_quarantined_logs(conn, &quarantined_logs).await;
+        ChaindexingRepo::create_spawned_contract_addresses(
+            conn,
+            &contracts::get_spawned_contract_addresses(contracts, &provider_events),
+        )
+        .await;
+
+        let added_and_removed_events =
+            get_provider_added_and_removed(&already_ingested_events, &provider_events);
+        let added_and_removed_transactions =
+            get_provider_added_and_removed(&already_ingested_transactions, &provider_transactions);
+        let added_and_removed_call_traces =
+            get_provider_added_and_removed(&already_ingested_call_traces, &provider_call_traces);
+
+        let reorged_block_numbers = blocks::get_reorged_handled(
+            conn,
+            provider,
+            retry_policy,
+            chain_id,
+            &block_handlers,
+            &block_filters,
+        )
+        .await?;
+
+        if added_and_removed_events.is_some()
+            || added_and_removed_transactions.is_some()
+            || added_and_removed_call_traces.is_some()
+            || !reorged_block_numbers.is_empty()
         {
-            handle_chain_reorg(conn, chain_id, added_and_removed_events).await?;
+            handle_chain_reorg(
+                conn,
+                chain_id,
+                added_and_removed_events.unwrap_or_default(),
+                added_and_removed_transactions.unwrap_or_default(),
+                added_and_removed_call_traces.unwrap_or_default(),
+                &reorged_block_numbers,
+            )
+            .await?;
         }
     }
 
@@ -75,12 +191,67 @@ async fn get_already_ingested_events<'a>(
     already_ingested_events
 }
 
+async fn get_already_ingested_transactions<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    filters: &Vec<Filter>,
+) -> Vec<Transaction> {
+    let mut already_ingested_transactions = vec![];
+    for filter in filters {
+        let from_block = filter.value.get_from_block().unwrap().as_u64();
+        let to_block = filter.value.get_to_block().unwrap().as_u64();
+
+        let mut transactions = ChaindexingRepo::get_transactions(
+            conn,
+            filter.address.to_owned(),
+            from_block,
+            to_block,
+        )
+        .await;
+        already_ingested_transactions.append(&mut transactions);
+    }
+
+    already_ingested_transactions
+}
+
+async fn get_already_ingested_call_traces<'a>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    filters: &Vec<Filter>,
+) -> Vec<CallTrace> {
+    let mut already_ingested_call_traces = vec![];
+    for filter in filters {
+        let from_block = filter.value.get_from_block().unwrap().as_u64();
+        let to_block = filter.value.get_to_block().unwrap().as_u64();
+
+        let mut call_traces =
+            ChaindexingRepo::get_call_traces(conn, filter.address.to_owned(), from_block, to_block)
+                .await;
+        already_ingested_call_traces.append(&mut call_traces);
+    }
+
+    already_ingested_call_traces
+}
+
 async fn handle_chain_reorg<'a>(
     conn: &mut ChaindexingRepoConn<'a>,
     chain_id: &ChainId,
     (added_events, removed_events): (Vec<Event>, Vec<Event>),
+    (added_transactions, removed_transactions): (Vec<Transaction>, Vec<Transaction>),
+    (added_call_traces, removed_call_traces): (Vec<CallTrace>, Vec<CallTrace>),
+    reorged_block_numbers: &[i64],
 ) -> Result<(), IngesterError> {
-    let earliest_block_number = get_earliest_block_number((&added_events, &removed_events));
+    let earliest_block_number = [
+        get_earliest_block_number(&added_events, |e| e.block_number),
+        get_earliest_block_number(&removed_events, |e| e.block_number),
+        get_earliest_block_number(&added_transactions, |t| t.block_number),
+        get_earliest_block_number(&removed_transactions, |t| t.block_number),
+        get_earliest_block_number(&added_call_traces, |c| c.block_number),
+        get_earliest_block_number(&removed_call_traces, |c| c.block_number),
+        reorged_block_numbers.iter().min().copied(),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    .expect("Added or removed events, transactions, call traces or reorged blocks must have at least one entry");
     let new_reorged_block = UnsavedReorgedBlock::new(earliest_block_number, chain_id);
 
     ChaindexingRepo::run_in_transaction(conn, move |conn| {
@@ -92,6 +263,16 @@ async fn handle_chain_reorg<'a>(
 
             ChaindexingRepo::create_events(conn, &added_events).await;
 
+            let transaction_ids: Vec<_> = removed_transactions.iter().map(|t| t.id).collect();
+            ChaindexingRepo::delete_transactions_by_ids(conn, &transaction_ids).await;
+
+            ChaindexingRepo::create_transactions(conn, &added_transactions).await;
+
+            let call_trace_ids: Vec<_> = removed_call_traces.iter().map(|c| c.id).collect();
+            ChaindexingRepo::delete_call_traces_by_ids(conn, &call_trace_ids).await;
+
+            ChaindexingRepo::create_call_traces(conn, &added_call_traces).await;
+
             Ok(())
         }
         .boxed()
@@ -101,41 +282,32 @@ async fn handle_chain_reorg<'a>(
     Ok(())
 }
 
-fn get_provider_added_and_removed_events(
-    already_ingested_events: &[Event],
-    provider_events: &[Event],
-) -> Option<(Vec<Event>, Vec<Event>)> {
-    let already_ingested_events_set: HashSet<_> = already_ingested_events.iter().cloned().collect();
-    let provider_events_set: HashSet<_> = provider_events.iter().cloned().collect();
+fn get_provider_added_and_removed<T: Clone + Eq + Hash>(
+    already_ingested: &[T],
+    provider_fetched: &[T],
+) -> Option<(Vec<T>, Vec<T>)> {
+    let already_ingested_set: HashSet<_> = already_ingested.iter().cloned().collect();
+    let provider_fetched_set: HashSet<_> = provider_fetched.iter().cloned().collect();
 
-    let added_events: Vec<_> = provider_events
+    let added: Vec<_> = provider_fetched
         .iter()
-        .filter(|e| !already_ingested_events_set.contains(e))
+        .filter(|e| !already_ingested_set.contains(e))
         .cloned()
         .collect();
 
-    let removed_events: Vec<_> = already_ingested_events
+    let removed: Vec<_> = already_ingested
         .iter()
-        .filter(|e| !provider_events_set.contains(e))
+        .filter(|e| !provider_fetched_set.contains(e))
         .cloned()
         .collect();
 
-    if added_events.is_empty() && removed_events.is_empty() {
+    if added.is_empty() && removed.is_empty() {
         None
     } else {
-        Some((added_events, removed_events))
+        Some((added, removed))
     }
 }
 
-fn get_earliest_block_number((added_events, removed_events): (&Vec<Event>, &Vec<Event>)) -> i64 {
-    let earliest_added_event = added_events.iter().min_by_key(|e| e.block_number);
-    let earliest_removed_event = removed_events.iter().min_by_key(|e| e.block_number);
-
-    match (earliest_added_event, earliest_removed_event) {
-        (Some(event), None) | (None, Some(event)) => event.block_number,
-        (Some(earliest_added), Some(earliest_removed)) => {
-            min(earliest_added.block_number, earliest_removed.block_number)
-        }
-        _ => unreachable!("Added Events or Removed Events must have at least one entry"),
-    }
+fn get_earliest_block_number<T>(entries: &[T], get_block_number: fn(&T) -> i64) -> Option<i64> {
+    entries.iter().map(get_block_number).min()
 }
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::providers::{Middleware, Provider as EthersProvider, Ws};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use tokio::time::interval;
+
+use super::{provider, AdaptiveBlocksPerBatch, IngesterError, Provider};
+use crate::{Chain, Config};
+
+/// Ingests the chain whenever a new block arrives through the `newHeads`
+/// subscription. While the subscription is down, it falls back to
+/// ingesting every `ingestion_rate_ms` and retries subscribing on each tick.
+pub async fn run<S: Send + Sync + Clone + 'static>(
+    chain: &Chain,
+    ws_url: &str,
+    config: &Config<S>,
+) -> Result<(), IngesterError> {
+    let mut chain_ingester = ConfigChainIngester {
+        chain,
+        config,
+        provider: provider::get(chain),
+        last_pruned_at_per_chain_id: HashMap::new(),
+        blocks_per_batch: AdaptiveBlocksPerBatch::new(config.get_blocks_per_batch(&chain.id)),
+    };
+
+    run_on_new_heads(
+        &mut WsNewHeads::new(ws_url),
+        &mut chain_ingester,
+        config.get_ingestion_rate_ms(&chain.id),
+    )
+    .await
+}
+
+async fn run_on_new_heads(
+    new_heads: &mut impl NewHeads,
+    chain_ingester: &mut impl ChainIngester,
+    ingestion_rate_ms: u64,
+) -> Result<(), IngesterError> {
+    let mut interval = interval(Duration::from_millis(ingestion_rate_ms));
+
+    loop {
+        match new_heads.subscribe().await {
+            Ok(mut new_heads) => {
+                // Catch up with whatever was missed before the first head arrives
+                chain_ingester.ingest().await?;
+
+                while new_heads.next().await.is_some() {
+                    chain_ingester.ingest().await?;
+                }
+
+                eprintln!("Subscription Dropped: newHeads");
+            }
+            Err(provider_error) => eprintln!("Provider Error: {}", provider_error),
+        }
+
+        chain_ingester.ingest().await?;
+
+        interval.tick().await;
+    }
+}
+
+/// Notifies of every new block of the chain
+#[crate::augmenting_std::async_trait]
+trait NewHeads: Send {
+    async fn subscribe(&mut self) -> Result<BoxStream<'_, ()>, String>;
+}
+
+struct WsNewHeads {
+    ws_url: String,
+    /// Subscriptions live as long as their provider
+    provider: Option<EthersProvider<Ws>>,
+}
+
+impl WsNewHeads {
+    fn new(ws_url: &str) -> Self {
+        Self {
+            ws_url: ws_url.to_string(),
+            provider: None,
+        }
+    }
+}
+
+#[crate::augmenting_std::async_trait]
+impl NewHeads for WsNewHeads {
+    async fn subscribe(&mut self) -> Result<BoxStream<'_, ()>, String> {
+        let provider =
+            EthersProvider::<Ws>::connect(&self.ws_url).await.map_err(|e| e.to_string())?;
+        let provider = self.provider.insert(provider);
+        let new_heads = provider.subscribe_blocks().await.map_err(|e| e.to_string())?;
+
+        Ok(new_heads.map(|_block| ()).boxed())
+    }
+}
+
+#[crate::augmenting_std::async_trait]
+trait ChainIngester: Send {
+    async fn ingest(&mut self) -> Result<(), IngesterError>;
+}
+
+struct ConfigChainIngester<'a, S: Send + Sync + Clone, P: Provider> {
+    chain: &'a Chain,
+    config: &'a Config<S>,
+    provider: Arc<P>,
+    last_pruned_at_per_chain_id: HashMap<u64, u64>,
+    blocks_per_batch: AdaptiveBlocksPerBatch,
+}
+
+#[crate::augmenting_std::async_trait]
+impl<'a, S: Send + Sync + Clone, P: Provider> ChainIngester for ConfigChainIngester<'a, S, P> {
+    async fn ingest(&mut self) -> Result<(), IngesterError> {
+        super::ingest_chain(
+            self.chain,
+            &self.provider,
+            self.config,
+            &mut self.last_pruned_at_per_chain_id,
+            &mut self.blocks_per_batch,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod new_heads_tests {
+    use futures_util::stream;
+
+    use super::*;
+
+    struct StubNewHeads {
+        heads_count: usize,
+        subscriptions_count: usize,
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl NewHeads for StubNewHeads {
+        async fn subscribe(&mut self) -> Result<BoxStream<'_, ()>, String> {
+            self.subscriptions_count += 1;
+
+            Ok(stream::iter(vec![(); self.heads_count]).boxed())
+        }
+    }
+
+    /// Fails on the given ingestion, to stop the otherwise endless run
+    struct CountingChainIngester {
+        ingestions_count: usize,
+        failing_ingestion: usize,
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl ChainIngester for CountingChainIngester {
+        async fn ingest(&mut self) -> Result<(), IngesterError> {
+            self.ingestions_count += 1;
+
+            if self.ingestions_count == self.failing_ingestion {
+                Err(IngesterError::GenericError("Stop".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn ingests_on_every_new_head_then_falls_back_to_polling() {
+        let mut new_heads = StubNewHeads {
+            heads_count: 2,
+            subscriptions_count: 0,
+        };
+        let mut chain_ingester = CountingChainIngester {
+            ingestions_count: 0,
+            failing_ingestion: 4,
+        };
+
+        let result = run_on_new_heads(&mut new_heads, &mut chain_ingester, 1).await;
+
+        assert!(result.is_err());
+        assert_eq!(new_heads.subscriptions_count, 1);
+        // One catch up, one per head, then one poll once the subscription drops
+        assert_eq!(chain_ingester.ingestions_count, 4);
+    }
+}
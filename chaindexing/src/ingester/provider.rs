@@ -1,15 +1,36 @@
-use std::collections::HashMap;
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
 use ethers::prelude::Middleware;
 use ethers::prelude::*;
-use ethers::providers::{Http, Provider as EthersProvider, ProviderError as EthersProviderError};
+use ethers::providers::{Provider as EthersProvider, ProviderError as EthersProviderError};
 use ethers::types::{Filter as EthersFilter, Log};
-use futures_util::future::try_join_all;
+use futures_util::future::{try_join_all, BoxFuture};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
-use super::filters::Filter;
+use super::blocks_per_batch::AdaptiveBlocksPerBatch;
+use super::filters::{self, Filter, MergedFilter};
+use crate::call_traces::TracedCall;
+use crate::chain_reorg::{Finality, FinalizedBlockNumber};
+use crate::{Chain, TraceMode};
+
+mod archive;
+mod failover;
+mod json_rpc_batch;
+mod rate_limiter;
+mod recording;
+mod retry_policy;
+
+pub use archive::ArchiveProvider;
+pub use failover::FailoverProvider;
+pub use rate_limiter::RateLimiter;
+pub use recording::{RecordingProvider, ReplayProvider};
+pub use retry_policy::{ProviderErrorKind, RetryPolicy};
 
 pub type ProviderError = EthersProviderError;
 
@@ -48,10 +69,45 @@ pub trait Provider: Clone + Sync + Send {
 
         Ok(blocks_by_number)
     }
+
+    /// Only needed for contracts with transaction handlers
+    async fn get_block_with_transactions(
+        &self,
+        _block_number: U64,
+    ) -> Result<Block<Transaction>, ProviderError> {
+        Err(ProviderError::UnsupportedRPC)
+    }
+    /// Only needed for contracts with transaction handlers
+    async fn get_transaction_receipt(
+        &self,
+        _transaction_hash: TxHash,
+    ) -> Result<TransactionReceipt, ProviderError> {
+        Err(ProviderError::UnsupportedRPC)
+    }
+
+    /// Only needed for chains with `Finality::Finalized` or `Finality::Safe`
+    async fn get_tagged_block_number(&self, _block_tag: BlockNumber) -> Result<U64, ProviderError> {
+        Err(ProviderError::UnsupportedRPC)
+    }
+
+    /// Only needed for chains traced with `TraceMode::TraceFilter`
+    async fn trace_filter(&self, _filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        Err(ProviderError::UnsupportedRPC)
+    }
+    /// Only needed for chains traced with `TraceMode::DebugTraceBlock`.
+    /// Returns the `callTracer`'s call frame of each transaction, in the block's order.
+    async fn debug_trace_block(&self, _block_number: U64) -> Result<Vec<CallFrame>, ProviderError> {
+        Err(ProviderError::UnsupportedRPC)
+    }
+}
+
+/// Lagging endpoints can return null for blocks and receipts that exist
+fn get_missing_error(missing: &str) -> ProviderError {
+    ProviderError::CustomError(format!("Missing {missing} in JSON-RPC response"))
 }
 
 #[crate::augmenting_std::async_trait]
-impl Provider for EthersProvider<Http> {
+impl<C: JsonRpcClient + Clone + 'static> Provider for EthersProvider<C> {
     async fn get_block_number(&self) -> Result<U64, ProviderError> {
         Middleware::get_block_number(&self).await
     }
@@ -61,80 +117,633 @@ impl Provider for EthersProvider<Http> {
     }
 
     async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
-        Ok(Middleware::get_block(&self, block_number).await?.unwrap())
+        Middleware::get_block(&self, block_number)
+            .await?
+            .ok_or_else(|| get_missing_error("block"))
+    }
+
+    async fn get_block_with_transactions(
+        &self,
+        block_number: U64,
+    ) -> Result<Block<Transaction>, ProviderError> {
+        Middleware::get_block_with_txs(&self, block_number)
+            .await?
+            .ok_or_else(|| get_missing_error("block"))
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        transaction_hash: TxHash,
+    ) -> Result<TransactionReceipt, ProviderError> {
+        Middleware::get_transaction_receipt(&self, transaction_hash)
+            .await?
+            .ok_or_else(|| get_missing_error("transaction receipt"))
+    }
+
+    async fn get_tagged_block_number(&self, block_tag: BlockNumber) -> Result<U64, ProviderError> {
+        Middleware::get_block(&self, block_tag)
+            .await?
+            .and_then(|b| b.number)
+            .ok_or_else(|| get_missing_error("block number"))
+    }
+
+    async fn trace_filter(&self, filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        Middleware::trace_filter(&self, filter.clone()).await
+    }
+
+    async fn debug_trace_block(&self, block_number: U64) -> Result<Vec<CallFrame>, ProviderError> {
+        debug_trace_block_with_call_tracer(self, block_number).await
     }
 }
 
-pub fn get(json_rpc_url: &str) -> Arc<impl Provider> {
-    Arc::new(EthersProvider::<Http>::try_from(json_rpc_url).unwrap())
+#[derive(Debug, Deserialize, Serialize)]
+struct TransactionCallFrame {
+    result: CallFrame,
 }
 
-pub async fn fetch_current_block_number(provider: &Arc<impl Provider>) -> u64 {
-    let mut maybe_current_block_number = None;
-    let mut retries_so_far = 0;
+/// ethers only deserializes `debug_traceBlockByNumber`'s results without
+/// their `result` wrapper, which geth always includes
+async fn debug_trace_block_with_call_tracer<C: JsonRpcClient>(
+    provider: &EthersProvider<C>,
+    block_number: U64,
+) -> Result<Vec<CallFrame>, ProviderError> {
+    let call_frames: Vec<TransactionCallFrame> = provider
+        .request(
+            "debug_traceBlockByNumber",
+            (block_number, serde_json::json!({ "tracer": "callTracer" })),
+        )
+        .await?;
 
-    while maybe_current_block_number.is_none() {
-        match provider.get_block_number().await {
-            Ok(current_block_number) => {
-                maybe_current_block_number = Some(current_block_number.as_u64())
-            }
-            Err(provider_error) => {
-                eprintln!("Provider Error: {}", provider_error);
+    Ok(call_frames.into_iter().map(|call_frame| call_frame.result).collect())
+}
 
-                backoff(retries_so_far).await;
-                retries_so_far += 1;
+pub fn get(
+    Chain {
+        id,
+        json_rpc_urls,
+        json_rpc_timeout_ms,
+        json_rpc_cool_down_ms,
+        json_rpc_requests_per_second,
+        json_rpc_method_costs,
+        json_rpc_batch_size,
+        archive_dir,
+        recording_path,
+        ..
+    }: &Chain,
+) -> Arc<impl Provider> {
+    let mut provider = FailoverProvider::new(
+        json_rpc_urls,
+        Duration::from_millis(*json_rpc_timeout_ms),
+        Duration::from_millis(*json_rpc_cool_down_ms),
+    )
+    .with_batch_size(*json_rpc_batch_size);
+
+    if let Some(archive_dir) = archive_dir {
+        provider = provider.with_archive(ArchiveProvider::new(archive_dir));
+    }
+
+    if let Some(requests_per_second) = json_rpc_requests_per_second {
+        provider = provider.with_rate_limiter(RateLimiter::new(
+            &format!("{:?}", id),
+            *requests_per_second,
+            json_rpc_method_costs,
+        ));
+    }
+
+    Arc::new(RecordingProvider::maybe_new(
+        provider,
+        recording_path.as_ref(),
+    ))
+}
+
+pub async fn fetch_current_block_number(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+) -> Result<u64, ProviderError> {
+    let current_block_number = with_retries(retry_policy, || provider.get_block_number()).await?;
+
+    Ok(current_block_number.as_u64())
+}
+
+/// Returns the last block that can no longer be re-organized
+pub async fn fetch_finalized_block_number(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    finality: &Finality,
+    current_block_number: u64,
+) -> Result<FinalizedBlockNumber, ProviderError> {
+    if let Finality::MinConfirmationCount(min_confirmation_count) = finality {
+        return Ok(FinalizedBlockNumber::from_min_confirmation_count(
+            *min_confirmation_count,
+            current_block_number,
+        ));
+    }
+
+    let block_tag = finality.get_block_tag().unwrap();
+
+    let block_number =
+        with_retries(retry_policy, || provider.get_tagged_block_number(block_tag)).await?;
+
+    Ok(FinalizedBlockNumber::new(block_number.as_u64()))
+}
+
+/// Fetches logs for filters merged across addresses. When a provider rejects
+/// a filter's block range, the range gets split in half until the provider
+/// accepts it and the accepted range is remembered for the filter's contract addresses.
+pub async fn fetch_logs(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filters: &[Filter],
+    max_addresses_per_filter: usize,
+    blocks_per_batch: &mut AdaptiveBlocksPerBatch,
+) -> Result<Vec<Log>, ProviderError> {
+    let merged_filters = filters::merge(filters, max_addresses_per_filter);
+
+    let logs_per_filter = try_join_all(
+        merged_filters
+            .iter()
+            .map(|f| fetch_logs_by_splitting(provider, retry_policy, f)),
+    )
+    .await?;
+
+    let mut logs = vec![];
+    for (filter, (filter_logs, accepted_blocks_count)) in merged_filters.iter().zip(logs_per_filter)
+    {
+        for contract_address_id in filter.contract_address_ids.iter() {
+            if accepted_blocks_count < filter.get_blocks_count() {
+                blocks_per_batch.narrow(*contract_address_id, accepted_blocks_count);
+            } else {
+                blocks_per_batch.grow(*contract_address_id);
             }
         }
+
+        logs.extend(filter_logs);
     }
 
-    maybe_current_block_number.unwrap()
-}
+    // Logs matching more than one topic filter of an event get fetched more than once
+    let mut fetched_logs = HashSet::new();
+    logs.retain(|log| fetched_logs.insert((log.block_hash, log.transaction_hash, log.log_index)));
 
-pub async fn fetch_logs(provider: &Arc<impl Provider>, filters: &[Filter]) -> Vec<Log> {
-    let mut maybe_logs = None;
-    let mut retries_so_far = 0;
+    Ok(logs)
+}
 
-    while maybe_logs.is_none() {
-        match try_join_all(filters.iter().map(|f| provider.get_logs(&f.value))).await {
-            Ok(logs_per_filter) => {
-                let logs = logs_per_filter.into_iter().flatten().collect();
+fn fetch_logs_by_splitting<'a>(
+    provider: &'a Arc<impl Provider>,
+    retry_policy: &'a RetryPolicy,
+    filter: &'a MergedFilter,
+) -> BoxFuture<'a, Result<(Vec<Log>, u64), ProviderError>> {
+    async move {
+        let halves = filter.split();
 
-                maybe_logs = Some(logs)
+        // Rejected block ranges get split instead of retried
+        let logs = with_retries(retry_policy, || async {
+            match provider.get_logs(&filter.value).await {
+                Err(provider_error)
+                    if is_block_range_too_large(&provider_error) && halves.is_some() =>
+                {
+                    Ok(None)
+                }
+                result => result.map(Some),
             }
-            Err(provider_error) => {
-                eprintln!("Provider Error: {}", provider_error);
+        })
+        .await?;
 
-                backoff(retries_so_far).await;
-                retries_so_far += 1;
+        match logs {
+            Some(logs) => Ok((logs, filter.get_blocks_count())),
+            None => {
+                // Only block ranges with halves get rejected without retries
+                let (first_half, second_half) = halves.unwrap();
+                let (mut logs, first_half_blocks_count) =
+                    fetch_logs_by_splitting(provider, retry_policy, &first_half).await?;
+                let (second_half_logs, second_half_blocks_count) =
+                    fetch_logs_by_splitting(provider, retry_policy, &second_half).await?;
+
+                logs.extend(second_half_logs);
+
+                Ok((logs, min(first_half_blocks_count, second_half_blocks_count)))
             }
         }
     }
+    .boxed()
+}
 
-    maybe_logs.unwrap()
+/// Providers reject `eth_getLogs` requests spanning too many blocks or
+/// returning too many logs, each with their own wording.
+pub fn is_block_range_too_large(provider_error: &ProviderError) -> bool {
+    const BLOCK_RANGE_TOO_LARGE_MESSAGES: [&str; 7] = [
+        "query returned more than",
+        "block range too large",
+        "block range is too large",
+        "range is too large",
+        "exceed maximum block range",
+        "log response size exceeded",
+        "response size should not greater than",
+    ];
+
+    let provider_error = provider_error.to_string().to_lowercase();
+
+    BLOCK_RANGE_TOO_LARGE_MESSAGES
+        .iter()
+        .any(|message| provider_error.contains(message))
 }
 
 pub async fn fetch_blocks_by_number(
     provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
     logs: &Vec<Log>,
-) -> HashMap<U64, Block<TxHash>> {
-    let mut maybe_blocks_by_number = None;
+) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+    with_retries(retry_policy, || provider.get_blocks_by_number(logs)).await
+}
+
+pub async fn fetch_blocks(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    block_numbers: &[u64],
+) -> Result<Vec<Block<TxHash>>, ProviderError> {
+    const CHUNK_SIZE: usize = 4;
+    let mut blocks = vec![];
+
+    for block_numbers in block_numbers.chunks(CHUNK_SIZE) {
+        blocks.extend(
+            try_join_all(
+                block_numbers.iter().map(|n| fetch_block(provider, retry_policy, U64::from(*n))),
+            )
+            .await?,
+        );
+    }
+
+    Ok(blocks)
+}
+
+pub async fn fetch_block(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    block_number: U64,
+) -> Result<Block<TxHash>, ProviderError> {
+    with_retries(retry_policy, || provider.get_block(block_number)).await
+}
+
+/// Fetches the blocks within the filters' block ranges, keeping only the
+/// transactions sent to the filters' addresses. Contract addresses have no
+/// index for their transactions, so every block in range has to be fetched.
+pub async fn fetch_transactions(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filters: &[Filter],
+) -> Result<(Vec<Transaction>, HashMap<U64, Block<TxHash>>), ProviderError> {
+    let addresses_by_block_number = group_addresses_by_block_number(filters);
+
+    let mut block_numbers: Vec<_> = addresses_by_block_number.keys().copied().collect();
+    block_numbers.sort();
+
+    const CHUNK_SIZE: usize = 4;
+    let mut transactions = vec![];
+    let mut blocks_by_number = HashMap::new();
+
+    for block_numbers in block_numbers.chunks(CHUNK_SIZE) {
+        let blocks = try_join_all(
+            block_numbers
+                .iter()
+                .map(|n| fetch_block_with_transactions(provider, retry_policy, U64::from(*n))),
+        )
+        .await?;
+
+        for block in blocks {
+            let addresses = &addresses_by_block_number[&block.number.unwrap().as_u64()];
+
+            transactions.extend(
+                block
+                    .transactions
+                    .iter()
+                    .filter(|t| t.to.map(|to| addresses.contains(&to)).unwrap_or(false))
+                    .cloned(),
+            );
+            blocks_by_number.insert(
+                block.number.unwrap(),
+                Block {
+                    number: block.number,
+                    hash: block.hash,
+                    parent_hash: block.parent_hash,
+                    timestamp: block.timestamp,
+                    transactions: block.transactions.iter().map(|t| t.hash).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    Ok((transactions, blocks_by_number))
+}
+
+async fn fetch_block_with_transactions(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    block_number: U64,
+) -> Result<Block<Transaction>, ProviderError> {
+    with_retries(retry_policy, || {
+        provider.get_block_with_transactions(block_number)
+    })
+    .await
+}
+
+pub async fn fetch_transaction_receipts(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    transactions: &[Transaction],
+) -> Result<HashMap<TxHash, TransactionReceipt>, ProviderError> {
+    const CHUNK_SIZE: usize = 4;
+    let mut receipts_by_hash = HashMap::new();
+
+    for transactions in transactions.chunks(CHUNK_SIZE) {
+        let receipts = try_join_all(
+            transactions
+                .iter()
+                .map(|t| fetch_transaction_receipt(provider, retry_policy, t.hash)),
+        )
+        .await?;
+
+        for receipt in receipts {
+            receipts_by_hash.insert(receipt.transaction_hash, receipt);
+        }
+    }
+
+    Ok(receipts_by_hash)
+}
+
+async fn fetch_transaction_receipt(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    transaction_hash: TxHash,
+) -> Result<TransactionReceipt, ProviderError> {
+    with_retries(retry_policy, || {
+        provider.get_transaction_receipt(transaction_hash)
+    })
+    .await
+}
+
+/// Fetches the calls made to the filters' addresses within their block ranges.
+/// Unlike `trace_filter`, `debug_traceBlockByNumber` traces every call in a
+/// block, so every block in range has to be traced.
+pub async fn fetch_call_traces(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filters: &[Filter],
+    trace_mode: &TraceMode,
+    max_addresses_per_filter: usize,
+) -> Result<Vec<TracedCall>, ProviderError> {
+    match trace_mode {
+        TraceMode::TraceFilter => {
+            let merged_filters = filters::merge(filters, max_addresses_per_filter);
+
+            let traces_per_filter = try_join_all(
+                merged_filters.iter().map(|f| fetch_traces(provider, retry_policy, f)),
+            )
+            .await?;
+
+            Ok(traces_per_filter.iter().flatten().filter_map(TracedCall::from_trace).collect())
+        }
+        TraceMode::DebugTraceBlock => {
+            let addresses_by_block_number = group_addresses_by_block_number(filters);
+
+            let mut block_numbers: Vec<_> = addresses_by_block_number.keys().copied().collect();
+            block_numbers.sort();
+
+            const CHUNK_SIZE: usize = 4;
+            let mut traced_calls = vec![];
+
+            for block_numbers in block_numbers.chunks(CHUNK_SIZE) {
+                let blocks_with_call_frames =
+                    try_join_all(block_numbers.iter().map(|n| {
+                        fetch_block_with_call_frames(provider, retry_policy, U64::from(*n))
+                    }))
+                    .await?;
+
+                for (block, call_frames) in blocks_with_call_frames {
+                    let addresses = &addresses_by_block_number[&block.number.unwrap().as_u64()];
+
+                    traced_calls.extend(
+                        TracedCall::from_call_frames(&block, &call_frames)
+                            .into_iter()
+                            .filter(|traced_call| addresses.contains(&traced_call.to)),
+                    );
+                }
+            }
+
+            Ok(traced_calls)
+        }
+    }
+}
+
+async fn fetch_traces(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filter: &MergedFilter,
+) -> Result<Vec<Trace>, ProviderError> {
+    let addresses = match filter.value.address.as_ref().unwrap() {
+        ValueOrArray::Value(address) => vec![*address],
+        ValueOrArray::Array(addresses) => addresses.clone(),
+    };
+    let trace_filter = TraceFilter::default()
+        .from_block(filter.value.get_from_block().unwrap())
+        .to_block(filter.value.get_to_block().unwrap())
+        .to_address(addresses);
+
+    with_retries(retry_policy, || provider.trace_filter(&trace_filter)).await
+}
+
+async fn fetch_block_with_call_frames(
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    block_number: U64,
+) -> Result<(Block<TxHash>, Vec<CallFrame>), ProviderError> {
+    with_retries(retry_policy, || async {
+        futures_util::try_join!(
+            provider.get_block(block_number),
+            provider.debug_trace_block(block_number)
+        )
+    })
+    .await
+}
+
+fn group_addresses_by_block_number(filters: &[Filter]) -> HashMap<u64, HashSet<Address>> {
+    let mut addresses_by_block_number: HashMap<u64, HashSet<Address>> = HashMap::new();
+
+    for filter in filters {
+        let from_block_number = filter.value.get_from_block().unwrap().as_u64();
+        let to_block_number = filter.value.get_to_block().unwrap().as_u64();
+
+        for block_number in from_block_number..=to_block_number {
+            addresses_by_block_number
+                .entry(block_number)
+                .or_default()
+                .insert(filter.address.parse().unwrap());
+        }
+    }
+
+    addresses_by_block_number
+}
+
+/// Sends the request until it succeeds, backing off between retries,
+/// or gives the error back when the request should not be retried anymore
+async fn with_retries<T, F, Fut>(
+    retry_policy: &RetryPolicy,
+    mut request: F,
+) -> Result<T, ProviderError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ProviderError>>,
+{
     let mut retries_so_far = 0;
 
-    while maybe_blocks_by_number.is_none() {
-        match provider.get_blocks_by_number(logs).await {
-            Ok(blocks_by_tx_hash) => maybe_blocks_by_number = Some(blocks_by_tx_hash),
+    loop {
+        match request().await {
+            Ok(response) => return Ok(response),
             Err(provider_error) => {
                 eprintln!("Provider Error: {}", provider_error);
 
-                backoff(retries_so_far).await;
+                backoff(retry_policy, retries_so_far, provider_error).await?;
                 retries_so_far += 1;
             }
         }
     }
+}
 
-    maybe_blocks_by_number.unwrap()
+/// Backs off before retrying a failed request, or gives the error back
+/// when the request should not be retried anymore
+async fn backoff(
+    retry_policy: &RetryPolicy,
+    retries_so_far: u32,
+    provider_error: ProviderError,
+) -> Result<(), ProviderError> {
+    if retry_policy.should_retry(retries_so_far, &provider_error) {
+        sleep(retry_policy.get_backoff(retries_so_far)).await;
+
+        Ok(())
+    } else {
+        Err(provider_error)
+    }
 }
 
-async fn backoff(retries_so_far: u32) {
-    sleep(Duration::from_secs(2u64.pow(retries_so_far))).await;
+#[cfg(test)]
+mod provider_tests {
+    use super::*;
+
+    #[test]
+    fn recognises_block_range_too_large_errors() {
+        for message in [
+            "query returned more than 10000 results",
+            "Block range too large",
+            "exceed maximum block range: 5000",
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range",
+        ] {
+            assert!(is_block_range_too_large(&ProviderError::CustomError(
+                message.to_string()
+            )));
+        }
+    }
+
+    #[test]
+    fn does_not_recognise_other_errors() {
+        assert!(!is_block_range_too_large(&ProviderError::CustomError(
+            "Request to http://localhost:8545 timed out".to_string()
+        )));
+    }
+
+    /// Counts requests failing with the error until the succeeding one
+    async fn count_requests(
+        retry_policy: &RetryPolicy,
+        provider_error: &str,
+        succeeding_request: u32,
+    ) -> (Result<u32, ProviderError>, u32) {
+        let mut requests_count = 0;
+
+        let response = with_retries(retry_policy, || {
+            requests_count += 1;
+            let request = requests_count;
+
+            async move {
+                if request < succeeding_request {
+                    Err(ProviderError::CustomError(provider_error.to_string()))
+                } else {
+                    Ok(request)
+                }
+            }
+        })
+        .await;
+
+        (response, requests_count)
+    }
+
+    fn retry_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(max_retries).with_initial_backoff_ms(1).without_jitter()
+    }
+
+    #[tokio::test]
+    async fn retries_requests_until_they_succeed() {
+        let (response, requests_count) = count_requests(&retry_policy(3), "timed out", 3).await;
+
+        assert_eq!(response.unwrap(), 3);
+        assert_eq!(requests_count, 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (response, requests_count) = count_requests(&retry_policy(2), "timed out", 10).await;
+
+        assert_eq!(response.unwrap_err().to_string(), "custom error: timed out");
+        assert_eq!(requests_count, 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_fatal_errors_right_away() {
+        let (response, requests_count) =
+            count_requests(&retry_policy(2), "Invalid API key", 10).await;
+
+        assert!(response.is_err());
+        assert_eq!(requests_count, 1);
+    }
+
+    #[tokio::test]
+    async fn returns_errors_for_unsupported_block_tags() {
+        let (provider, mock) = EthersProvider::mocked();
+        mock.push(serde_json::Value::Null).unwrap();
+
+        let finalized_block_number = fetch_finalized_block_number(
+            &Arc::new(provider),
+            &retry_policy(0),
+            &Finality::Finalized,
+            100,
+        )
+        .await;
+
+        assert_eq!(
+            finalized_block_number.unwrap_err().to_string(),
+            "custom error: Missing block number in JSON-RPC response"
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_errors_for_null_blocks_and_receipts() {
+        let (provider, mock) = EthersProvider::mocked();
+        mock.push(serde_json::Value::Null).unwrap();
+        mock.push(serde_json::Value::Null).unwrap();
+        mock.push(serde_json::Value::Null).unwrap();
+
+        let header = Provider::get_block(&provider, U64::from(1)).await;
+        let block = Provider::get_block_with_transactions(&provider, U64::from(1)).await;
+        let receipt = Provider::get_transaction_receipt(&provider, TxHash::zero()).await;
+
+        assert_eq!(
+            header.unwrap_err().to_string(),
+            "custom error: Missing block in JSON-RPC response"
+        );
+        assert_eq!(
+            block.unwrap_err().to_string(),
+            "custom error: Missing block in JSON-RPC response"
+        );
+        assert_eq!(
+            receipt.unwrap_err().to_string(),
+            "custom error: Missing transaction receipt in JSON-RPC response"
+        );
+    }
 }
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ethers::types::{Block, Filter as EthersFilter, Log, Topic, TxHash, ValueOrArray, H256, U64};
+use serde::de::DeserializeOwned;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use super::{Provider, ProviderError};
+
+/// Serves logs and blocks exported to JSONL archive files, to backfill
+/// without any RPC cost. The archive directory is laid out as:
+///
+/// ```text
+/// <archive_dir>/logs/<from_block_number>-<to_block_number>.jsonl
+/// <archive_dir>/blocks/<from_block_number>-<to_block_number>.jsonl
+/// ```
+///
+/// Each line of a logs file is a log in `eth_getLogs`'s format, and each line of
+/// a blocks file is a block in `eth_getBlockByNumber`'s format, without transactions.
+/// Block ranges are inclusive and only the files overlapping a request get read.
+#[derive(Clone, Debug)]
+pub struct ArchiveProvider {
+    log_files: Arc<Vec<ArchiveFile>>,
+    block_files: Arc<Vec<ArchiveFile>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ArchiveFile {
+    path: PathBuf,
+    from_block_number: u64,
+    to_block_number: u64,
+}
+
+impl ArchiveFile {
+    fn maybe_new(path: PathBuf) -> Option<Self> {
+        if path.extension()? != "jsonl" {
+            return None;
+        }
+
+        let (from_block_number, to_block_number) = path.file_stem()?.to_str()?.split_once('-')?;
+
+        Some(Self {
+            from_block_number: from_block_number.parse().ok()?,
+            to_block_number: to_block_number.parse().ok()?,
+            path,
+        })
+    }
+
+    fn overlaps(&self, from_block_number: u64, to_block_number: u64) -> bool {
+        self.from_block_number <= to_block_number && from_block_number <= self.to_block_number
+    }
+
+    async fn read<T: DeserializeOwned>(&self) -> Result<Vec<T>, ProviderError> {
+        let file =
+            File::open(&self.path).await.map_err(|error| archive_error(&self.path, error))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let mut entries = vec![];
+        while let Some(line) = lines.next_line().await.map_err(|e| archive_error(&self.path, e))? {
+            if !line.trim().is_empty() {
+                entries
+                    .push(serde_json::from_str(&line).map_err(|e| archive_error(&self.path, e))?);
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+impl ArchiveProvider {
+    /// Indexes the archive files by their block ranges.
+    /// Panics if the archive's logs or blocks directory cannot be read.
+    pub fn new(archive_dir: impl AsRef<Path>) -> Self {
+        let archive_dir = archive_dir.as_ref();
+
+        Self {
+            log_files: Arc::new(index_files(&archive_dir.join("logs"))),
+            block_files: Arc::new(index_files(&archive_dir.join("blocks"))),
+        }
+    }
+
+    /// Returns the last block number of the archived logs
+    pub fn get_last_block_number(&self) -> Option<u64> {
+        self.log_files.iter().map(|f| f.to_block_number).max()
+    }
+
+    /// Returns whether the archive holds the logs of every block up to the block number
+    pub fn has_logs_until(&self, block_number: u64) -> bool {
+        self.get_last_block_number().map(|n| block_number <= n).unwrap_or(false)
+    }
+
+    /// Returns whether the archive holds the block
+    pub fn has_block(&self, block_number: u64) -> bool {
+        self.block_files.iter().any(|f| f.overlaps(block_number, block_number))
+    }
+
+    async fn read_blocks(
+        &self,
+        block_numbers: &[U64],
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        let mut blocks_by_number = HashMap::new();
+
+        for block_file in self.block_files.iter() {
+            let has_any_block =
+                block_numbers.iter().any(|n| block_file.overlaps(n.as_u64(), n.as_u64()));
+
+            if has_any_block {
+                for block in block_file.read::<Block<TxHash>>().await? {
+                    if block.number.map(|n| block_numbers.contains(&n)).unwrap_or(false) {
+                        blocks_by_number.insert(block.number.unwrap(), block);
+                    }
+                }
+            }
+        }
+
+        match block_numbers.iter().find(|n| !blocks_by_number.contains_key(n)) {
+            Some(missing_block_number) => Err(ProviderError::CustomError(format!(
+                "Archive Error: block {missing_block_number} is not archived"
+            ))),
+            None => Ok(blocks_by_number),
+        }
+    }
+}
+
+#[crate::augmenting_std::async_trait]
+impl Provider for ArchiveProvider {
+    async fn get_block_number(&self) -> Result<U64, ProviderError> {
+        self.get_last_block_number().map(U64::from).ok_or_else(|| {
+            ProviderError::CustomError("Archive Error: no logs archived".to_string())
+        })
+    }
+
+    async fn get_logs(&self, filter: &EthersFilter) -> Result<Vec<Log>, ProviderError> {
+        let from_block_number = filter.get_from_block().unwrap_or_default().as_u64();
+        let to_block_number = filter.get_to_block().map(|n| n.as_u64()).unwrap_or(u64::MAX);
+
+        let mut logs = vec![];
+
+        for log_file in
+            self.log_files.iter().filter(|f| f.overlaps(from_block_number, to_block_number))
+        {
+            logs.extend(log_file.read::<Log>().await?.into_iter().filter(|log| {
+                let block_number = log.block_number.unwrap().as_u64();
+
+                from_block_number <= block_number
+                    && block_number <= to_block_number
+                    && matches_address(filter, log)
+                    && matches_topics(filter, log)
+            }));
+        }
+
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        Ok(logs)
+    }
+
+    async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+        let mut blocks_by_number = self.read_blocks(&[block_number]).await?;
+
+        Ok(blocks_by_number.remove(&block_number).unwrap())
+    }
+
+    async fn get_blocks_by_number(
+        &self,
+        logs: &Vec<Log>,
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        let mut block_numbers: Vec<_> = logs.iter().map(|log| log.block_number.unwrap()).collect();
+        block_numbers.sort();
+        block_numbers.dedup();
+
+        self.read_blocks(&block_numbers).await
+    }
+}
+
+fn index_files(dir: &Path) -> Vec<ArchiveFile> {
+    if !dir.exists() {
+        return vec![];
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap_or_else(|error| panic!("Archive Error: {}: {error}", dir.display()))
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| ArchiveFile::maybe_new(entry.path()))
+        .collect();
+
+    files.sort_by_key(|f| f.from_block_number);
+
+    files
+}
+
+fn matches_address(filter: &EthersFilter, log: &Log) -> bool {
+    match &filter.address {
+        Some(ValueOrArray::Value(address)) => log.address == *address,
+        Some(ValueOrArray::Array(addresses)) => {
+            addresses.is_empty() || addresses.contains(&log.address)
+        }
+        None => true,
+    }
+}
+
+/// Matches topics position by position, as `eth_getLogs` does
+fn matches_topics(filter: &EthersFilter, log: &Log) -> bool {
+    filter.topics.iter().enumerate().all(|(index, topic)| match topic {
+        Some(topic) => matches_topic(topic, log.topics.get(index)),
+        None => true,
+    })
+}
+
+fn matches_topic(topic: &Topic, log_topic: Option<&H256>) -> bool {
+    let topic_values: Vec<_> = match topic {
+        ValueOrArray::Value(value) => vec![*value],
+        ValueOrArray::Array(values) => values.clone(),
+    };
+
+    // Wildcards match any topic
+    if topic_values.is_empty() || topic_values.iter().any(|value| value.is_none()) {
+        return true;
+    }
+
+    log_topic
+        .map(|log_topic| topic_values.contains(&Some(*log_topic)))
+        .unwrap_or(false)
+}
+
+fn archive_error(path: &Path, error: impl std::fmt::Display) -> ProviderError {
+    ProviderError::CustomError(format!("Archive Error: {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+
+    use ethers::types::Address;
+
+    fn log(address: Address, topics: Vec<H256>, block_number: u64) -> Log {
+        Log {
+            address,
+            topics,
+            block_number: Some(U64::from(block_number)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn indexes_archive_files_by_block_range() {
+        let archive_file =
+            ArchiveFile::maybe_new(PathBuf::from("archive/logs/17773490-17773589.jsonl")).unwrap();
+
+        assert_eq!(archive_file.from_block_number, 17773490);
+        assert_eq!(archive_file.to_block_number, 17773589);
+        assert!(archive_file.overlaps(17773589, 17773600));
+        assert!(!archive_file.overlaps(17773590, 17773600));
+    }
+
+    #[test]
+    fn ignores_files_not_named_by_block_range() {
+        assert!(ArchiveFile::maybe_new(PathBuf::from("archive/logs/README.md")).is_none());
+        assert!(ArchiveFile::maybe_new(PathBuf::from("archive/logs/latest.jsonl")).is_none());
+    }
+
+    #[test]
+    fn matches_topics_by_position() {
+        let transfer = H256::random();
+        let vault = H256::random();
+        let log = log(Address::random(), vec![transfer, H256::random(), vault], 1);
+
+        let filter = EthersFilter::new().topic0(transfer).topic2(vault);
+        assert!(matches_topics(&filter, &log));
+
+        let filter = EthersFilter::new().topic0(transfer).topic1(vault);
+        assert!(!matches_topics(&filter, &log));
+
+        let filter = EthersFilter::new().topic0(vec![H256::random(), transfer]);
+        assert!(matches_topics(&filter, &log));
+    }
+
+    #[test]
+    fn matches_any_of_the_filters_addresses() {
+        let address = Address::random();
+        let log = log(address, vec![], 1);
+
+        assert!(matches_address(
+            &EthersFilter::new().address(vec![Address::random(), address]),
+            &log
+        ));
+        assert!(!matches_address(
+            &EthersFilter::new().address(Address::random()),
+            &log
+        ));
+    }
+
+    #[tokio::test]
+    async fn serves_logs_and_blocks_from_archive_files() {
+        let archive_dir = std::env::temp_dir().join(format!("archive-{}", H256::random()));
+        std::fs::create_dir_all(archive_dir.join("logs")).unwrap();
+        std::fs::create_dir_all(archive_dir.join("blocks")).unwrap();
+
+        let address = Address::random();
+        let logs = [log(address, vec![], 10), log(Address::random(), vec![], 11)];
+        let logs: Vec<_> = logs.iter().map(|l| serde_json::to_string(l).unwrap()).collect();
+        std::fs::write(archive_dir.join("logs/10-19.jsonl"), logs.join("\n")).unwrap();
+
+        let block = Block::<TxHash> {
+            number: Some(U64::from(10)),
+            hash: Some(H256::random()),
+            ..Default::default()
+        };
+        std::fs::write(
+            archive_dir.join("blocks/10-19.jsonl"),
+            serde_json::to_string(&block).unwrap(),
+        )
+        .unwrap();
+
+        let provider = ArchiveProvider::new(&archive_dir);
+
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(19));
+        assert!(provider.has_logs_until(19));
+        assert!(!provider.has_logs_until(20));
+
+        let filter = EthersFilter::new().address(address).from_block(10).to_block(19);
+        let archived_logs = provider.get_logs(&filter).await.unwrap();
+        assert_eq!(archived_logs.len(), 1);
+        assert_eq!(archived_logs[0].address, address);
+
+        assert_eq!(
+            provider.get_block(U64::from(10)).await.unwrap().hash,
+            block.hash
+        );
+        assert!(provider.get_block(U64::from(11)).await.is_err());
+
+        std::fs::remove_dir_all(archive_dir).unwrap();
+    }
+}
@@ -0,0 +1,544 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ethers::prelude::*;
+use ethers::providers::{Http, Provider as EthersProvider};
+use ethers::types::{Filter as EthersFilter, Log};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use super::archive::ArchiveProvider;
+use super::json_rpc_batch;
+use super::rate_limiter::RateLimiter;
+use super::{
+    debug_trace_block_with_call_tracer, get_missing_error, is_block_range_too_large, Provider,
+    ProviderError,
+};
+
+/// Bounds the block timestamps kept from logs. Timestamps only get taken
+/// when their blocks get fetched, so they would pile up otherwise.
+const MAX_BLOCK_TIMESTAMPS_COUNT: usize = 10_000;
+
+/// Provider over an ordered list of JSON-RPC endpoints.
+/// Requests go to the first healthy endpoint. An endpoint that errors or
+/// times out cools down and requests rotate to the next healthy one.
+#[derive(Clone)]
+pub struct FailoverProvider {
+    endpoints: Vec<Endpoint>,
+    health: Arc<Mutex<EndpointsHealth>>,
+    timeout: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    batch_size: usize,
+    /// Timestamps returned along with logs, saving the block fetches for them
+    block_timestamps_by_hash: Arc<Mutex<HashMap<H256, U256>>>,
+    /// Serves the archived block ranges instead of the endpoints
+    archive: Option<ArchiveProvider>,
+}
+
+#[derive(Clone)]
+struct Endpoint {
+    url: String,
+    provider: EthersProvider<Http>,
+    http_client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LogWithBlockTimestamp {
+    #[serde(flatten)]
+    log: Log,
+    #[serde(rename = "blockTimestamp")]
+    block_timestamp: Option<U256>,
+}
+
+impl FailoverProvider {
+    pub fn new(json_rpc_urls: &[String], timeout: Duration, cool_down: Duration) -> Self {
+        let http_client = reqwest::Client::new();
+
+        Self {
+            endpoints: json_rpc_urls
+                .iter()
+                .map(|url| Endpoint {
+                    url: url.clone(),
+                    provider: EthersProvider::new(Http::new_with_client(
+                        url.parse::<reqwest::Url>().unwrap(),
+                        http_client.clone(),
+                    )),
+                    http_client: http_client.clone(),
+                })
+                .collect(),
+            health: Arc::new(Mutex::new(EndpointsHealth::new(
+                json_rpc_urls.len(),
+                cool_down,
+            ))),
+            timeout,
+            rate_limiter: None,
+            batch_size: 50,
+            block_timestamps_by_hash: Arc::new(Mutex::new(HashMap::new())),
+            archive: None,
+        }
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+
+        self
+    }
+
+    pub fn with_archive(mut self, archive: ArchiveProvider) -> Self {
+        self.archive = Some(archive);
+
+        self
+    }
+
+    async fn get_logs_from_endpoints(
+        &self,
+        filter: &EthersFilter,
+    ) -> Result<Vec<Log>, ProviderError> {
+        let logs: Vec<LogWithBlockTimestamp> = self
+            .request("eth_getLogs", 1, |Endpoint { provider, .. }| async move {
+                provider.request("eth_getLogs", [filter]).await
+            })
+            .await?;
+
+        let mut block_timestamps_by_hash = self.block_timestamps_by_hash.lock().await;
+
+        Ok(keep_block_timestamps(&mut block_timestamps_by_hash, logs))
+    }
+
+    async fn get_blocks_by_number_from_endpoints(
+        &self,
+        logs: &[Log],
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        let mut blocks_by_number = HashMap::new();
+        let mut block_numbers_to_fetch = vec![];
+        let mut seen_block_numbers = HashSet::new();
+
+        let mut block_timestamps_by_hash = self.block_timestamps_by_hash.lock().await;
+
+        for Log {
+            block_number,
+            block_hash,
+            ..
+        } in logs
+        {
+            let block_number = block_number.unwrap();
+
+            if !seen_block_numbers.insert(block_number) {
+                continue;
+            }
+
+            match block_hash.and_then(|h| block_timestamps_by_hash.remove(&h)) {
+                Some(timestamp) => {
+                    blocks_by_number.insert(
+                        block_number,
+                        Block {
+                            number: Some(block_number),
+                            hash: *block_hash,
+                            timestamp,
+                            ..Default::default()
+                        },
+                    );
+                }
+                None => block_numbers_to_fetch.push(block_number),
+            }
+        }
+
+        drop(block_timestamps_by_hash);
+
+        for block_numbers in block_numbers_to_fetch.chunks(self.batch_size) {
+            let blocks = self
+                .request(
+                    "eth_getBlockByNumber",
+                    block_numbers.len() as u32,
+                    |Endpoint {
+                         url, http_client, ..
+                     }| async move {
+                        json_rpc_batch::get_blocks(&http_client, &url, block_numbers).await
+                    },
+                )
+                .await?;
+
+            for block in blocks {
+                blocks_by_number.insert(block.number.unwrap(), block);
+            }
+        }
+
+        Ok(blocks_by_number)
+    }
+
+    async fn request<T, F, Fut>(
+        &self,
+        method: &str,
+        requests_count: u32,
+        request: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: Fn(Endpoint) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, ProviderError>> + Send,
+        T: Send,
+    {
+        let endpoint_indices = self.health.lock().await.get_order(Instant::now());
+
+        let mut last_error = None;
+
+        for endpoint_index in endpoint_indices {
+            let endpoint = &self.endpoints[endpoint_index];
+            let url = &endpoint.url;
+
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire(method, requests_count).await;
+            }
+
+            let response = match timeout(self.timeout, request(endpoint.clone())).await {
+                Ok(response) => response,
+                Err(_elapsed) => Err(ProviderError::CustomError(format!(
+                    "Request to {url} timed out"
+                ))),
+            };
+
+            let mut health = self.health.lock().await;
+
+            match response {
+                Ok(response) => {
+                    health.mark_healthy(endpoint_index);
+
+                    return Ok(response);
+                }
+                // The endpoint is healthy, the request just needs a narrower block range
+                Err(provider_error) if is_block_range_too_large(&provider_error) => {
+                    return Err(provider_error);
+                }
+                Err(provider_error) => {
+                    eprintln!("Provider Error: {url}: {provider_error}");
+
+                    health.mark_unhealthy(endpoint_index, Instant::now());
+                    last_error = Some(provider_error);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| ProviderError::CustomError("No JSON-RPC endpoint".to_string())))
+    }
+}
+
+fn keep_block_timestamps(
+    block_timestamps_by_hash: &mut HashMap<H256, U256>,
+    logs: Vec<LogWithBlockTimestamp>,
+) -> Vec<Log> {
+    // Missing timestamps only cost block fetches
+    if block_timestamps_by_hash.len() + logs.len() > MAX_BLOCK_TIMESTAMPS_COUNT {
+        block_timestamps_by_hash.clear();
+    }
+
+    logs.into_iter()
+        .map(
+            |LogWithBlockTimestamp {
+                 log,
+                 block_timestamp,
+             }| {
+                if let (Some(block_hash), Some(block_timestamp)) = (log.block_hash, block_timestamp)
+                {
+                    block_timestamps_by_hash.insert(block_hash, block_timestamp);
+                }
+
+                log
+            },
+        )
+        .collect()
+}
+
+#[crate::augmenting_std::async_trait]
+impl Provider for FailoverProvider {
+    async fn get_block_number(&self) -> Result<U64, ProviderError> {
+        self.request("eth_blockNumber", 1, |Endpoint { provider, .. }| async move {
+            Middleware::get_block_number(&provider).await
+        })
+        .await
+    }
+
+    async fn get_logs(&self, filter: &EthersFilter) -> Result<Vec<Log>, ProviderError> {
+        let from_block_number = filter.get_from_block().unwrap_or_default().as_u64();
+
+        match self.archive.as_ref().and_then(|a| a.get_last_block_number()) {
+            Some(last_archived_block_number) if from_block_number <= last_archived_block_number => {
+                let archived_filter = filter.clone().to_block(
+                    filter
+                        .get_to_block()
+                        .map(|n| n.as_u64().min(last_archived_block_number))
+                        .unwrap_or(last_archived_block_number),
+                );
+                let mut logs = self.archive.as_ref().unwrap().get_logs(&archived_filter).await?;
+
+                // Switches to the endpoints once the archive gets exhausted
+                if !self
+                    .archive
+                    .as_ref()
+                    .unwrap()
+                    .has_logs_until(filter.get_to_block().map(|n| n.as_u64()).unwrap_or(u64::MAX))
+                {
+                    let live_filter = filter.clone().from_block(last_archived_block_number + 1);
+
+                    logs.extend(self.get_logs_from_endpoints(&live_filter).await?);
+                }
+
+                Ok(logs)
+            }
+            _ => self.get_logs_from_endpoints(filter).await,
+        }
+    }
+
+    async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+        if let Some(archive) = self.archive.as_ref().filter(|a| a.has_block(block_number.as_u64()))
+        {
+            return archive.get_block(block_number).await;
+        }
+
+        self.request(
+            "eth_getBlockByNumber",
+            1,
+            |Endpoint { provider, .. }| async move {
+                Middleware::get_block(&provider, block_number)
+                    .await?
+                    .ok_or_else(|| get_missing_error("block"))
+            },
+        )
+        .await
+    }
+
+    async fn get_tagged_block_number(&self, block_tag: BlockNumber) -> Result<U64, ProviderError> {
+        self.request(
+            "eth_getBlockByNumber",
+            1,
+            |Endpoint { provider, .. }| async move {
+                Middleware::get_block(&provider, block_tag)
+                    .await?
+                    .and_then(|b| b.number)
+                    .ok_or_else(|| get_missing_error("block number"))
+            },
+        )
+        .await
+    }
+
+    async fn get_block_with_transactions(
+        &self,
+        block_number: U64,
+    ) -> Result<Block<Transaction>, ProviderError> {
+        self.request(
+            "eth_getBlockByNumber",
+            1,
+            |Endpoint { provider, .. }| async move {
+                Middleware::get_block_with_txs(&provider, block_number)
+                    .await?
+                    .ok_or_else(|| get_missing_error("block"))
+            },
+        )
+        .await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        transaction_hash: TxHash,
+    ) -> Result<TransactionReceipt, ProviderError> {
+        self.request(
+            "eth_getTransactionReceipt",
+            1,
+            |Endpoint { provider, .. }| async move {
+                Middleware::get_transaction_receipt(&provider, transaction_hash)
+                    .await?
+                    .ok_or_else(|| get_missing_error("transaction receipt"))
+            },
+        )
+        .await
+    }
+
+    async fn trace_filter(&self, filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        self.request("trace_filter", 1, |Endpoint { provider, .. }| async move {
+            Middleware::trace_filter(&provider, filter.clone()).await
+        })
+        .await
+    }
+
+    async fn debug_trace_block(&self, block_number: U64) -> Result<Vec<CallFrame>, ProviderError> {
+        self.request(
+            "debug_traceBlockByNumber",
+            1,
+            |Endpoint { provider, .. }| async move {
+                debug_trace_block_with_call_tracer(&provider, block_number).await
+            },
+        )
+        .await
+    }
+
+    async fn get_blocks_by_number(
+        &self,
+        logs: &Vec<Log>,
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        match &self.archive {
+            Some(archive) => {
+                let (archived_logs, logs): (Vec<_>, Vec<_>) = logs
+                    .iter()
+                    .cloned()
+                    .partition(|log| archive.has_block(log.block_number.unwrap().as_u64()));
+
+                let mut blocks_by_number = archive.get_blocks_by_number(&archived_logs).await?;
+                blocks_by_number.extend(self.get_blocks_by_number_from_endpoints(&logs).await?);
+
+                Ok(blocks_by_number)
+            }
+            None => self.get_blocks_by_number_from_endpoints(logs).await,
+        }
+    }
+}
+
+struct EndpointsHealth {
+    cool_down_until: Vec<Option<Instant>>,
+    cool_down: Duration,
+}
+
+impl EndpointsHealth {
+    fn new(endpoints_count: usize, cool_down: Duration) -> Self {
+        Self {
+            cool_down_until: vec![None; endpoints_count],
+            cool_down,
+        }
+    }
+
+    /// Healthy endpoints in order of preference, followed by cooling down
+    /// endpoints in the order they recover, so that a request is never
+    /// dropped just because every endpoint failed recently.
+    fn get_order(&self, now: Instant) -> Vec<usize> {
+        let (healthy, mut cooling_down): (Vec<_>, Vec<_>) =
+            self.cool_down_until.iter().enumerate().partition(|(_index, cool_down_until)| {
+                match cool_down_until {
+                    Some(cool_down_until) => *cool_down_until <= now,
+                    None => true,
+                }
+            });
+
+        cooling_down.sort_by_key(|(_index, cool_down_until)| **cool_down_until);
+
+        healthy.into_iter().chain(cooling_down).map(|(index, _)| index).collect()
+    }
+
+    fn mark_healthy(&mut self, endpoint_index: usize) {
+        self.cool_down_until[endpoint_index] = None;
+    }
+
+    fn mark_unhealthy(&mut self, endpoint_index: usize, now: Instant) {
+        self.cool_down_until[endpoint_index] = Some(now + self.cool_down);
+    }
+}
+
+#[cfg(test)]
+mod endpoints_health_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fails_requests_without_endpoints() {
+        let provider = FailoverProvider::new(&[], Duration::from_secs(1), Duration::from_secs(1));
+
+        assert!(Provider::get_block_number(&provider).await.is_err());
+    }
+
+    #[test]
+    fn prefers_endpoints_in_the_given_order() {
+        let endpoints_health = EndpointsHealth::new(3, Duration::from_secs(60));
+
+        assert_eq!(endpoints_health.get_order(Instant::now()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn skips_endpoints_cooling_down() {
+        let mut endpoints_health = EndpointsHealth::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+
+        endpoints_health.mark_unhealthy(0, now);
+
+        assert_eq!(endpoints_health.get_order(now), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn retries_endpoints_after_cooling_down() {
+        let mut endpoints_health = EndpointsHealth::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        endpoints_health.mark_unhealthy(0, now);
+
+        assert_eq!(
+            endpoints_health.get_order(now + Duration::from_secs(61)),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_earliest_recovering_endpoint_when_all_are_cooling_down() {
+        let mut endpoints_health = EndpointsHealth::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        endpoints_health.mark_unhealthy(1, now);
+        endpoints_health.mark_unhealthy(0, now + Duration::from_secs(1));
+
+        assert_eq!(endpoints_health.get_order(now), vec![1, 0]);
+    }
+}
+
+#[cfg(test)]
+mod log_with_block_timestamp_tests {
+    use super::*;
+
+    #[test]
+    fn reads_block_timestamp_returned_along_with_log() {
+        let LogWithBlockTimestamp {
+            log,
+            block_timestamp,
+        } = serde_json::from_value(serde_json::json!({
+            "address": "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d",
+            "topics": [],
+            "data": "0x",
+            "blockHash": "0x8fd4ca304a2e81854059bc3e42f32064cca8b6b453f6286f95060edc6382c6f8",
+            "blockNumber": "0x1146d76",
+            "blockTimestamp": "0x650a6f9b",
+            "transactionHash": "0x83d751998ff98cd609bc9b18bb36bdef8659cde2f74d6d7a1b0fef2c2bf8f839",
+            "transactionIndex": "0x59",
+            "logIndex": "0x10",
+            "removed": false
+        }))
+        .unwrap();
+
+        assert_eq!(log.block_number, Some(U64::from(18115958)));
+        assert_eq!(block_timestamp, Some(U256::from(0x650a6f9b)));
+    }
+
+    #[test]
+    fn bounds_kept_block_timestamps() {
+        let mut block_timestamps_by_hash: HashMap<_, _> = (0..MAX_BLOCK_TIMESTAMPS_COUNT)
+            .map(|i| (H256::from_low_u64_be(i as u64), U256::from(i)))
+            .collect();
+        let block_hash = H256::random();
+        let log = LogWithBlockTimestamp {
+            log: Log {
+                block_hash: Some(block_hash),
+                ..Default::default()
+            },
+            block_timestamp: Some(U256::from(0x650a6f9b)),
+        };
+
+        keep_block_timestamps(&mut block_timestamps_by_hash, vec![log]);
+
+        assert_eq!(block_timestamps_by_hash.len(), 1);
+        assert_eq!(
+            block_timestamps_by_hash[&block_hash],
+            U256::from(0x650a6f9b)
+        );
+    }
+}
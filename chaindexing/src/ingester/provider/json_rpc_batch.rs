@@ -0,0 +1,119 @@
+use ethers::types::{Block, TxHash, U64};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::ProviderError;
+
+#[derive(Debug, Deserialize)]
+struct Response<R> {
+    id: usize,
+    result: Option<R>,
+    error: Option<Value>,
+}
+
+/// Fetches blocks with a single JSON-RPC batch request
+pub async fn get_blocks(
+    http_client: &reqwest::Client,
+    url: &str,
+    block_numbers: &[U64],
+) -> Result<Vec<Block<TxHash>>, ProviderError> {
+    let requests: Vec<_> = block_numbers
+        .iter()
+        .enumerate()
+        .map(|(id, block_number)| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "eth_getBlockByNumber",
+                "params": [block_number, false]
+            })
+        })
+        .collect();
+
+    let responses = http_client.post(url).json(&requests).send().await?.json().await?;
+
+    get_blocks_from_responses(responses, block_numbers)
+}
+
+fn get_blocks_from_responses(
+    mut responses: Vec<Response<Block<TxHash>>>,
+    block_numbers: &[U64],
+) -> Result<Vec<Block<TxHash>>, ProviderError> {
+    // Batch responses can come back in any order
+    responses.sort_by_key(|response| response.id);
+
+    if responses.len() != block_numbers.len() {
+        return Err(ProviderError::CustomError(format!(
+            "Batch returned {} responses for {} blocks",
+            responses.len(),
+            block_numbers.len()
+        )));
+    }
+
+    responses
+        .into_iter()
+        .zip(block_numbers)
+        .map(|(response, block_number)| match response {
+            Response {
+                result: Some(block),
+                ..
+            } => Ok(block),
+            Response {
+                error: Some(error), ..
+            } => Err(ProviderError::CustomError(error.to_string())),
+            _ => Err(ProviderError::CustomError(format!(
+                "Block {block_number} not found"
+            ))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod json_rpc_batch_tests {
+    use super::*;
+
+    fn responses(value: Value) -> Vec<Response<Block<TxHash>>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn block(number: &str, timestamp: &str) -> Value {
+        let mut block = serde_json::to_value(Block::<TxHash>::default()).unwrap();
+        block["number"] = json!(number);
+        block["timestamp"] = json!(timestamp);
+
+        block
+    }
+
+    #[test]
+    fn returns_blocks_in_requested_order() {
+        let responses = responses(json!([
+            { "jsonrpc": "2.0", "id": 1, "result": block("0x11", "0x65") },
+            { "jsonrpc": "2.0", "id": 0, "result": block("0x10", "0x64") }
+        ]));
+
+        let blocks = get_blocks_from_responses(responses, &[U64::from(16), U64::from(17)]).unwrap();
+
+        assert_eq!(blocks[0].number, Some(U64::from(16)));
+        assert_eq!(blocks[1].number, Some(U64::from(17)));
+        assert_eq!(blocks[1].timestamp.as_u64(), 101);
+    }
+
+    #[test]
+    fn fails_when_a_block_is_missing() {
+        let responses = responses(json!([
+            { "jsonrpc": "2.0", "id": 0, "result": block("0x10", "0x64") },
+            { "jsonrpc": "2.0", "id": 1, "result": null }
+        ]));
+
+        assert!(get_blocks_from_responses(responses, &[U64::from(16), U64::from(17)]).is_err());
+    }
+
+    #[test]
+    fn fails_when_a_request_errors() {
+        let responses = responses(json!([
+            { "jsonrpc": "2.0", "id": 0, "error": { "code": 429, "message": "Too Many Requests" } }
+        ]));
+
+        assert!(get_blocks_from_responses(responses, &[U64::from(16)]).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+/// Token bucket shared by every JSON-RPC request made for a chain.
+/// The bucket holds up to a second's worth of budget and each request
+/// takes its method's cost out of it, waiting when the budget is exhausted.
+pub struct RateLimiter {
+    chain_name: String,
+    method_costs: HashMap<String, u32>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        chain_name: &str,
+        requests_per_second: u32,
+        method_costs: &HashMap<String, u32>,
+    ) -> Self {
+        Self {
+            chain_name: chain_name.to_string(),
+            method_costs: method_costs.clone(),
+            bucket: Mutex::new(Bucket::new(requests_per_second, Instant::now())),
+        }
+    }
+
+    pub async fn acquire(&self, method: &str, requests_count: u32) {
+        let cost = self.method_costs.get(method).unwrap_or(&1) * requests_count;
+        let wait = self.bucket.lock().await.reserve(cost, Instant::now());
+
+        if !wait.is_zero() {
+            eprintln!(
+                "Rate Limited: JSON-RPC budget exhausted for {}, {} waits {}ms",
+                self.chain_name,
+                method,
+                wait.as_millis()
+            );
+
+            sleep(wait).await;
+        }
+    }
+}
+
+struct Bucket {
+    requests_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(requests_per_second: u32, now: Instant) -> Self {
+        let requests_per_second = max(requests_per_second, 1) as f64;
+
+        Self {
+            requests_per_second,
+            tokens: requests_per_second,
+            refilled_at: now,
+        }
+    }
+
+    /// Takes the cost out of the bucket and returns how long to wait before
+    /// the request is within budget. Tokens can go negative so that waiting
+    /// requests are served in the order they were reserved.
+    fn reserve(&mut self, cost: u32, now: Instant) -> Duration {
+        let refilled_tokens =
+            now.duration_since(self.refilled_at).as_secs_f64() * self.requests_per_second;
+        self.tokens = (self.tokens + refilled_tokens).min(self.requests_per_second);
+        self.refilled_at = now;
+
+        // A request costing more than the whole bucket would otherwise never fit
+        let cost = min(cost as u64, self.requests_per_second as u64) as f64;
+        self.tokens -= cost;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.requests_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod bucket_tests {
+    use super::*;
+
+    #[test]
+    fn allows_requests_within_budget() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(2, now);
+
+        assert_eq!(bucket.reserve(1, now), Duration::ZERO);
+        assert_eq!(bucket.reserve(1, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn waits_when_budget_is_exhausted() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(2, now);
+
+        bucket.reserve(2, now);
+
+        assert_eq!(bucket.reserve(1, now), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(1, now), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(2, now);
+
+        bucket.reserve(2, now);
+
+        assert_eq!(
+            bucket.reserve(2, now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn caps_costs_to_the_bucket_size() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(2, now);
+
+        assert_eq!(bucket.reserve(10, now), Duration::ZERO);
+    }
+}
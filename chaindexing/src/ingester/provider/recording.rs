@@ -0,0 +1,437 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Arc;
+
+use ethers::prelude::*;
+use ethers::types::{Filter as EthersFilter, Log};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use super::{Provider, ProviderError};
+
+/// A provider's response to a request, as written to recording files.
+/// Recording files hold one recording per line.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Recording {
+    method: String,
+    params: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Method and JSON params of a request
+type RecordingKey = (String, String);
+
+impl Recording {
+    fn get_key(&self) -> RecordingKey {
+        (self.method.clone(), self.params.to_string())
+    }
+}
+
+/// Wraps a provider to write every request and its response to a recording file,
+/// so that ingestion can be reproduced with `ReplayProvider`.
+#[derive(Clone)]
+pub struct RecordingProvider<P: Provider> {
+    provider: P,
+    recording_file: Option<Arc<Mutex<File>>>,
+}
+
+impl<P: Provider> RecordingProvider<P> {
+    /// Appends to the recording file, creating it if it does not exist.
+    /// Panics if the recording file cannot be opened.
+    pub fn new(provider: P, recording_path: impl AsRef<Path>) -> Self {
+        let recording_path = recording_path.as_ref();
+        let recording_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(recording_path)
+            .unwrap_or_else(|error| {
+                panic!("Recording Error: {}: {error}", recording_path.display())
+            });
+
+        Self {
+            provider,
+            recording_file: Some(Arc::new(Mutex::new(File::from_std(recording_file)))),
+        }
+    }
+
+    /// Records only when given a recording path
+    pub(crate) fn maybe_new(provider: P, recording_path: Option<impl AsRef<Path>>) -> Self {
+        match recording_path {
+            Some(recording_path) => Self::new(provider, recording_path),
+            None => Self {
+                provider,
+                recording_file: None,
+            },
+        }
+    }
+
+    async fn record<T: Serialize>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+        response: Result<T, ProviderError>,
+    ) -> Result<T, ProviderError> {
+        let Some(recording_file) = &self.recording_file else {
+            return response;
+        };
+
+        let recording = Recording {
+            method: method.to_string(),
+            params: serde_json::to_value(params).unwrap(),
+            result: response.as_ref().ok().map(|result| serde_json::to_value(result).unwrap()),
+            error: response.as_ref().err().map(|error| error.to_string()),
+        };
+
+        let mut line = serde_json::to_string(&recording).unwrap();
+        line.push('\n');
+
+        let mut recording_file = recording_file.lock().await;
+        if let Err(error) = recording_file.write_all(line.as_bytes()).await {
+            eprintln!("Recording Error: {error}");
+        }
+        if let Err(error) = recording_file.flush().await {
+            eprintln!("Recording Error: {error}");
+        }
+
+        response
+    }
+}
+
+#[crate::augmenting_std::async_trait]
+impl<P: Provider> Provider for RecordingProvider<P> {
+    async fn get_block_number(&self) -> Result<U64, ProviderError> {
+        let response = self.provider.get_block_number().await;
+
+        self.record("eth_blockNumber", (), response).await
+    }
+
+    async fn get_logs(&self, filter: &EthersFilter) -> Result<Vec<Log>, ProviderError> {
+        let response = self.provider.get_logs(filter).await;
+
+        self.record("eth_getLogs", filter, response).await
+    }
+
+    async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+        let response = self.provider.get_block(block_number).await;
+
+        self.record("eth_getBlockByNumber", block_number, response).await
+    }
+
+    async fn get_blocks_by_number(
+        &self,
+        logs: &Vec<Log>,
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        let response = self.provider.get_blocks_by_number(logs).await.map(|blocks_by_number| {
+            let mut blocks: Vec<_> = blocks_by_number.into_values().collect();
+            blocks.sort_by_key(|block| block.number);
+
+            blocks
+        });
+
+        let response =
+            self.record("eth_getBlocksByNumber", get_block_numbers(logs), response).await;
+
+        response.map(group_blocks_by_number)
+    }
+
+    async fn get_block_with_transactions(
+        &self,
+        block_number: U64,
+    ) -> Result<Block<Transaction>, ProviderError> {
+        let response = self.provider.get_block_with_transactions(block_number).await;
+
+        self.record(
+            "eth_getBlockByNumberWithTransactions",
+            block_number,
+            response,
+        )
+        .await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        transaction_hash: TxHash,
+    ) -> Result<TransactionReceipt, ProviderError> {
+        let response = self.provider.get_transaction_receipt(transaction_hash).await;
+
+        self.record("eth_getTransactionReceipt", transaction_hash, response).await
+    }
+
+    async fn get_tagged_block_number(&self, block_tag: BlockNumber) -> Result<U64, ProviderError> {
+        let response = self.provider.get_tagged_block_number(block_tag).await;
+
+        self.record("eth_getBlockNumberByTag", block_tag, response).await
+    }
+
+    async fn trace_filter(&self, filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        let response = self.provider.trace_filter(filter).await;
+
+        self.record("trace_filter", filter, response).await
+    }
+
+    async fn debug_trace_block(&self, block_number: U64) -> Result<Vec<CallFrame>, ProviderError> {
+        let response = self.provider.debug_trace_block(block_number).await;
+
+        self.record("debug_traceBlockByNumber", block_number, response).await
+    }
+}
+
+/// Serves the responses written by `RecordingProvider`, to reproduce ingestion
+/// bit-for-bit. Identical requests get their recorded responses in order, with
+/// the last one repeated once they run out.
+#[derive(Clone)]
+pub struct ReplayProvider {
+    recordings_by_key: Arc<Mutex<HashMap<RecordingKey, VecDeque<Recording>>>>,
+}
+
+impl ReplayProvider {
+    /// Panics if the recording file cannot be read.
+    pub fn new(recording_path: impl AsRef<Path>) -> Self {
+        let recording_path = recording_path.as_ref();
+        let recordings = std::fs::read_to_string(recording_path)
+            .unwrap_or_else(|error| panic!("Replay Error: {}: {error}", recording_path.display()));
+
+        let mut recordings_by_key: HashMap<_, VecDeque<_>> = HashMap::new();
+
+        for recording in recordings.lines().filter(|line| !line.trim().is_empty()) {
+            let recording: Recording = serde_json::from_str(recording).unwrap_or_else(|error| {
+                panic!("Replay Error: {}: {error}", recording_path.display())
+            });
+
+            recordings_by_key.entry(recording.get_key()).or_default().push_back(recording);
+        }
+
+        Self {
+            recordings_by_key: Arc::new(Mutex::new(recordings_by_key)),
+        }
+    }
+
+    async fn replay<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<T, ProviderError> {
+        let params = serde_json::to_value(params).unwrap();
+        let key = (method.to_string(), params.to_string());
+
+        let mut recordings_by_key = self.recordings_by_key.lock().await;
+
+        let recording = match recordings_by_key.get_mut(&key) {
+            Some(recordings) if recordings.len() > 1 => recordings.pop_front(),
+            Some(recordings) => recordings.front().cloned(),
+            None => None,
+        };
+
+        match recording {
+            Some(Recording {
+                result: Some(result),
+                ..
+            }) => serde_json::from_value(result).map_err(ProviderError::SerdeJson),
+            Some(Recording {
+                error: Some(error), ..
+            }) => Err(ProviderError::CustomError(error)),
+            _ => Err(ProviderError::CustomError(format!(
+                "Replay Error: no recording of {method} with {params}"
+            ))),
+        }
+    }
+}
+
+#[crate::augmenting_std::async_trait]
+impl Provider for ReplayProvider {
+    async fn get_block_number(&self) -> Result<U64, ProviderError> {
+        self.replay("eth_blockNumber", ()).await
+    }
+
+    async fn get_logs(&self, filter: &EthersFilter) -> Result<Vec<Log>, ProviderError> {
+        self.replay("eth_getLogs", filter).await
+    }
+
+    async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+        self.replay("eth_getBlockByNumber", block_number).await
+    }
+
+    async fn get_blocks_by_number(
+        &self,
+        logs: &Vec<Log>,
+    ) -> Result<HashMap<U64, Block<TxHash>>, ProviderError> {
+        self.replay("eth_getBlocksByNumber", get_block_numbers(logs))
+            .await
+            .map(group_blocks_by_number)
+    }
+
+    async fn get_block_with_transactions(
+        &self,
+        block_number: U64,
+    ) -> Result<Block<Transaction>, ProviderError> {
+        self.replay("eth_getBlockByNumberWithTransactions", block_number).await
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        transaction_hash: TxHash,
+    ) -> Result<TransactionReceipt, ProviderError> {
+        self.replay("eth_getTransactionReceipt", transaction_hash).await
+    }
+
+    async fn get_tagged_block_number(&self, block_tag: BlockNumber) -> Result<U64, ProviderError> {
+        self.replay("eth_getBlockNumberByTag", block_tag).await
+    }
+
+    async fn trace_filter(&self, filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        self.replay("trace_filter", filter).await
+    }
+
+    async fn debug_trace_block(&self, block_number: U64) -> Result<Vec<CallFrame>, ProviderError> {
+        self.replay("debug_traceBlockByNumber", block_number).await
+    }
+}
+
+fn get_block_numbers(logs: &[Log]) -> Vec<U64> {
+    let mut block_numbers: Vec<_> = logs.iter().map(|log| log.block_number.unwrap()).collect();
+    block_numbers.sort();
+    block_numbers.dedup();
+
+    block_numbers
+}
+
+fn group_blocks_by_number(blocks: Vec<Block<TxHash>>) -> HashMap<U64, Block<TxHash>> {
+    blocks.into_iter().map(|block| (block.number.unwrap(), block)).collect()
+}
+
+#[cfg(test)]
+mod recording_tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct CountingProvider {
+        block_number: Arc<AtomicU64>,
+    }
+
+    #[crate::augmenting_std::async_trait]
+    impl Provider for CountingProvider {
+        async fn get_block_number(&self) -> Result<U64, ProviderError> {
+            Ok(U64::from(self.block_number.fetch_add(1, Ordering::SeqCst)))
+        }
+
+        async fn get_logs(&self, filter: &EthersFilter) -> Result<Vec<Log>, ProviderError> {
+            Ok(vec![Log {
+                block_number: filter.get_from_block(),
+                ..Default::default()
+            }])
+        }
+
+        async fn get_block(&self, block_number: U64) -> Result<Block<TxHash>, ProviderError> {
+            if block_number.is_zero() {
+                Err(ProviderError::CustomError(
+                    "block range is too large".to_string(),
+                ))
+            } else {
+                Ok(Block {
+                    number: Some(block_number),
+                    hash: Some(H256::random()),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    fn get_recording_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("recording-{}.jsonl", H256::random()))
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_responses() {
+        let recording_path = get_recording_path();
+        let recording_provider =
+            RecordingProvider::new(CountingProvider::default(), &recording_path);
+
+        let filter = EthersFilter::new().from_block(17773490).to_block(17773500);
+        let recorded_logs = recording_provider.get_logs(&filter).await.unwrap();
+        let recorded_block = recording_provider.get_block(U64::from(17773490)).await.unwrap();
+        let recorded_blocks_by_number =
+            recording_provider.get_blocks_by_number(&recorded_logs).await.unwrap();
+
+        let replay_provider = ReplayProvider::new(&recording_path);
+
+        assert_eq!(
+            replay_provider.get_logs(&filter).await.unwrap(),
+            recorded_logs
+        );
+        assert_eq!(
+            replay_provider.get_block(U64::from(17773490)).await.unwrap(),
+            recorded_block
+        );
+        assert_eq!(
+            replay_provider.get_blocks_by_number(&recorded_logs).await.unwrap(),
+            recorded_blocks_by_number
+        );
+
+        std::fs::remove_file(recording_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_responses_to_identical_requests_in_order() {
+        let recording_path = get_recording_path();
+        let recording_provider =
+            RecordingProvider::new(CountingProvider::default(), &recording_path);
+
+        for _ in 0..2 {
+            recording_provider.get_block_number().await.unwrap();
+        }
+
+        let replay_provider = ReplayProvider::new(&recording_path);
+
+        assert_eq!(
+            replay_provider.get_block_number().await.unwrap(),
+            U64::from(0)
+        );
+        assert_eq!(
+            replay_provider.get_block_number().await.unwrap(),
+            U64::from(1)
+        );
+        assert_eq!(
+            replay_provider.get_block_number().await.unwrap(),
+            U64::from(1)
+        );
+
+        std::fs::remove_file(recording_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_errors() {
+        let recording_path = get_recording_path();
+        let recording_provider =
+            RecordingProvider::new(CountingProvider::default(), &recording_path);
+
+        assert!(recording_provider.get_block(U64::zero()).await.is_err());
+
+        let replay_provider = ReplayProvider::new(&recording_path);
+        let replayed_error = replay_provider.get_block(U64::zero()).await.unwrap_err();
+
+        assert!(super::super::is_block_range_too_large(&replayed_error));
+
+        std::fs::remove_file(recording_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_requests_that_were_not_recorded() {
+        let recording_path = get_recording_path();
+        RecordingProvider::new(CountingProvider::default(), &recording_path);
+
+        let replay_provider = ReplayProvider::new(&recording_path);
+
+        assert!(replay_provider.get_block(U64::from(1)).await.is_err());
+
+        std::fs::remove_file(recording_path).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use std::cmp::min;
+use std::time::Duration;
+
+use ethers::providers::RpcError;
+use rand::Rng;
+
+use super::ProviderError;
+
+/// Decides how often and how long failed JSON-RPC requests get retried.
+/// Requests failing with fatal errors, such as an invalid API key or an
+/// unsupported method, are never retried.
+///
+/// # Example
+/// ```
+/// use chaindexing::RetryPolicy;
+///
+/// RetryPolicy::new(5)
+///     .with_initial_backoff_ms(500)
+///     .with_max_backoff_ms(10_000);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) initial_backoff_ms: u64,
+    pub(crate) max_backoff_ms: u64,
+    pub(crate) jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 10,
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Gives up on requests still failing after `max_retries` retries
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Backoff before the first retry, doubled on each retry after.
+    /// Default is 1_000
+    pub fn with_initial_backoff_ms(mut self, initial_backoff_ms: u64) -> Self {
+        self.initial_backoff_ms = initial_backoff_ms;
+
+        self
+    }
+
+    /// Caps the doubling backoff.
+    /// Default is 60_000
+    pub fn with_max_backoff_ms(mut self, max_backoff_ms: u64) -> Self {
+        self.max_backoff_ms = max_backoff_ms;
+
+        self
+    }
+
+    /// Backs off for exactly the doubling backoff. By default, a random
+    /// part of it is taken off, so chains failing together retry apart.
+    pub fn without_jitter(mut self) -> Self {
+        self.jitter = false;
+
+        self
+    }
+
+    pub(crate) fn should_retry(&self, retries_so_far: u32, provider_error: &ProviderError) -> bool {
+        retries_so_far < self.max_retries
+            && ProviderErrorKind::of(provider_error) == ProviderErrorKind::Transient
+    }
+
+    pub(crate) fn get_backoff(&self, retries_so_far: u32) -> Duration {
+        let backoff_ms = 2u64
+            .checked_pow(retries_so_far)
+            .and_then(|factor| self.initial_backoff_ms.checked_mul(factor))
+            .map(|backoff_ms| min(backoff_ms, self.max_backoff_ms))
+            .unwrap_or(self.max_backoff_ms);
+
+        if self.jitter {
+            let half_backoff_ms = backoff_ms / 2;
+
+            Duration::from_millis(
+                half_backoff_ms + rand::thread_rng().gen_range(0..=backoff_ms - half_backoff_ms),
+            )
+        } else {
+            Duration::from_millis(backoff_ms)
+        }
+    }
+}
+
+/// Whether retrying a failed request can succeed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProviderErrorKind {
+    /// Timeouts, rate limits, unavailable endpoints, etc.
+    Transient,
+    /// Invalid API keys, unsupported methods, etc.
+    Fatal,
+}
+
+impl ProviderErrorKind {
+    const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+    const FATAL_MESSAGES: [&'static str; 9] = [
+        "unauthorized",
+        "invalid api key",
+        "must be authenticated",
+        "forbidden",
+        "method not found",
+        "method not supported",
+        "is not supported",
+        "does not exist/is not available",
+        "not whitelisted",
+    ];
+
+    pub fn of(provider_error: &ProviderError) -> Self {
+        match provider_error {
+            ProviderError::UnsupportedRPC
+            | ProviderError::UnsupportedNodeClient
+            | ProviderError::SignerUnavailable
+            | ProviderError::EnsError(_)
+            | ProviderError::EnsNotOwned(_)
+            | ProviderError::HexError(_) => ProviderErrorKind::Fatal,
+            _ if provider_error
+                .as_error_response()
+                .is_some_and(|error| error.code == Self::METHOD_NOT_FOUND_CODE) =>
+            {
+                ProviderErrorKind::Fatal
+            }
+            _ => {
+                let provider_error = provider_error.to_string().to_lowercase();
+
+                if Self::FATAL_MESSAGES.iter().any(|message| provider_error.contains(message)) {
+                    ProviderErrorKind::Fatal
+                } else {
+                    ProviderErrorKind::Transient
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod retry_policy_tests {
+    use super::*;
+
+    fn custom_error(message: &str) -> ProviderError {
+        ProviderError::CustomError(message.to_string())
+    }
+
+    #[test]
+    fn doubles_backoff_up_to_the_max_backoff() {
+        let retry_policy = RetryPolicy::default().without_jitter();
+
+        assert_eq!(retry_policy.get_backoff(0), Duration::from_secs(1));
+        assert_eq!(retry_policy.get_backoff(3), Duration::from_secs(8));
+        assert_eq!(retry_policy.get_backoff(6), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn does_not_overflow_after_many_retries() {
+        let retry_policy = RetryPolicy::default().without_jitter();
+
+        assert_eq!(retry_policy.get_backoff(64), Duration::from_secs(60));
+        assert_eq!(retry_policy.get_backoff(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn takes_at_most_half_of_the_backoff_off_with_jitter() {
+        let retry_policy = RetryPolicy::default();
+
+        for _ in 0..100 {
+            let backoff = retry_policy.get_backoff(3);
+
+            assert!(backoff >= Duration::from_secs(4) && backoff <= Duration::from_secs(8));
+        }
+    }
+
+    #[test]
+    fn stops_retrying_after_max_retries() {
+        let retry_policy = RetryPolicy::new(2);
+        let provider_error = custom_error("Request to http://localhost:8545 timed out");
+
+        assert!(retry_policy.should_retry(1, &provider_error));
+        assert!(!retry_policy.should_retry(2, &provider_error));
+    }
+
+    #[test]
+    fn never_retries_fatal_errors() {
+        let retry_policy = RetryPolicy::default();
+
+        assert!(!retry_policy.should_retry(0, &ProviderError::UnsupportedRPC));
+        assert!(!retry_policy.should_retry(0, &custom_error("Invalid API key")));
+    }
+
+    #[test]
+    fn classifies_provider_errors() {
+        for message in [
+            "401 Unauthorized",
+            "Must be authenticated!",
+            "the method trace_filter does not exist/is not available",
+            "Method not found",
+        ] {
+            assert_eq!(
+                ProviderErrorKind::of(&custom_error(message)),
+                ProviderErrorKind::Fatal
+            );
+        }
+
+        for message in [
+            "Request to http://localhost:8545 timed out",
+            "429 Too Many Requests",
+            "header not found",
+        ] {
+            assert_eq!(
+                ProviderErrorKind::of(&custom_error(message)),
+                ProviderErrorKind::Transient
+            );
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use super::filters::Filter;
+use super::provider::{self, Provider, ProviderError, RetryPolicy};
+use crate::blocks::Block;
+use crate::transactions::{self, Transaction};
+use crate::{ChainId, ChaindexingRepo, ChaindexingRepoConn, Contract, ContractAddress, Repo};
+
+/// Fetches the handled transactions sent to the filters' contract addresses,
+/// caching the headers of the blocks fetched along the way
+pub async fn get<'a, S: Send + Sync + Clone>(
+    conn: &mut ChaindexingRepoConn<'a>,
+    provider: &Arc<impl Provider>,
+    retry_policy: &RetryPolicy,
+    filters: &[Filter],
+    contracts: &[Contract<S>],
+    contract_addresses: &[ContractAddress],
+    chain_id: &ChainId,
+) -> Result<Vec<Transaction>, ProviderError> {
+    if filters.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (fetched_transactions, blocks_by_number) =
+        provider::fetch_transactions(provider, retry_policy, filters).await?;
+
+    let blocks: Vec<_> = blocks_by_number
+        .values()
+        .filter(|block| block.hash.is_some())
+        .map(|block| Block::new(chain_id, block))
+        .collect();
+    ChaindexingRepo::upsert_blocks(conn, &blocks).await;
+
+    let handled_transactions = transactions::get_handled(
+        &fetched_transactions,
+        contracts,
+        contract_addresses,
+        chain_id,
+    );
+    let receipts_by_hash =
+        provider::fetch_transaction_receipts(provider, retry_policy, &handled_transactions).await?;
+
+    Ok(transactions::get(
+        &handled_transactions,
+        &receipts_by_hash,
+        contracts,
+        contract_addresses,
+        chain_id,
+        &blocks_by_number,
+    ))
+}
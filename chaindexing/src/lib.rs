@@ -24,15 +24,24 @@ mod root;
 /// Augmenting modules for standard library to support Chaindexing's operations
 pub mod augmenting_std;
 
-pub use chains::{Chain, ChainId};
+pub use call_traces::CallTrace;
+pub use chain_reorg::{Finality, ReorgDetection};
+pub use chains::{Chain, ChainId, JsonRpcUrls, TraceMode};
 pub use config::{Config, OptimizationConfig};
-pub use contracts::{Contract, ContractAddress, EventAbi};
-pub use events::{Event, EventParam};
+pub use contracts::{
+    Contract, ContractAddress, ContractAddressError, ContractAddressStatus, EventAbi, FunctionAbi,
+};
+pub use events::{Event, EventParam, QuarantinedLog};
 pub use handlers::{
-    PureHandler as EventHandler, PureHandlerContext as EventContext, SideEffectHandler,
-    SideEffectHandlerContext as SideEffectContext,
+    BlockHandler, BlockHandlerContext as BlockContext, CallHandler,
+    CallHandlerContext as CallContext, PureHandler as EventHandler,
+    PureHandlerContext as EventContext, SideEffectHandler,
+    SideEffectHandlerContext as SideEffectContext, TopicFilter, TransactionHandler,
+    TransactionHandlerContext as TransactionContext,
 };
-pub use nodes::NodeHeartbeat as Heartbeat;
+pub use ingester::{ArchiveProvider, RecordingProvider, ReplayProvider, RetryPolicy};
+pub use nodes::{NodeHeartbeat as Heartbeat, NodeTaskError};
+pub use transactions::Transaction;
 
 pub use ethers::types::{I256, U256};
 use tokio::sync::Mutex;
@@ -47,15 +56,21 @@ pub type Bytes = Vec<u8>;
 #[cfg(feature = "postgres")]
 pub use repos::PostgresRepo;
 
+#[doc(hidden)]
+pub mod blocks;
 #[doc(hidden)]
 pub mod booting;
 #[doc(hidden)]
+pub mod call_traces;
+#[doc(hidden)]
 pub mod deferred_futures;
 #[doc(hidden)]
 pub mod events;
 #[doc(hidden)]
 pub mod ingester;
 #[doc(hidden)]
+pub mod transactions;
+#[doc(hidden)]
 pub use contracts::{ContractEvent, UnsavedContractAddress};
 #[doc(hidden)]
 pub use ingester::Provider as IngesterProvider;
@@ -145,7 +160,7 @@ pub async fn index_states<S: Send + Sync + Clone + Debug + 'static>(
         let mut conn = ChaindexingRepo::get_conn(&pool).await;
         let conn = &mut conn;
 
-        let mut node_tasks = NodeTasks::new(&current_node);
+        let mut node_tasks = NodeTasks::new(&current_node, &config.node_task_errors);
 
         loop {
             // Keep node active first to guarantee that at least this node is active before election
@@ -170,10 +185,14 @@ pub async fn index_states<S: Send + Sync + Clone + Debug + 'static>(
 
 /// Includes runtime-discovered contract addresses for indexing.
 ///
+/// Contracts spawned by a factory's event can be declared with
+/// `Contract::spawned_by` instead. Those get included as the factory's events
+/// get ingested, from the events' block numbers on, like this function does.
+///
 /// # Arguments
 ///
 /// * `event_context` - context where the contract was discovered.
-/// N/B: Indexing for this contract starts from this point onwards
+///   N/B: Indexing for this contract starts from this point onwards
 /// * `name` -  name of the contract as defined in the config
 /// * `address` -  address of discovered contract
 ///
@@ -200,6 +219,94 @@ pub async fn include_contract<'a, C: handlers::HandlerContext<'a>>(
     ChaindexingRepo::create_contract_address(event_context.get_client(), &contract_address).await;
 }
 
+/// Includes runtime-discovered contract addresses for indexing up to the end block,
+/// e.g. for contracts known to get deprecated.
+///
+/// # Example
+///
+/// ```ignore
+/// // In an EventHandler...
+/// chaindexing::include_contract_until(&context, "Auction", &auction_address, end_block_number)
+///  .await;
+/// ```
+pub async fn include_contract_until<'a, C: handlers::HandlerContext<'a>>(
+    event_context: &C,
+    contract_name: &str,
+    address: &str,
+    end_block_number: u64,
+) {
+    let event = event_context.get_event();
+    let chain_id = event.get_chain_id();
+    let start_block_number = event.get_block_number();
+
+    let contract_address =
+        UnsavedContractAddress::new(contract_name, address, &chain_id, start_block_number)
+            .with_end_block_number(end_block_number);
+
+    ChaindexingRepo::create_contract_address(event_context.get_client(), &contract_address).await;
+}
+
+/// Stops indexing a contract address for good, including it again changes nothing.
+/// Its indexed states and events are kept.
+/// Use `Config::exclude_contract` outside handlers.
+///
+/// # Example
+///
+/// ```ignore
+/// // In an EventHandler...
+/// chaindexing::exclude_contract(&context, &pool_contract_address).await?;
+/// ```
+pub async fn exclude_contract<'a, C: handlers::HandlerContext<'a>>(
+    event_context: &C,
+    address: &str,
+) -> Result<(), ContractAddressError> {
+    update_contract_address_status(event_context, address, ContractAddressStatus::Excluded).await
+}
+
+/// Pauses indexing a contract address until it gets resumed with `resume_contract`.
+/// Use `Config::pause_contract` outside handlers.
+///
+/// # Example
+///
+/// ```ignore
+/// // In an EventHandler...
+/// chaindexing::pause_contract(&context, &pool_contract_address).await?;
+/// ```
+pub async fn pause_contract<'a, C: handlers::HandlerContext<'a>>(
+    event_context: &C,
+    address: &str,
+) -> Result<(), ContractAddressError> {
+    update_contract_address_status(event_context, address, ContractAddressStatus::Paused).await
+}
+
+/// Resumes indexing a paused contract address from where it stopped.
+/// Use `Config::resume_contract` outside handlers.
+pub async fn resume_contract<'a, C: handlers::HandlerContext<'a>>(
+    event_context: &C,
+    address: &str,
+) -> Result<(), ContractAddressError> {
+    update_contract_address_status(event_context, address, ContractAddressStatus::Active).await
+}
+
+async fn update_contract_address_status<'a, C: handlers::HandlerContext<'a>>(
+    event_context: &C,
+    address: &str,
+    status: ContractAddressStatus,
+) -> Result<(), ContractAddressError> {
+    let address = contracts::parse_address(address)?;
+    let chain_id = event_context.get_event().get_chain_id();
+
+    ChaindexingRepo::update_contract_address_status_in_txn(
+        event_context.get_client(),
+        &address,
+        chain_id as u64,
+        status,
+    )
+    .await;
+
+    Ok(())
+}
+
 async fn wait_for_non_leader_nodes_to_abort(node_election_rate_ms: u64) {
     time::sleep(Duration::from_millis(node_election_rate_ms)).await;
 }
@@ -226,18 +333,27 @@ fn get_tasks_runner<S: Sync + Send + Debug + Clone + 'static>(
 
 pub mod prelude {
     pub use crate::augmenting_std::{async_trait, serde};
-    pub use crate::chains::{Chain, ChainId};
+    pub use crate::call_traces::CallTrace;
+    pub use crate::chain_reorg::{Finality, ReorgDetection};
+    pub use crate::chains::{Chain, ChainId, JsonRpcUrls, TraceMode};
     pub use crate::config::{Config, OptimizationConfig};
-    pub use crate::contracts::{Contract, ContractAddress, EventAbi};
+    pub use crate::contracts::{
+        Contract, ContractAddress, ContractAddressStatus, EventAbi, FunctionAbi,
+    };
     pub use crate::events::{Event, EventParam};
     pub use crate::handlers::{
-        PureHandler as EventHandler, PureHandlerContext as EventContext, SideEffectHandler,
-        SideEffectHandlerContext as SideEffectContext,
+        BlockHandler, BlockHandlerContext as BlockContext, CallHandler,
+        CallHandlerContext as CallContext, PureHandler as EventHandler,
+        PureHandlerContext as EventContext, SideEffectHandler,
+        SideEffectHandlerContext as SideEffectContext, TopicFilter, TransactionHandler,
+        TransactionHandlerContext as TransactionContext,
     };
-    pub use crate::nodes::NodeHeartbeat as Heartbeat;
+    pub use crate::ingester::RetryPolicy;
+    pub use crate::nodes::{NodeHeartbeat as Heartbeat, NodeTaskError};
     pub use crate::states::{
         ChainState, ContractState, Filters, MultiChainState, StateMigrations, Updates,
     };
+    pub use crate::transactions::Transaction;
     pub use crate::Address;
     pub use ethers::types::{I256, U256};
 }
@@ -5,12 +5,15 @@
 mod node;
 mod node_heartbeat;
 mod node_task;
+mod node_task_errors;
+mod node_task_supervisor;
 mod node_tasks;
 mod node_tasks_runner;
 
 pub use node::Node;
 pub use node_heartbeat::NodeHeartbeat;
 pub use node_task::NodeTask;
+pub use node_task_errors::{NodeTaskError, NodeTaskErrors};
 pub use node_tasks::NodeTasks;
 pub use node_tasks_runner::NodeTasksRunner;
 
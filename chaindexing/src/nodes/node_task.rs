@@ -1,9 +1,13 @@
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use super::node_task_errors::{NodeTaskError, NodeTaskErrors};
+use super::node_task_supervisor::{self, Subtask};
+
 #[derive(Clone, Debug)]
 pub struct NodeTask {
     subtasks: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>,
+    errors: NodeTaskErrors,
 }
 
 impl Default for NodeTask {
@@ -16,12 +20,25 @@ impl NodeTask {
     pub fn new() -> Self {
         NodeTask {
             subtasks: Arc::new(Mutex::new(Vec::new())),
+            errors: NodeTaskErrors::new(),
         }
     }
     pub async fn add_subtask(&self, task: tokio::task::JoinHandle<()>) {
         let mut subtasks = self.subtasks.lock().await;
         subtasks.push(task);
     }
+    /// Adds a subtask that gets restarted whenever it fails or panics.
+    /// `run` starts a new run of the subtask each time it gets called.
+    pub async fn add_supervised_subtask<F>(&self, name: &str, run: F)
+    where
+        F: Fn() -> Subtask + Send + Sync + 'static,
+    {
+        self.add_subtask(node_task_supervisor::supervise(name, &self.errors, run)).await;
+    }
+    /// Returns the errors of failed subtasks recorded since last taken
+    pub async fn take_errors(&self) -> Vec<NodeTaskError> {
+        self.errors.take_all().await
+    }
     pub async fn stop(&self) {
         let subtasks = self.subtasks.lock().await;
         for subtask in subtasks.iter() {
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+/// Bounds the errors kept, for subtasks failing over and over
+const MAX_ERRORS_COUNT: usize = 100;
+
+/// A failure of one of the node's subtasks, such as a chain's ingestion loop
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeTaskError {
+    /// Name of the failed subtask
+    pub subtask: String,
+    /// Returned error or panic message
+    pub message: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl NodeTaskError {
+    pub fn new(subtask: &str, message: &str) -> Self {
+        Self {
+            subtask: subtask.to_string(),
+            message: message.to_string(),
+            failed_at: Utc::now(),
+        }
+    }
+}
+
+/// Most recent errors of the node's subtasks, oldest first.
+/// Clones share the same errors.
+#[derive(Clone, Debug, Default)]
+pub struct NodeTaskErrors {
+    errors: Arc<Mutex<Vec<NodeTaskError>>>,
+}
+
+impl NodeTaskErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn add(&self, error: NodeTaskError) {
+        self.extend(vec![error]).await;
+    }
+
+    pub async fn extend(&self, new_errors: Vec<NodeTaskError>) {
+        let mut errors = self.errors.lock().await;
+
+        errors.extend(new_errors);
+
+        let overflow_count = errors.len().saturating_sub(MAX_ERRORS_COUNT);
+        errors.drain(..overflow_count);
+    }
+
+    pub async fn get_all(&self) -> Vec<NodeTaskError> {
+        self.errors.lock().await.clone()
+    }
+
+    pub async fn take_all(&self) -> Vec<NodeTaskError> {
+        std::mem::take(&mut *self.errors.lock().await)
+    }
+}
+
+#[cfg(test)]
+mod node_task_errors_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_only_the_most_recent_errors() {
+        let errors = NodeTaskErrors::new();
+
+        for i in 0..MAX_ERRORS_COUNT + 5 {
+            errors.add(NodeTaskError::new("ingester", &i.to_string())).await;
+        }
+
+        let errors = errors.get_all().await;
+        assert_eq!(errors.len(), MAX_ERRORS_COUNT);
+        assert_eq!(errors.first().unwrap().message, "5");
+    }
+}
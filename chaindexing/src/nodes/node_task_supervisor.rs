@@ -0,0 +1,156 @@
+use std::any::Any;
+use std::cmp::min;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+use super::node_task_errors::{NodeTaskError, NodeTaskErrors};
+
+/// A subtask's run, failing with an error or a panic
+pub type Subtask = BoxFuture<'static, Result<(), String>>;
+
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Keeps a subtask running, restarting it with backoff whenever it fails or
+/// panics. Each failure gets recorded in the errors. Runs lasting longer than
+/// the max backoff start the backoff over.
+pub fn supervise<F>(name: &str, errors: &NodeTaskErrors, run: F) -> JoinHandle<()>
+where
+    F: Fn() -> Subtask + Send + Sync + 'static,
+{
+    let name = name.to_string();
+    let errors = errors.clone();
+
+    tokio::spawn(async move {
+        let mut restarts_so_far = 0;
+
+        loop {
+            let started_at = Instant::now();
+            let mut subtask = AbortOnDrop(tokio::spawn(run()));
+
+            let message = match (&mut subtask.0).await {
+                Ok(Ok(())) => return,
+                Ok(Err(error)) => error,
+                Err(join_error) if join_error.is_panic() => {
+                    get_panic_message(join_error.into_panic())
+                }
+                // Aborted along with the node task
+                Err(_cancelled) => return,
+            };
+
+            eprintln!("Node Task Error: {name}: {message}");
+            errors.add(NodeTaskError::new(&name, &message)).await;
+
+            if started_at.elapsed() >= MAX_RESTART_BACKOFF {
+                restarts_so_far = 0;
+            }
+
+            sleep(get_restart_backoff(restarts_so_far)).await;
+            restarts_so_far += 1;
+        }
+    })
+}
+
+fn get_restart_backoff(restarts_so_far: u32) -> Duration {
+    2u32.checked_pow(restarts_so_far)
+        .map(|factor| min(Duration::from_secs(1) * factor, MAX_RESTART_BACKOFF))
+        .unwrap_or(MAX_RESTART_BACKOFF)
+}
+
+fn get_panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Unknown panic".to_string(),
+        },
+    }
+}
+
+/// Aborting the supervisor drops the running subtask's handle,
+/// which has to abort the subtask too
+struct AbortOnDrop(JoinHandle<Result<(), String>>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod node_task_supervisor_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn doubles_restart_backoff_up_to_the_max() {
+        assert_eq!(get_restart_backoff(0), Duration::from_secs(1));
+        assert_eq!(get_restart_backoff(4), Duration::from_secs(16));
+        assert_eq!(get_restart_backoff(6), MAX_RESTART_BACKOFF);
+        assert_eq!(get_restart_backoff(u32::MAX), MAX_RESTART_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn restarts_panicked_subtasks_and_records_their_errors() {
+        let runs_count = Arc::new(AtomicUsize::new(0));
+        let errors = NodeTaskErrors::new();
+
+        let supervisor = supervise("ingester", &errors, {
+            let runs_count = runs_count.clone();
+
+            move || {
+                let runs_count = runs_count.clone();
+
+                async move {
+                    if runs_count.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("Repo Error: connection closed");
+                    }
+
+                    Ok(())
+                }
+                .boxed()
+            }
+        });
+        supervisor.await.unwrap();
+
+        assert_eq!(runs_count.load(Ordering::SeqCst), 2);
+
+        let errors = errors.get_all().await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].subtask, "ingester");
+        assert_eq!(errors[0].message, "Repo Error: connection closed");
+    }
+
+    #[tokio::test]
+    async fn records_errors_of_failed_subtasks() {
+        let errors = NodeTaskErrors::new();
+        let runs_count = Arc::new(AtomicUsize::new(0));
+
+        let supervisor = supervise("handlers", &errors, {
+            let runs_count = runs_count.clone();
+
+            move || {
+                let runs_count = runs_count.clone();
+
+                async move {
+                    match runs_count.fetch_add(1, Ordering::SeqCst) {
+                        0 => Err("Provider Error: invalid API key".to_string()),
+                        _ => Ok(()),
+                    }
+                }
+                .boxed()
+            }
+        });
+        supervisor.await.unwrap();
+
+        let errors = errors.get_all().await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Provider Error: invalid API key");
+    }
+}
@@ -5,7 +5,7 @@ use std::fmt::Debug;
 
 use super::node::{self, Node};
 use super::node_tasks_runner::NodeTasksRunner;
-use super::NodeTask;
+use super::{NodeTask, NodeTaskErrors};
 
 #[derive(PartialEq, Debug)]
 enum NodeTasksState {
@@ -28,18 +28,18 @@ pub struct NodeTasks<'a> {
     state: NodeTasksState,
     tasks: Vec<NodeTask>,
     started_at_in_secs: u64,
-    /// Not used currently. In V2, We will populate NodeTasksErrors here
-    pub errors: Vec<String>,
+    /// Errors of the tasks' failed subtasks, which get restarted
+    pub errors: NodeTaskErrors,
 }
 
 impl<'a> NodeTasks<'a> {
-    pub fn new(current_node: &'a Node) -> Self {
+    pub fn new(current_node: &'a Node, errors: &NodeTaskErrors) -> Self {
         Self {
             current_node,
             state: NodeTasksState::Idle,
             started_at_in_secs: Self::now_in_secs(),
             tasks: vec![],
-            errors: vec![],
+            errors: errors.clone(),
         }
     }
 
@@ -82,6 +82,14 @@ impl<'a> NodeTasks<'a> {
         } else if self.state == NodeTasksState::Active {
             self.abort().await;
         }
+
+        self.collect_errors().await;
+    }
+
+    async fn collect_errors(&mut self) {
+        for task in &self.tasks {
+            self.errors.extend(task.take_errors().await).await;
+        }
     }
 
     async fn make_active(&mut self, tasks_runner: &impl NodeTasksRunner) {
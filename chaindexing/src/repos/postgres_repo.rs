@@ -1,14 +1,19 @@
 mod migrations;
 mod raw_queries;
 
+use crate::blocks::{Block, BlockHash};
+use crate::call_traces::CallTrace;
 use crate::chain_reorg::UnsavedReorgedBlock;
 
-use crate::{contracts::ContractAddress, events::Event, nodes::Node};
+use crate::contracts::{ContractAddress, ContractAddressStatus, UnsavedContractAddress};
+use crate::events::{Event, QuarantinedLog};
+use crate::{nodes::Node, transactions::Transaction};
 use diesel_async::RunQueryDsl;
 
 use diesel::{
     delete,
     result::{DatabaseErrorKind, Error as DieselError},
+    upsert::excluded,
     ExpressionMethods, QueryDsl,
 };
 use diesel_async::{pooled_connection::AsyncDieselConnectionManager, AsyncPgConnection};
@@ -85,8 +90,10 @@ impl Repo for PostgresRepo {
     async fn create_events<'a>(conn: &mut Conn<'a>, events: &[Event]) {
         use crate::diesel::schema::chaindexing_events::dsl::*;
 
+        // Events are identified by their logs, so ingesting them again changes nothing
         diesel::insert_into(chaindexing_events)
             .values(events)
+            .on_conflict_do_nothing()
             .execute(conn)
             .await
             .unwrap();
@@ -117,6 +124,104 @@ impl Repo for PostgresRepo {
         delete(chaindexing_events).filter(id.eq_any(ids)).execute(conn).await.unwrap();
     }
 
+    async fn create_quarantined_logs<'a>(conn: &mut Conn<'a>, quarantined_logs: &[QuarantinedLog]) {
+        use crate::diesel::schema::chaindexing_quarantined_logs::dsl::*;
+
+        // Like events, quarantined logs are identified by their logs
+        diesel::insert_into(chaindexing_quarantined_logs)
+            .values(quarantined_logs)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn get_all_quarantined_logs<'a>(conn: &mut Conn<'a>) -> Vec<QuarantinedLog> {
+        use crate::diesel::schema::chaindexing_quarantined_logs::dsl::*;
+
+        chaindexing_quarantined_logs.load(conn).await.unwrap()
+    }
+
+    async fn create_transactions<'a>(conn: &mut Conn<'a>, transactions: &[Transaction]) {
+        use crate::diesel::schema::chaindexing_transactions::dsl::*;
+
+        // Blocks can get ingested again, e.g. when their events were already ingested
+        diesel::insert_into(chaindexing_transactions)
+            .values(transactions)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn get_all_transactions<'a>(conn: &mut Conn<'a>) -> Vec<Transaction> {
+        use crate::diesel::schema::chaindexing_transactions::dsl::*;
+
+        chaindexing_transactions.load(conn).await.unwrap()
+    }
+    async fn get_transactions<'a>(
+        conn: &mut Self::Conn<'a>,
+        address: String,
+        from: u64,
+        to: u64,
+    ) -> Vec<Transaction> {
+        use crate::diesel::schema::chaindexing_transactions::dsl::*;
+
+        chaindexing_transactions
+            .filter(contract_address.eq(address.to_lowercase()))
+            .filter(block_number.between(from as i64, to as i64))
+            .load(conn)
+            .await
+            .unwrap()
+    }
+    async fn delete_transactions_by_ids<'a>(conn: &mut Self::Conn<'a>, ids: &[Uuid]) {
+        use crate::diesel::schema::chaindexing_transactions::dsl::*;
+
+        delete(chaindexing_transactions)
+            .filter(id.eq_any(ids))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn create_call_traces<'a>(conn: &mut Conn<'a>, call_traces: &[CallTrace]) {
+        use crate::diesel::schema::chaindexing_call_traces::dsl::*;
+
+        diesel::insert_into(chaindexing_call_traces)
+            .values(call_traces)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn get_all_call_traces<'a>(conn: &mut Conn<'a>) -> Vec<CallTrace> {
+        use crate::diesel::schema::chaindexing_call_traces::dsl::*;
+
+        chaindexing_call_traces.load(conn).await.unwrap()
+    }
+    async fn get_call_traces<'a>(
+        conn: &mut Self::Conn<'a>,
+        address: String,
+        from: u64,
+        to: u64,
+    ) -> Vec<CallTrace> {
+        use crate::diesel::schema::chaindexing_call_traces::dsl::*;
+
+        chaindexing_call_traces
+            .filter(contract_address.eq(address.to_lowercase()))
+            .filter(block_number.between(from as i64, to as i64))
+            .load(conn)
+            .await
+            .unwrap()
+    }
+    async fn delete_call_traces_by_ids<'a>(conn: &mut Self::Conn<'a>, ids: &[Uuid]) {
+        use crate::diesel::schema::chaindexing_call_traces::dsl::*;
+
+        delete(chaindexing_call_traces)
+            .filter(id.eq_any(ids))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
     async fn update_next_block_number_to_ingest_from<'a>(
         conn: &mut Self::Conn<'a>,
         contract_address: &ContractAddress,
@@ -131,6 +236,56 @@ impl Repo for PostgresRepo {
             .await
             .unwrap();
     }
+    async fn create_spawned_contract_addresses<'a>(
+        conn: &mut Self::Conn<'a>,
+        contract_addresses: &[UnsavedContractAddress],
+    ) {
+        use crate::diesel::schema::chaindexing_contract_addresses::dsl::*;
+
+        if contract_addresses.is_empty() {
+            return;
+        }
+
+        let values: Vec<_> = contract_addresses
+            .iter()
+            .map(|contract_address| {
+                (
+                    address.eq(&contract_address.address),
+                    chain_id.eq(contract_address.chain_id),
+                    contract_name.eq(&contract_address.contract_name),
+                    start_block_number.eq(contract_address.start_block_number),
+                    next_block_number_to_ingest_from.eq(contract_address.start_block_number),
+                    next_block_number_to_handle_from.eq(contract_address.start_block_number),
+                )
+            })
+            .collect();
+
+        // Factories' events can get ingested again, e.g. when confirming them
+        diesel::insert_into(chaindexing_contract_addresses)
+            .values(values)
+            .on_conflict((chain_id, address))
+            .do_nothing()
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn complete_contract_addresses<'a>(conn: &mut Self::Conn<'a>, ids: &[i64]) {
+        use crate::diesel::schema::chaindexing_contract_addresses::dsl::*;
+
+        // Paused and excluded addresses keep their statuses
+        diesel::update(chaindexing_contract_addresses)
+            .filter(id.eq_any(ids))
+            .filter(status.eq(ContractAddressStatus::Active.as_str()))
+            .set(status.eq(ContractAddressStatus::Completed.as_str()))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn get_all_contract_addresses<'a>(conn: &mut Self::Conn<'a>) -> Vec<ContractAddress> {
+        use crate::diesel::schema::chaindexing_contract_addresses::dsl::*;
+
+        chaindexing_contract_addresses.load(conn).await.unwrap()
+    }
 
     async fn create_reorged_block<'a>(
         conn: &mut Self::Conn<'a>,
@@ -145,6 +300,155 @@ impl Repo for PostgresRepo {
             .unwrap();
     }
 
+    async fn get_blocks<'a>(
+        conn: &mut Self::Conn<'a>,
+        chain_id: i64,
+        block_numbers: &[i64],
+    ) -> Vec<Block> {
+        use crate::diesel::schema::chaindexing_blocks;
+
+        chaindexing_blocks::table
+            .filter(chaindexing_blocks::chain_id.eq(chain_id))
+            .filter(chaindexing_blocks::number.eq_any(block_numbers))
+            .load(conn)
+            .await
+            .unwrap()
+    }
+    async fn upsert_blocks<'a>(conn: &mut Self::Conn<'a>, blocks: &[Block]) {
+        use crate::diesel::schema::chaindexing_blocks::dsl::*;
+        use diesel::dsl::sql;
+        use diesel::sql_types::{Nullable, VarChar};
+
+        if blocks.is_empty() {
+            return;
+        }
+
+        diesel::insert_into(chaindexing_blocks)
+            .values(blocks)
+            .on_conflict((chain_id, number))
+            .do_update()
+            .set((
+                hash.eq(excluded(hash)),
+                // Blocks upserted with only their timestamps keep their known parents
+                parent_hash.eq(sql::<Nullable<VarChar>>(
+                    "COALESCE(excluded.parent_hash, chaindexing_blocks.parent_hash)",
+                )),
+                timestamp.eq(excluded(timestamp)),
+            ))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn get_block_hashes<'a>(conn: &mut Self::Conn<'a>, chain_id: i64) -> Vec<BlockHash> {
+        use crate::diesel::schema::chaindexing_block_hashes;
+
+        chaindexing_block_hashes::table
+            .filter(chaindexing_block_hashes::chain_id.eq(chain_id))
+            .order_by(chaindexing_block_hashes::number.asc())
+            .load(conn)
+            .await
+            .unwrap()
+    }
+    async fn upsert_block_hashes<'a>(conn: &mut Self::Conn<'a>, block_hashes: &[BlockHash]) {
+        use crate::diesel::schema::chaindexing_block_hashes::dsl::*;
+
+        if block_hashes.is_empty() {
+            return;
+        }
+
+        diesel::insert_into(chaindexing_block_hashes)
+            .values(block_hashes)
+            .on_conflict((chain_id, number))
+            .do_update()
+            .set((
+                hash.eq(excluded(hash)),
+                parent_hash.eq(excluded(parent_hash)),
+            ))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+    async fn prune_block_hashes<'a>(
+        conn: &mut Self::Conn<'a>,
+        chain_id_: i64,
+        min_block_number: i64,
+    ) {
+        use crate::diesel::schema::chaindexing_block_hashes::dsl::*;
+
+        delete(chaindexing_block_hashes)
+            .filter(chain_id.eq(chain_id_))
+            .filter(number.lt(min_block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn rewind_to_reorged_block<'a>(
+        conn: &mut Self::Conn<'a>,
+        &UnsavedReorgedBlock {
+            block_number,
+            chain_id,
+        }: &UnsavedReorgedBlock,
+    ) {
+        use crate::diesel::schema::{
+            chaindexing_block_hashes, chaindexing_blocks, chaindexing_call_traces,
+            chaindexing_events, chaindexing_quarantined_logs, chaindexing_transactions,
+        };
+
+        delete(chaindexing_events::table)
+            .filter(chaindexing_events::chain_id.eq(chain_id))
+            .filter(chaindexing_events::block_number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        delete(chaindexing_quarantined_logs::table)
+            .filter(chaindexing_quarantined_logs::chain_id.eq(chain_id))
+            .filter(chaindexing_quarantined_logs::block_number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        delete(chaindexing_transactions::table)
+            .filter(chaindexing_transactions::chain_id.eq(chain_id))
+            .filter(chaindexing_transactions::block_number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        delete(chaindexing_call_traces::table)
+            .filter(chaindexing_call_traces::chain_id.eq(chain_id))
+            .filter(chaindexing_call_traces::block_number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        delete(chaindexing_blocks::table)
+            .filter(chaindexing_blocks::chain_id.eq(chain_id))
+            .filter(chaindexing_blocks::number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        delete(chaindexing_block_hashes::table)
+            .filter(chaindexing_block_hashes::chain_id.eq(chain_id))
+            .filter(chaindexing_block_hashes::number.ge(block_number))
+            .execute(conn)
+            .await
+            .unwrap();
+
+        diesel::sql_query(format!(
+            "UPDATE chaindexing_contract_addresses
+            SET next_block_number_to_ingest_from =
+                GREATEST(start_block_number, LEAST(next_block_number_to_ingest_from, {block_number}))
+            WHERE chain_id = {chain_id}"
+        ))
+        .execute(conn)
+        .await
+        .unwrap();
+    }
+
     async fn get_active_nodes<'a>(
         conn: &mut Self::Conn<'a>,
         node_election_rate_ms: u64,
@@ -11,6 +11,9 @@ impl RepoMigrations for PostgresRepo {
     fn restart_ingest_and_handlers_next_block_numbers_migration() -> &'static [&'static str] {
         SQLikeMigrations::restart_ingest_and_handlers_next_block_numbers()
     }
+    fn reactivate_completed_contract_addresses_migration() -> &'static [&'static str] {
+        SQLikeMigrations::reactivate_completed_contract_addresses()
+    }
     fn zero_next_block_number_for_side_effects_migration() -> &'static [&'static str] {
         SQLikeMigrations::zero_next_block_number_for_side_effects()
     }
@@ -22,6 +25,20 @@ impl RepoMigrations for PostgresRepo {
         SQLikeMigrations::drop_events()
     }
 
+    fn create_transactions_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_transactions()
+    }
+    fn drop_transactions_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_transactions()
+    }
+
+    fn create_call_traces_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_call_traces()
+    }
+    fn drop_call_traces_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_call_traces()
+    }
+
     fn create_reorged_blocks_migration() -> &'static [&'static str] {
         SQLikeMigrations::create_reorged_blocks()
     }
@@ -29,6 +46,34 @@ impl RepoMigrations for PostgresRepo {
         SQLikeMigrations::drop_reorged_blocks()
     }
 
+    fn create_blocks_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_blocks()
+    }
+    fn drop_blocks_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_blocks()
+    }
+
+    fn create_block_handler_cursors_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_block_handler_cursors()
+    }
+    fn drop_block_handler_cursors_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_block_handler_cursors()
+    }
+
+    fn create_block_hashes_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_block_hashes()
+    }
+    fn drop_block_hashes_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_block_hashes()
+    }
+
+    fn create_quarantined_logs_migration() -> &'static [&'static str] {
+        SQLikeMigrations::create_quarantined_logs()
+    }
+    fn drop_quarantined_logs_migration() -> &'static [&'static str] {
+        SQLikeMigrations::drop_quarantined_logs()
+    }
+
     fn create_root_states_migration() -> &'static [&'static str] {
         SQLikeMigrations::create_root_states()
     }
@@ -1,9 +1,13 @@
 use tokio_postgres::{types::ToSql, Client, NoTls, Transaction};
 
+use crate::blocks::{Block, BlockHandlerRange};
+use crate::call_traces::CallTrace;
 use crate::chain_reorg::ReorgedBlock;
+use crate::contracts::ContractAddressStatus;
 use crate::events::PartialEvent;
 use crate::nodes::Node;
-use crate::{root, Event, UnsavedContractAddress};
+use crate::transactions::Transaction as ChaindexingTransaction;
+use crate::{root, Address, Event, UnsavedContractAddress};
 use crate::{ExecutesWithRawQuery, HasRawQueryClient, LoadsDataWithRawQuery, PostgresRepo};
 use serde::de::DeserializeOwned;
 
@@ -53,9 +57,12 @@ impl ExecutesWithRawQuery for PostgresRepo {
                      chain_id,
                      contract_name,
                      start_block_number,
+                     end_block_number,
                      ..
                  }| {
-                    format!("('{address}', {chain_id}, '{contract_name}', {start_block_number}, {start_block_number}, {start_block_number})")
+                    let end_block_number = to_nullable_number(end_block_number);
+
+                    format!("('{address}', {chain_id}, '{contract_name}', {start_block_number}, {start_block_number}, {start_block_number}, {end_block_number})")
                 },
             )
             .collect::<Vec<_>>()
@@ -63,7 +70,7 @@ impl ExecutesWithRawQuery for PostgresRepo {
 
         let query = format!("
             INSERT INTO chaindexing_contract_addresses 
-            (address, chain_id, contract_name, next_block_number_to_handle_from, next_block_number_to_ingest_from, start_block_number)
+            (address, chain_id, contract_name, next_block_number_to_handle_from, next_block_number_to_ingest_from, start_block_number, end_block_number)
             VALUES {contract_addresses_values}
             ON CONFLICT (chain_id, address)
             DO NOTHING
@@ -80,11 +87,12 @@ impl ExecutesWithRawQuery for PostgresRepo {
         let contract_name = &contract_address.contract_name;
         let chain_id = contract_address.chain_id;
         let start_block_number = contract_address.start_block_number;
+        let end_block_number = to_nullable_number(&contract_address.end_block_number);
 
         let query = format!(
             "INSERT INTO chaindexing_contract_addresses 
-            (address, chain_id, contract_name, next_block_number_to_handle_from, next_block_number_to_ingest_from, start_block_number)
-            VALUES ('{address}', {chain_id}, '{contract_name}', {start_block_number}, {start_block_number}, {start_block_number})
+            (address, chain_id, contract_name, next_block_number_to_handle_from, next_block_number_to_ingest_from, start_block_number, end_block_number)
+            VALUES ('{address}', {chain_id}, '{contract_name}', {start_block_number}, {start_block_number}, {start_block_number}, {end_block_number})
             ON CONFLICT (chain_id, address)
             DO NOTHING"
         );
@@ -92,6 +100,27 @@ impl ExecutesWithRawQuery for PostgresRepo {
         Self::execute_in_txn(client, &query).await;
     }
 
+    async fn update_contract_address_status(
+        client: &Self::RawQueryClient,
+        address: &Address,
+        chain_id: u64,
+        status: ContractAddressStatus,
+    ) {
+        let query = get_update_contract_address_status_query(address, chain_id, status);
+
+        Self::execute(client, &query).await;
+    }
+    async fn update_contract_address_status_in_txn<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        address: &Address,
+        chain_id: u64,
+        status: ContractAddressStatus,
+    ) {
+        let query = get_update_contract_address_status_query(address, chain_id, status);
+
+        Self::execute_in_txn(client, &query).await;
+    }
+
     async fn update_next_block_number_to_handle_from<'a>(
         client: &Self::RawQueryTxnClient<'a>,
         address: &str,
@@ -136,6 +165,35 @@ impl ExecutesWithRawQuery for PostgresRepo {
         Self::execute_in_txn(client, &query).await;
     }
 
+    async fn update_next_block_number_for_block_handlers<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        chain_id: u64,
+        block_number: u64,
+    ) {
+        let query = format!(
+            "INSERT INTO chaindexing_block_handler_cursors (chain_id, next_block_number)
+        VALUES ({chain_id}, {block_number})
+        ON CONFLICT (chain_id)
+        DO UPDATE SET next_block_number = excluded.next_block_number"
+        );
+
+        Self::execute_in_txn(client, &query).await;
+    }
+
+    async fn rewind_next_block_number_for_block_handlers<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        chain_id: u64,
+        block_number: u64,
+    ) {
+        let query = format!(
+            "UPDATE chaindexing_block_handler_cursors
+        SET next_block_number = LEAST(next_block_number, {block_number})
+        WHERE chain_id = {chain_id}"
+        );
+
+        Self::execute_in_txn(client, &query).await;
+    }
+
     async fn update_reorged_blocks_as_handled<'a>(
         client: &Self::RawQueryTxnClient<'a>,
         reorged_block_ids: &[i32],
@@ -175,6 +233,47 @@ impl ExecutesWithRawQuery for PostgresRepo {
         Self::execute(client, &query).await;
     }
 
+    async fn prune_transactions(
+        client: &Self::RawQueryClient,
+        min_block_number: u64,
+        chain_id: u64,
+    ) {
+        let query = format!(
+            "DELETE FROM chaindexing_transactions
+            WHERE block_number < {min_block_number}
+            AND chain_id = {chain_id}
+            "
+        );
+
+        Self::execute(client, &query).await;
+    }
+
+    async fn prune_call_traces(
+        client: &Self::RawQueryClient,
+        min_block_number: u64,
+        chain_id: u64,
+    ) {
+        let query = format!(
+            "DELETE FROM chaindexing_call_traces
+            WHERE block_number < {min_block_number}
+            AND chain_id = {chain_id}
+            "
+        );
+
+        Self::execute(client, &query).await;
+    }
+
+    async fn prune_blocks(client: &Self::RawQueryClient, min_block_number: u64, chain_id: u64) {
+        let query = format!(
+            "DELETE FROM chaindexing_blocks
+            WHERE number < {min_block_number}
+            AND chain_id = {chain_id}
+            "
+        );
+
+        Self::execute(client, &query).await;
+    }
+
     async fn prune_nodes(client: &Self::RawQueryClient, retain_size: u16) {
         let query = format!(
             "DELETE FROM chaindexing_nodes
@@ -245,6 +344,42 @@ impl LoadsDataWithRawQuery for PostgresRepo {
         Self::load_data_list(client, &query).await
     }
 
+    async fn load_transactions(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        contract_address: &str,
+        from_block_number: u64,
+        limit: u64,
+    ) -> Vec<ChaindexingTransaction> {
+        let query = format!(
+            "SELECT * from chaindexing_transactions
+            WHERE chain_id = {chain_id} AND contract_address= '{contract_address}'
+            AND block_number >= {from_block_number}
+            ORDER BY block_number ASC, transaction_index ASC
+            LIMIT {limit}",
+        );
+
+        Self::load_data_list(client, &query).await
+    }
+
+    async fn load_call_traces(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        contract_address: &str,
+        from_block_number: u64,
+        limit: u64,
+    ) -> Vec<CallTrace> {
+        let query = format!(
+            "SELECT * from chaindexing_call_traces
+            WHERE chain_id = {chain_id} AND contract_address= '{contract_address}'
+            AND block_number >= {from_block_number}
+            ORDER BY block_number ASC, transaction_index ASC
+            LIMIT {limit}",
+        );
+
+        Self::load_data_list(client, &query).await
+    }
+
     async fn load_latest_events(
         client: &Self::RawQueryClient,
         addresses: &[String],
@@ -278,6 +413,39 @@ impl LoadsDataWithRawQuery for PostgresRepo {
         .await
     }
 
+    async fn load_block_handler_range(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+    ) -> BlockHandlerRange {
+        let query = format!(
+            "SELECT
+                (SELECT next_block_number FROM chaindexing_block_handler_cursors
+                WHERE chain_id = {chain_id}) AS next_block_number,
+                MIN(start_block_number) AS start_block_number,
+                MIN(next_block_number_to_handle_from) AS next_block_number_to_handle_from
+            FROM chaindexing_contract_addresses
+            WHERE chain_id = {chain_id}",
+        );
+
+        Self::load_data(client, &query).await.unwrap()
+    }
+
+    async fn load_blocks(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        from_block_number: u64,
+        to_block_number: u64,
+    ) -> Vec<Block> {
+        let query = format!(
+            "SELECT * from chaindexing_blocks
+            WHERE chain_id = {chain_id}
+            AND number >= {from_block_number} AND number < {to_block_number}
+            ORDER BY number ASC",
+        );
+
+        Self::load_data_list(client, &query).await
+    }
+
     async fn load_data<Data: Send + DeserializeOwned>(
         client: &Self::RawQueryClient,
         query: &str,
@@ -339,10 +507,34 @@ async fn get_json_aggregate_in_txn<'a>(
     rows.first().unwrap().get(0)
 }
 
+fn get_update_contract_address_status_query(
+    address: &Address,
+    chain_id: u64,
+    status: ContractAddressStatus,
+) -> String {
+    let switchable_statuses: Vec<_> = status
+        .get_switchable_statuses()
+        .iter()
+        .map(|s| s.as_str().to_string())
+        .collect();
+
+    format!(
+        "UPDATE chaindexing_contract_addresses
+        SET status = '{status}'
+        WHERE chain_id = {chain_id} AND address = '{address:?}' AND status IN ({switchable_statuses})",
+        status = status.as_str(),
+        switchable_statuses = join_strings_with_comma(&switchable_statuses),
+    )
+}
+
 fn json_aggregate_query(query: &str) -> String {
     format!("WITH result AS ({query}) SELECT COALESCE(json_agg(result), '[]'::json) FROM result",)
 }
 
+fn to_nullable_number(number: &Option<i64>) -> String {
+    number.map(|n| n.to_string()).unwrap_or("NULL".to_string())
+}
+
 fn join_numbers_with_comma(numbers: &[impl ToString]) -> String {
     numbers.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(",")
 }
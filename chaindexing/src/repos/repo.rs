@@ -5,13 +5,16 @@ use uuid::Uuid;
 use futures_core::future::BoxFuture;
 use serde::de::DeserializeOwned;
 
+use crate::blocks::{Block, BlockHandlerRange, BlockHash};
 use crate::chain_reorg::{ReorgedBlock, UnsavedReorgedBlock};
 use crate::root;
 use crate::{
-    contracts::UnsavedContractAddress,
-    events::{Event, PartialEvent},
+    call_traces::CallTrace,
+    contracts::{ContractAddressStatus, UnsavedContractAddress},
+    events::{Event, PartialEvent, QuarantinedLog},
     nodes::Node,
-    ContractAddress,
+    transactions::Transaction,
+    Address, ContractAddress,
 };
 
 /// Errors from interacting the configured SQL database
@@ -51,17 +54,71 @@ pub trait Repo:
     ) -> Vec<Event>;
     async fn delete_events_by_ids<'a>(conn: &mut Self::Conn<'a>, ids: &[Uuid]);
 
+    async fn create_quarantined_logs<'a>(
+        conn: &mut Self::Conn<'a>,
+        quarantined_logs: &[QuarantinedLog],
+    );
+    async fn get_all_quarantined_logs<'a>(conn: &mut Self::Conn<'a>) -> Vec<QuarantinedLog>;
+
+    async fn create_transactions<'a>(conn: &mut Self::Conn<'a>, transactions: &[Transaction]);
+    async fn get_all_transactions<'a>(conn: &mut Self::Conn<'a>) -> Vec<Transaction>;
+    async fn get_transactions<'a>(
+        conn: &mut Self::Conn<'a>,
+        address: String,
+        from: u64,
+        to: u64,
+    ) -> Vec<Transaction>;
+    async fn delete_transactions_by_ids<'a>(conn: &mut Self::Conn<'a>, ids: &[Uuid]);
+
+    async fn create_call_traces<'a>(conn: &mut Self::Conn<'a>, call_traces: &[CallTrace]);
+    async fn get_all_call_traces<'a>(conn: &mut Self::Conn<'a>) -> Vec<CallTrace>;
+    async fn get_call_traces<'a>(
+        conn: &mut Self::Conn<'a>,
+        address: String,
+        from: u64,
+        to: u64,
+    ) -> Vec<CallTrace>;
+    async fn delete_call_traces_by_ids<'a>(conn: &mut Self::Conn<'a>, ids: &[Uuid]);
+
     async fn update_next_block_number_to_ingest_from<'a>(
         conn: &mut Self::Conn<'a>,
         contract_address: &ContractAddress,
         block_number: i64,
     );
+    async fn create_spawned_contract_addresses<'a>(
+        conn: &mut Self::Conn<'a>,
+        contract_addresses: &[UnsavedContractAddress],
+    );
+    async fn complete_contract_addresses<'a>(conn: &mut Self::Conn<'a>, ids: &[i64]);
+    async fn get_all_contract_addresses<'a>(conn: &mut Self::Conn<'a>) -> Vec<ContractAddress>;
 
     async fn create_reorged_block<'a>(
         conn: &mut Self::Conn<'a>,
         reorged_block: &UnsavedReorgedBlock,
     );
 
+    async fn get_blocks<'a>(
+        conn: &mut Self::Conn<'a>,
+        chain_id: i64,
+        block_numbers: &[i64],
+    ) -> Vec<Block>;
+    async fn upsert_blocks<'a>(conn: &mut Self::Conn<'a>, blocks: &[Block]);
+
+    async fn get_block_hashes<'a>(conn: &mut Self::Conn<'a>, chain_id: i64) -> Vec<BlockHash>;
+    async fn upsert_block_hashes<'a>(conn: &mut Self::Conn<'a>, block_hashes: &[BlockHash]);
+    async fn prune_block_hashes<'a>(
+        conn: &mut Self::Conn<'a>,
+        chain_id: i64,
+        min_block_number: i64,
+    );
+    /// Deletes the events, transactions, call traces, blocks and block hashes
+    /// of the reorged block's chain from the reorged block onwards, and rewinds
+    /// the contract addresses to ingest them again
+    async fn rewind_to_reorged_block<'a>(
+        conn: &mut Self::Conn<'a>,
+        reorged_block: &UnsavedReorgedBlock,
+    );
+
     async fn get_active_nodes<'a>(
         conn: &mut Self::Conn<'a>,
         node_election_rate_ms: u64,
@@ -96,6 +153,19 @@ pub trait ExecutesWithRawQuery: HasRawQueryClient {
         contract_addresses: &[UnsavedContractAddress],
     );
 
+    async fn update_contract_address_status(
+        client: &Self::RawQueryClient,
+        address: &Address,
+        chain_id: u64,
+        status: ContractAddressStatus,
+    );
+    async fn update_contract_address_status_in_txn<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        address: &Address,
+        chain_id: u64,
+        status: ContractAddressStatus,
+    );
+
     async fn update_next_block_number_to_handle_from<'a>(
         client: &Self::RawQueryTxnClient<'a>,
         address: &str,
@@ -116,6 +186,18 @@ pub trait ExecutesWithRawQuery: HasRawQueryClient {
         block_number: u64,
     );
 
+    async fn update_next_block_number_for_block_handlers<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        chain_id: u64,
+        block_number: u64,
+    );
+
+    async fn rewind_next_block_number_for_block_handlers<'a>(
+        client: &Self::RawQueryTxnClient<'a>,
+        chain_id: u64,
+        block_number: u64,
+    );
+
     async fn update_reorged_blocks_as_handled<'a>(
         client: &Self::RawQueryTxnClient<'a>,
         reorged_block_ids: &[i32],
@@ -123,6 +205,13 @@ pub trait ExecutesWithRawQuery: HasRawQueryClient {
 
     async fn append_root_state(client: &Self::RawQueryClient, new_root_state: &root::State);
     async fn prune_events(client: &Self::RawQueryClient, min_block_number: u64, chain_id: u64);
+    async fn prune_transactions(
+        client: &Self::RawQueryClient,
+        min_block_number: u64,
+        chain_id: u64,
+    );
+    async fn prune_call_traces(client: &Self::RawQueryClient, min_block_number: u64, chain_id: u64);
+    async fn prune_blocks(client: &Self::RawQueryClient, min_block_number: u64, chain_id: u64);
     async fn prune_nodes(client: &Self::RawQueryClient, retain_size: u16);
     async fn prune_root_states(client: &Self::RawQueryClient, retain_size: u64);
 }
@@ -145,6 +234,34 @@ pub trait LoadsDataWithRawQuery: HasRawQueryClient {
         limit: u64,
     ) -> Vec<Event>;
 
+    async fn load_transactions(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        contract_address: &str,
+        from_block_number: u64,
+        limit: u64,
+    ) -> Vec<Transaction>;
+
+    async fn load_call_traces(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        contract_address: &str,
+        from_block_number: u64,
+        limit: u64,
+    ) -> Vec<CallTrace>;
+
+    async fn load_block_handler_range(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+    ) -> BlockHandlerRange;
+
+    async fn load_blocks(
+        client: &Self::RawQueryClient,
+        chain_id: u64,
+        from_block_number: u64,
+        to_block_number: u64,
+    ) -> Vec<Block>;
+
     async fn load_data<Data: Send + DeserializeOwned>(
         client: &Self::RawQueryClient,
         query: &str,
@@ -170,18 +287,43 @@ pub trait RepoMigrations: Migratable {
 
     fn create_contract_addresses_migration() -> &'static [&'static str];
     fn restart_ingest_and_handlers_next_block_numbers_migration() -> &'static [&'static str];
+    fn reactivate_completed_contract_addresses_migration() -> &'static [&'static str];
     fn zero_next_block_number_for_side_effects_migration() -> &'static [&'static str];
 
     fn create_events_migration() -> &'static [&'static str];
     fn drop_events_migration() -> &'static [&'static str];
 
+    fn create_transactions_migration() -> &'static [&'static str];
+    fn drop_transactions_migration() -> &'static [&'static str];
+
+    fn create_call_traces_migration() -> &'static [&'static str];
+    fn drop_call_traces_migration() -> &'static [&'static str];
+
     fn create_reorged_blocks_migration() -> &'static [&'static str];
     fn drop_reorged_blocks_migration() -> &'static [&'static str];
 
+    fn create_blocks_migration() -> &'static [&'static str];
+    fn drop_blocks_migration() -> &'static [&'static str];
+
+    fn create_block_handler_cursors_migration() -> &'static [&'static str];
+    fn drop_block_handler_cursors_migration() -> &'static [&'static str];
+
+    fn create_block_hashes_migration() -> &'static [&'static str];
+    fn drop_block_hashes_migration() -> &'static [&'static str];
+
+    fn create_quarantined_logs_migration() -> &'static [&'static str];
+    fn drop_quarantined_logs_migration() -> &'static [&'static str];
+
     fn get_internal_migrations() -> Vec<&'static str> {
         [
             Self::create_events_migration(),
+            Self::create_transactions_migration(),
+            Self::create_call_traces_migration(),
             Self::create_reorged_blocks_migration(),
+            Self::create_blocks_migration(),
+            Self::create_block_handler_cursors_migration(),
+            Self::create_block_hashes_migration(),
+            Self::create_quarantined_logs_migration(),
         ]
         .concat()
     }
@@ -189,8 +331,15 @@ pub trait RepoMigrations: Migratable {
     fn get_reset_internal_migrations() -> Vec<&'static str> {
         [
             Self::drop_events_migration(),
+            Self::drop_transactions_migration(),
+            Self::drop_call_traces_migration(),
             Self::drop_reorged_blocks_migration(),
+            Self::drop_blocks_migration(),
+            Self::drop_block_handler_cursors_migration(),
+            Self::drop_block_hashes_migration(),
+            Self::drop_quarantined_logs_migration(),
             Self::restart_ingest_and_handlers_next_block_numbers_migration(),
+            Self::reactivate_completed_contract_addresses_migration(),
         ]
         .concat()
     }
@@ -241,11 +390,22 @@ impl SQLikeMigrations {
         )",
             "CREATE UNIQUE INDEX IF NOT EXISTS chaindexing_contract_addresses_chain_address_index
         ON chaindexing_contract_addresses(chain_id, address)",
+            "ALTER TABLE chaindexing_contract_addresses
+        ADD COLUMN IF NOT EXISTS status VARCHAR NOT NULL DEFAULT 'active'",
+            "ALTER TABLE chaindexing_contract_addresses
+        ADD COLUMN IF NOT EXISTS end_block_number BIGINT",
         ]
     }
     pub fn restart_ingest_and_handlers_next_block_numbers() -> &'static [&'static str] {
-        &["UPDATE chaindexing_contract_addresses 
-           SET next_block_number_to_handle_from = start_block_number, next_block_number_to_ingest_from = start_block_number"]
+        &[
+            "UPDATE chaindexing_contract_addresses 
+           SET next_block_number_to_handle_from = start_block_number, next_block_number_to_ingest_from = start_block_number",
+        ]
+    }
+    /// Resets drop ingested events, so completed addresses get ingested again.
+    /// Paused and excluded addresses keep their statuses.
+    pub fn reactivate_completed_contract_addresses() -> &'static [&'static str] {
+        &["UPDATE chaindexing_contract_addresses SET status = 'active' WHERE status = 'completed'"]
     }
     pub fn zero_next_block_number_for_side_effects() -> &'static [&'static str] {
         &["UPDATE chaindexing_contract_addresses SET next_block_number_for_side_effects = 0"]
@@ -274,12 +434,91 @@ impl SQLikeMigrations {
             ON chaindexing_events(chain_id,contract_address,block_number,log_index)",
             "CREATE INDEX IF NOT EXISTS chaindexing_events_abi
             ON chaindexing_events(abi)",
+            // Events ingested more than once before the unique index existed keep their first id
+            "DO $$
+            BEGIN
+                IF NOT EXISTS (
+                    SELECT 1 FROM pg_indexes
+                    WHERE indexname = 'chaindexing_events_chain_block_transaction_log_index'
+                ) THEN
+                    DELETE FROM chaindexing_events duplicate_events
+                    USING chaindexing_events kept_events
+                    WHERE duplicate_events.chain_id = kept_events.chain_id
+                    AND duplicate_events.block_hash = kept_events.block_hash
+                    AND duplicate_events.transaction_hash = kept_events.transaction_hash
+                    AND duplicate_events.log_index = kept_events.log_index
+                    AND duplicate_events.id > kept_events.id;
+                END IF;
+            END $$",
+            "CREATE UNIQUE INDEX IF NOT EXISTS chaindexing_events_chain_block_transaction_log_index
+            ON chaindexing_events(chain_id,block_hash,transaction_hash,log_index)",
         ]
     }
     pub fn drop_events() -> &'static [&'static str] {
         &["DROP TABLE IF EXISTS chaindexing_events"]
     }
 
+    pub fn create_transactions() -> &'static [&'static str] {
+        &[
+            "CREATE TABLE IF NOT EXISTS chaindexing_transactions (
+                id uuid PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                contract_address VARCHAR NOT NULL,
+                contract_name VARCHAR NOT NULL,
+                abi TEXT NOT NULL,
+                parameters JSON NOT NULL,
+                from_address VARCHAR NOT NULL,
+                value VARCHAR NOT NULL,
+                gas_used BIGINT NOT NULL,
+                succeeded BOOLEAN,
+                block_hash VARCHAR NOT NULL,
+                block_number BIGINT NOT NULL,
+                block_timestamp BIGINT NOT NULL,
+                transaction_hash VARCHAR NOT NULL,
+                transaction_index INTEGER NOT NULL,
+                inserted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+            "CREATE UNIQUE INDEX IF NOT EXISTS chaindexing_transactions_chain_contract_hash
+            ON chaindexing_transactions(chain_id,contract_address,transaction_hash,block_hash)",
+            "CREATE INDEX IF NOT EXISTS chaindexing_transactions_chain_contract_block_index
+            ON chaindexing_transactions(chain_id,contract_address,block_number,transaction_index)",
+        ]
+    }
+    pub fn drop_transactions() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_transactions"]
+    }
+
+    pub fn create_call_traces() -> &'static [&'static str] {
+        &[
+            "CREATE TABLE IF NOT EXISTS chaindexing_call_traces (
+                id uuid PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                contract_address VARCHAR NOT NULL,
+                contract_name VARCHAR NOT NULL,
+                abi TEXT,
+                parameters JSON,
+                call_type VARCHAR NOT NULL,
+                from_address VARCHAR NOT NULL,
+                value VARCHAR NOT NULL,
+                input TEXT NOT NULL,
+                error TEXT,
+                trace_address VARCHAR NOT NULL,
+                block_hash VARCHAR NOT NULL,
+                block_number BIGINT NOT NULL,
+                transaction_hash VARCHAR NOT NULL,
+                transaction_index INTEGER NOT NULL,
+                inserted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+            "CREATE UNIQUE INDEX IF NOT EXISTS chaindexing_call_traces_chain_contract_hash_trace
+            ON chaindexing_call_traces(chain_id,contract_address,transaction_hash,trace_address,block_hash)",
+            "CREATE INDEX IF NOT EXISTS chaindexing_call_traces_chain_contract_block_index
+            ON chaindexing_call_traces(chain_id,contract_address,block_number,transaction_index)",
+        ]
+    }
+    pub fn drop_call_traces() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_call_traces"]
+    }
+
     pub fn create_reorged_blocks() -> &'static [&'static str] {
         &["CREATE TABLE IF NOT EXISTS chaindexing_reorged_blocks (
                 id SERIAL PRIMARY KEY,
@@ -292,4 +531,68 @@ impl SQLikeMigrations {
     pub fn drop_reorged_blocks() -> &'static [&'static str] {
         &["DROP TABLE IF EXISTS chaindexing_reorged_blocks"]
     }
+
+    pub fn create_blocks() -> &'static [&'static str] {
+        &["CREATE TABLE IF NOT EXISTS chaindexing_blocks (
+                chain_id BIGINT NOT NULL,
+                number BIGINT NOT NULL,
+                hash VARCHAR NOT NULL,
+                parent_hash VARCHAR,
+                timestamp BIGINT NOT NULL,
+                inserted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (chain_id, number)
+            )"]
+    }
+    pub fn drop_blocks() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_blocks"]
+    }
+
+    pub fn create_block_handler_cursors() -> &'static [&'static str] {
+        &[
+            "CREATE TABLE IF NOT EXISTS chaindexing_block_handler_cursors (
+                chain_id BIGINT PRIMARY KEY,
+                next_block_number BIGINT NOT NULL
+            )",
+        ]
+    }
+    pub fn drop_block_handler_cursors() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_block_handler_cursors"]
+    }
+
+    pub fn create_block_hashes() -> &'static [&'static str] {
+        &["CREATE TABLE IF NOT EXISTS chaindexing_block_hashes (
+                chain_id BIGINT NOT NULL,
+                number BIGINT NOT NULL,
+                hash VARCHAR NOT NULL,
+                parent_hash VARCHAR NOT NULL,
+                PRIMARY KEY (chain_id, number)
+            )"]
+    }
+    pub fn drop_block_hashes() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_block_hashes"]
+    }
+
+    pub fn create_quarantined_logs() -> &'static [&'static str] {
+        &[
+            "CREATE TABLE IF NOT EXISTS chaindexing_quarantined_logs (
+                id uuid PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                contract_address VARCHAR NOT NULL,
+                contract_name VARCHAR,
+                topics JSON NOT NULL,
+                data TEXT NOT NULL,
+                block_hash VARCHAR,
+                block_number BIGINT,
+                transaction_hash VARCHAR,
+                log_index INTEGER,
+                reason TEXT NOT NULL,
+                inserted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+            "CREATE INDEX IF NOT EXISTS chaindexing_quarantined_logs_chain_block
+            ON chaindexing_quarantined_logs(chain_id,block_number)",
+        ]
+    }
+    pub fn drop_quarantined_logs() -> &'static [&'static str] {
+        &["DROP TABLE IF EXISTS chaindexing_quarantined_logs"]
+    }
 }
@@ -14,6 +14,7 @@ use futures_util::FutureExt;
 use serde::Deserialize;
 use tokio::sync::Mutex;
 
+use crate::contracts::ContractAddressStatus;
 use crate::{ChaindexingRepo, ChaindexingRepoClient, ContractAddress, LoadsDataWithRawQuery};
 
 type DataStream = Vec<ContractAddress>;
@@ -31,6 +32,7 @@ pin_project!(
         from: Option<i64>,
         to: Option<i64>,
         chunk_size: i64,
+        statuses: Vec<ContractAddressStatus>,
         client: Arc<Mutex<ChaindexingRepoClient>>,
         state: ContractAddressesStreamState,
     }
@@ -43,6 +45,7 @@ impl ContractAddressesStream {
             from: None,
             to: None,
             chunk_size: 500,
+            statuses: vec![ContractAddressStatus::Active],
             client: client.clone(),
             state: ContractAddressesStreamState::GetFromAndTo,
         }
@@ -51,6 +54,11 @@ impl ContractAddressesStream {
         self.chunk_size = chunk_size;
         self
     }
+    /// Streams only active contract addresses by default
+    pub fn with_statuses(mut self, statuses: &[ContractAddressStatus]) -> Self {
+        self.statuses = statuses.to_vec();
+        self
+    }
 }
 
 impl Stream for ContractAddressesStream {
@@ -130,6 +138,12 @@ impl Stream for ContractAddressesStream {
                 let client = this.client.clone();
                 let from = *from;
                 let to = *to;
+                let statuses = this
+                    .statuses
+                    .iter()
+                    .map(|status| format!("'{}'", status.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(",");
 
                 if from > to {
                     Poll::Ready(None)
@@ -142,7 +156,8 @@ impl Stream for ContractAddressesStream {
                         let query = format!(
                             "
                         SELECT * FROM chaindexing_contract_addresses 
-                        WHERE chain_id = {chain_id_} AND id BETWEEN {from} AND {chunk_limit}
+                        WHERE chain_id = {chain_id_} AND id >= {from} AND id < {chunk_limit}
+                        AND status IN ({statuses})
                         "
                         );
 
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::diesel::schema::chaindexing_transactions;
+use diesel::{Insertable, Queryable};
+use ethers::abi::Token;
+use ethers::types::{
+    Address, Block, Transaction as EthersTransaction, TransactionReceipt, TxHash, U256, U64,
+};
+use uuid::Uuid;
+
+use crate::contracts::{self, ContractFunction, ContractFunctionKey, ContractFunctionSelector};
+use crate::{ChainId, Contract, ContractAddress, EventParam};
+
+use serde::Deserialize;
+
+/// Transactions sent to contract addresses, with their calldata decoded by
+/// the ABI of the function they call.
+#[derive(Debug, Deserialize, Clone, Eq, Queryable, Insertable)]
+#[diesel(table_name = chaindexing_transactions)]
+pub struct Transaction {
+    pub id: Uuid,
+    pub(crate) chain_id: i64,
+    pub contract_address: String,
+    pub contract_name: String,
+    pub abi: String,
+    parameters: serde_json::Value,
+    pub from_address: String,
+    value: String,
+    gas_used: i64,
+    succeeded: Option<bool>,
+    pub block_hash: String,
+    pub(crate) block_number: i64,
+    block_timestamp: i64,
+    pub transaction_hash: String,
+    pub(crate) transaction_index: i32,
+}
+
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.chain_id == other.chain_id
+            && self.contract_address == other.contract_address
+            && self.transaction_hash == other.transaction_hash
+            && self.block_hash == other.block_hash
+    }
+}
+
+impl Hash for Transaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chain_id.hash(state);
+        self.contract_address.hash(state);
+        self.transaction_hash.hash(state);
+        self.block_hash.hash(state);
+    }
+}
+
+impl Transaction {
+    pub fn new(
+        transaction: &EthersTransaction,
+        receipt: &TransactionReceipt,
+        function: &ContractFunction,
+        chain_id: &ChainId,
+        contract_name: &str,
+        block_timestamp: i64,
+    ) -> Self {
+        let params = function.value.decode_input(&transaction.input[4..]).unwrap();
+        let parameters: HashMap<_, _> = function
+            .value
+            .inputs
+            .iter()
+            .map(|input| input.name.to_string())
+            .zip(params)
+            .collect::<HashMap<String, Token>>();
+        let contract_address = format!("{:?}", transaction.to.unwrap());
+        let block_hash = format!("{:?}", transaction.block_hash.unwrap());
+        let transaction_hash = format!("{:?}", transaction.hash);
+
+        Self {
+            id: Self::get_id(chain_id, &contract_address, &block_hash, &transaction_hash),
+            chain_id: *chain_id as i64,
+            contract_address,
+            contract_name: contract_name.to_owned(),
+            abi: function.abi.clone(),
+            parameters: serde_json::to_value(parameters).unwrap(),
+            from_address: format!("{:?}", transaction.from),
+            value: transaction.value.to_string(),
+            gas_used: receipt.gas_used.unwrap_or_default().as_u64() as i64,
+            // Receipts have no status before Byzantium
+            succeeded: receipt.status.map(|status| status == U64::one()),
+            block_hash,
+            block_number: transaction.block_number.unwrap().as_u64() as i64,
+            block_timestamp,
+            transaction_hash,
+            transaction_index: transaction.transaction_index.unwrap().as_u32() as i32,
+        }
+    }
+
+    /// Derives the transaction's id like events', see `Event::get_id`, so the same
+    /// transaction always gets the same id, however many times it gets ingested
+    pub(crate) fn get_id(
+        chain_id: &ChainId,
+        contract_address: &str,
+        block_hash: &str,
+        transaction_hash: &str,
+    ) -> Uuid {
+        let name = format!(
+            "{}:{}:{}:{}",
+            *chain_id as u64, contract_address, block_hash, transaction_hash
+        );
+
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    }
+
+    pub(crate) fn get_abi(&self) -> &str {
+        self.abi.as_str()
+    }
+
+    /// Returns the transaction's block number
+    pub fn get_block_number(&self) -> u64 {
+        self.block_number as u64
+    }
+    /// Returns the transaction's block timestamp
+    pub fn get_block_timestamp(&self) -> u64 {
+        self.block_timestamp as u64
+    }
+    /// Returns the transaction's index in its block
+    pub fn get_transaction_index(&self) -> u32 {
+        self.transaction_index as u32
+    }
+
+    /// Returns the arguments the function was called with
+    pub fn get_params(&self) -> EventParam {
+        EventParam::new(&self.parameters)
+    }
+
+    /// Returns the transaction's sender
+    pub fn get_from(&self) -> Address {
+        self.from_address.parse().unwrap()
+    }
+    /// Returns the wei sent along with the transaction
+    pub fn get_value(&self) -> U256 {
+        U256::from_dec_str(&self.value).unwrap()
+    }
+    /// Returns the gas used by the transaction
+    pub fn get_gas_used(&self) -> u64 {
+        self.gas_used as u64
+    }
+    /// Returns whether the transaction succeeded.
+    /// Unknown for transactions before Byzantium.
+    pub fn is_successful(&self) -> Option<bool> {
+        self.succeeded
+    }
+
+    /// Returns the transaction's chain id
+    pub fn get_chain_id(&self) -> ChainId {
+        U64::from(self.chain_id).try_into().unwrap()
+    }
+}
+
+/// Returns the transactions calling a handled function of their contract addresses
+pub fn get_handled<S: Send + Sync + Clone>(
+    transactions: &[EthersTransaction],
+    contracts: &[Contract<S>],
+    contract_addresses: &[ContractAddress],
+    chain_id: &ChainId,
+) -> Vec<EthersTransaction> {
+    let functions_by_keys = contracts::group_functions_by_keys(contracts);
+    let contract_addresses_by_address =
+        ContractAddress::group_contract_addresses_by_address_and_chain_id(contract_addresses);
+
+    transactions
+        .iter()
+        .filter(|transaction| {
+            get_function(
+                transaction,
+                &functions_by_keys,
+                &contract_addresses_by_address,
+                chain_id,
+            )
+            // Calldata not matching the function's params, e.g. of fallbacks, fails decoding
+            .map(|function| function.value.decode_input(&transaction.input[4..]).is_ok())
+            .unwrap_or(false)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Decodes transactions returned by `get_handled`
+pub fn get<S: Send + Sync + Clone>(
+    transactions: &[EthersTransaction],
+    receipts_by_hash: &HashMap<TxHash, TransactionReceipt>,
+    contracts: &[Contract<S>],
+    contract_addresses: &[ContractAddress],
+    chain_id: &ChainId,
+    blocks_by_number: &HashMap<U64, Block<TxHash>>,
+) -> Vec<Transaction> {
+    let functions_by_keys = contracts::group_functions_by_keys(contracts);
+    let contract_addresses_by_address =
+        ContractAddress::group_contract_addresses_by_address_and_chain_id(contract_addresses);
+
+    transactions
+        .iter()
+        .map(|transaction| {
+            let contract_address = contract_addresses_by_address
+                .get(&(transaction.to.unwrap(), *chain_id))
+                .unwrap();
+            let block = blocks_by_number.get(&transaction.block_number.unwrap()).unwrap();
+
+            Transaction::new(
+                transaction,
+                receipts_by_hash.get(&transaction.hash).unwrap(),
+                get_function(
+                    transaction,
+                    &functions_by_keys,
+                    &contract_addresses_by_address,
+                    chain_id,
+                )
+                .unwrap(),
+                chain_id,
+                &contract_address.contract_name,
+                block.timestamp.as_u64() as i64,
+            )
+        })
+        .collect()
+}
+
+/// Returns the function of the transaction's contract its selector calls, if handled
+fn get_function<'a>(
+    transaction: &EthersTransaction,
+    functions_by_keys: &'a HashMap<ContractFunctionKey, ContractFunction>,
+    contract_addresses_by_address: &HashMap<(Address, ChainId), &ContractAddress>,
+    chain_id: &ChainId,
+) -> Option<&'a ContractFunction> {
+    let contract_address = contract_addresses_by_address.get(&(transaction.to?, *chain_id))?;
+    let selector = get_selector(transaction)?;
+
+    functions_by_keys.get(&(contract_address.contract_name.clone(), selector))
+}
+
+fn get_selector(transaction: &EthersTransaction) -> Option<ContractFunctionSelector> {
+    transaction.input.get(..4).map(|selector| selector.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod transaction_tests {
+    use ethers::abi::Tokenize;
+    use ethers::types::{Bytes, H256};
+
+    use super::*;
+    use crate::ContractAddressStatus;
+
+    const SET_APPROVAL_FOR_ALL_ABI: &str =
+        "function setApprovalForAll(address operator, bool approved)";
+
+    struct SetApprovalForAllHandler;
+
+    #[crate::augmenting_std::async_trait]
+    impl crate::TransactionHandler for SetApprovalForAllHandler {
+        fn abi(&self) -> &'static str {
+            SET_APPROVAL_FOR_ALL_ABI
+        }
+        async fn handle_transaction<'a>(&self, _context: crate::TransactionContext<'a>) {}
+    }
+
+    fn contract_address(contract_name: &str, address: Address) -> ContractAddress {
+        ContractAddress {
+            id: 1,
+            chain_id: ChainId::Mainnet as i64,
+            next_block_number_to_ingest_from: 0,
+            next_block_number_to_handle_from: 0,
+            next_block_number_for_side_effects: 0,
+            start_block_number: 0,
+            address: format!("{:?}", address),
+            contract_name: contract_name.to_string(),
+            status: ContractAddressStatus::Active,
+            end_block_number: None,
+        }
+    }
+
+    fn set_approval_for_all(operator: Address) -> EthersTransaction {
+        let function = ContractFunction::new(SET_APPROVAL_FOR_ALL_ABI);
+        let calldata = function.value.encode_input(&(operator, true).into_tokens()).unwrap();
+
+        EthersTransaction {
+            hash: H256::random(),
+            block_hash: Some(H256::random()),
+            block_number: Some(U64::from(17773490)),
+            transaction_index: Some(U64::from(3)),
+            from: Address::random(),
+            to: Some(Address::random()),
+            value: U256::from(10),
+            input: Bytes::from(calldata),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_calldata_of_functions() {
+        let operator = Address::random();
+        let transaction = set_approval_for_all(operator);
+        let receipt = TransactionReceipt {
+            gas_used: Some(U256::from(46_000)),
+            status: Some(U64::one()),
+            ..Default::default()
+        };
+
+        let transaction = Transaction::new(
+            &transaction,
+            &receipt,
+            &ContractFunction::new(SET_APPROVAL_FOR_ALL_ABI),
+            &ChainId::Mainnet,
+            "BoredApeYachtClub",
+            1690000000,
+        );
+
+        assert_eq!(transaction.get_params().get_address("operator"), operator);
+        assert_eq!(transaction.get_value(), U256::from(10));
+        assert_eq!(transaction.get_gas_used(), 46_000);
+        assert_eq!(transaction.is_successful(), Some(true));
+    }
+
+    #[test]
+    fn derives_ids_from_transactions() {
+        let transaction = set_approval_for_all(Address::random());
+        let receipt = TransactionReceipt::default();
+        let function = ContractFunction::new(SET_APPROVAL_FOR_ALL_ABI);
+        let get_transaction = |transaction: &EthersTransaction| {
+            Transaction::new(
+                transaction,
+                &receipt,
+                &function,
+                &ChainId::Mainnet,
+                "BoredApeYachtClub",
+                1690000000,
+            )
+        };
+
+        let reorged_transaction = EthersTransaction {
+            block_hash: Some(H256::random()),
+            ..transaction.clone()
+        };
+
+        assert_eq!(
+            get_transaction(&transaction).id,
+            get_transaction(&transaction).id
+        );
+        assert_ne!(
+            get_transaction(&transaction).id,
+            get_transaction(&reorged_transaction).id
+        );
+    }
+
+    #[test]
+    fn returns_only_transactions_calling_handled_functions() {
+        let contract = Contract::<()>::new("BoredApeYachtClub")
+            .add_transaction_handler(SetApprovalForAllHandler);
+
+        let handled_transaction = set_approval_for_all(Address::random());
+        let ether_transfer = EthersTransaction {
+            input: Bytes::new(),
+            ..handled_transaction.clone()
+        };
+        let contract_addresses = [contract_address(
+            "BoredApeYachtClub",
+            handled_transaction.to.unwrap(),
+        )];
+
+        assert_eq!(
+            get_handled(
+                &[handled_transaction.clone(), ether_transfer],
+                &[contract],
+                &contract_addresses,
+                &ChainId::Mainnet
+            ),
+            vec![handled_transaction]
+        );
+    }
+
+    #[test]
+    fn matches_selectors_with_functions_of_the_called_contracts() {
+        let contract = Contract::<()>::new("BoredApeYachtClub")
+            .add_transaction_handler(SetApprovalForAllHandler);
+
+        let handled_transaction = set_approval_for_all(Address::random());
+        let other_contract_transaction = set_approval_for_all(Address::random());
+        let contract_addresses = [
+            contract_address("BoredApeYachtClub", handled_transaction.to.unwrap()),
+            contract_address("Doodles", other_contract_transaction.to.unwrap()),
+        ];
+
+        assert_eq!(
+            get_handled(
+                &[handled_transaction.clone(), other_contract_transaction],
+                &[contract, Contract::new("Doodles")],
+                &contract_addresses,
+                &ChainId::Mainnet
+            ),
+            vec![handled_transaction]
+        );
+    }
+}